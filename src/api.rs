@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 use std::error::Error;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use scru128::Scru128Id;
 
 use base64::Engine;
 
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
 use tokio_util::io::ReaderStream;
@@ -18,19 +19,33 @@ use hyper::header::ACCEPT;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Method, Request, Response, StatusCode};
-use hyper_util::rt::TokioIo;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::server::graceful::GracefulShutdown;
+
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
 use crate::listener::Listener;
 use crate::nu;
-use crate::store::{self, FollowOption, Frame, ReadOptions, Store, TTL};
+use crate::store::{self, FollowOption, Frame, ReadOptions, Store, StoreError, TTL};
 
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
 type HTTPResult = Result<Response<BoxBody<Bytes, BoxError>>, BoxError>;
 
+/// How long in-flight connections get to finish once shutdown is requested.
+const SHUTDOWN_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long the store's gc worker gets to answer a health check.
+const HEALTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
 #[derive(Debug, PartialEq, Clone)]
 enum AcceptType {
     Ndjson,
     EventStream,
+    /// Each frame as CBOR, prefixed with its length as a big-endian u32.
+    Cbor,
+    /// Each frame as MessagePack, prefixed with its length as a big-endian u32.
+    Msgpack,
 }
 
 enum Routes {
@@ -42,6 +57,15 @@ enum Routes {
         topic: String,
         ttl: Option<TTL>,
         context_id: Scru128Id,
+        meta: Option<serde_json::Value>,
+        schema: Option<String>,
+    },
+    StreamAppendBatch {
+        topic: String,
+        ttl: Option<TTL>,
+        context_id: Scru128Id,
+        meta: Option<serde_json::Value>,
+        schema: Option<String>,
     },
     HeadGet {
         topic: String,
@@ -51,13 +75,34 @@ enum Routes {
     StreamItemGet(Scru128Id),
     StreamItemRemove(Scru128Id),
     CasGet(ssri::Integrity),
+    CasHead(ssri::Integrity),
     CasPost,
+    CasBatch,
     Import,
     Version,
+    Stats,
+    Health,
     NotFound,
     BadRequest(String),
 }
 
+/// Whether the request carries `Authorization: Bearer <token>`. The comparison doesn't stop at
+/// the first differing byte, so timing doesn't give the token away.
+fn is_authorized(headers: &hyper::HeaderMap, token: &str) -> bool {
+    let Some(provided) = headers
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.as_bytes().strip_prefix(b"Bearer "))
+    else {
+        return false;
+    };
+    provided.len() == token.len()
+        && provided
+            .iter()
+            .zip(token.as_bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
 fn match_route(
     method: &Method,
     path: &str,
@@ -71,21 +116,45 @@ fn match_route(
 
     match (method, path) {
         (&Method::GET, "/version") => Routes::Version,
+        (&Method::GET, "/stats") => Routes::Stats,
+        (&Method::GET, "/health") => Routes::Health,
 
         (&Method::GET, "/") => {
             let accept_type = match headers.get(ACCEPT) {
                 Some(accept) if accept == "text/event-stream" => AcceptType::EventStream,
+                Some(accept) if accept == "application/cbor" => AcceptType::Cbor,
+                Some(accept) if accept == "application/msgpack" => AcceptType::Msgpack,
                 _ => AcceptType::Ndjson,
             };
 
             let options = ReadOptions::from_query(query);
 
-            match options {
-                Ok(options) => Routes::StreamCat {
-                    accept_type,
-                    options,
+            match (options, params.get("after")) {
+                (Ok(options), None) => match last_event_id(&accept_type, headers) {
+                    // An explicit last-id wins over the one EventSource resends on reconnect
+                    Ok(last_event_id) => Routes::StreamCat {
+                        accept_type,
+                        options: ReadOptions {
+                            last_id: options.last_id.or(last_event_id.map(Into::into)),
+                            ..options
+                        },
+                    },
+                    Err(e) => Routes::BadRequest(e),
                 },
-                Err(e) => Routes::BadRequest(e.to_string()),
+                (Ok(options), Some(_)) if options.last_id.is_some() || options.cursor.is_some() => {
+                    Routes::BadRequest("after can't be used with last-id or cursor".into())
+                }
+                (Ok(options), Some(token)) => match decode_page_token(token) {
+                    Some(id) => Routes::StreamCat {
+                        accept_type,
+                        options: ReadOptions {
+                            last_id: Some(id.into()),
+                            ..options
+                        },
+                    },
+                    None => Routes::BadRequest(format!("Invalid after token: {}", token)),
+                },
+                (Err(e), _) => Routes::BadRequest(e.to_string()),
             }
         }
 
@@ -117,7 +186,18 @@ fn match_route(
             }
         }
 
+        // HEAD is supported for the cheap-to-check routes: CAS content and individual frames
+        (&Method::HEAD, p)
+            if p.starts_with("/cas/") || Scru128Id::from_str(p.trim_start_matches('/')).is_ok() =>
+        {
+            match match_route(&Method::GET, p, headers, query) {
+                Routes::CasGet(hash) => Routes::CasHead(hash),
+                route => route,
+            }
+        }
+
         (&Method::POST, "/cas") => Routes::CasPost,
+        (&Method::POST, "/cas/batch") => Routes::CasBatch,
         (&Method::POST, "/import") => Routes::Import,
 
         (&Method::GET, p) => match Scru128Id::from_str(p.trim_start_matches('/')) {
@@ -131,7 +211,10 @@ fn match_route(
         },
 
         (&Method::POST, path) if path.starts_with('/') => {
-            let topic = path.trim_start_matches('/').to_string();
+            let (topic, batch) = match path.strip_prefix("/batch/") {
+                Some(topic) => (topic.to_string(), true),
+                None => (path.trim_start_matches('/').to_string(), false),
+            };
             let context_id = match params.get("context") {
                 None => crate::store::ZERO_CONTEXT,
                 Some(ctx) => match ctx.parse() {
@@ -140,11 +223,26 @@ fn match_route(
                 },
             };
 
+            let meta = match parse_meta(headers, &params) {
+                Ok(meta) => meta,
+                Err(e) => return Routes::BadRequest(e),
+            };
+            let schema = params.get("schema").cloned();
+
             match TTL::from_query(query) {
+                Ok(ttl) if batch => Routes::StreamAppendBatch {
+                    topic,
+                    ttl: Some(ttl),
+                    context_id,
+                    meta,
+                    schema,
+                },
                 Ok(ttl) => Routes::StreamAppend {
                     topic,
                     ttl: Some(ttl),
                     context_id,
+                    meta,
+                    schema,
                 },
                 Err(e) => Routes::BadRequest(e.to_string()),
             }
@@ -154,19 +252,115 @@ fn match_route(
     }
 }
 
+/// Meta can be passed either as the Base64-encoded `xs-meta` header, or as plain JSON in the
+/// `meta` query parameter.
+fn parse_meta(
+    headers: &hyper::HeaderMap,
+    params: &HashMap<String, String>,
+) -> Result<Option<serde_json::Value>, String> {
+    let json_str = match (headers.get("xs-meta"), params.get("meta")) {
+        (None, None) => return Ok(None),
+        (Some(_), Some(_)) => {
+            return Err("meta can't be passed as both the xs-meta header and a query param".into())
+        }
+        (None, Some(meta)) => meta.clone(),
+        (Some(header), None) => {
+            let header = header
+                .to_str()
+                .map_err(|e| format!("xs-meta isn't valid ASCII: {}", e))?;
+            // First decode the Base64-encoded string
+            let decoded = base64::prelude::BASE64_STANDARD
+                .decode(header)
+                .map_err(|e| format!("xs-meta isn't valid Base64: {}", e))?;
+            // Then parse the decoded bytes as UTF-8 string
+            String::from_utf8(decoded).map_err(|e| format!("xs-meta isn't valid UTF-8: {}", e))?
+        }
+    };
+
+    // Finally parse the UTF-8 string as JSON
+    serde_json::from_str(&json_str)
+        .map(Some)
+        .map_err(|e| format!("meta isn't valid JSON: {}", e))
+}
+
+/// Handles a request, responding 408 if it isn't handled within `timeout`. Handlers have read
+/// the request's body by the time they respond, so this bounds how long it can take to send.
+///
+/// Each request gets a `request` span, with its method and path, and ends with an event
+/// recording the response's status and how long it took to respond, in milliseconds: at info
+/// level, or warn and error for client and server errors. A streamed response's body can go on
+/// well after that.
+async fn handle_within(
+    timeout: Option<std::time::Duration>,
+    store: Store,
+    engine: nu::Engine,
+    token: Option<Arc<String>>,
+    req: Request<hyper::body::Incoming>,
+) -> HTTPResult {
+    let span = tracing::info_span!(
+        "request",
+        method = %req.method(),
+        path = %req.uri().path()
+    );
+    let start = std::time::Instant::now();
+    let res = handle(store, engine, token, req).instrument(span.clone());
+    let res = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, res).await {
+            Ok(res) => res,
+            Err(_) => response_408(),
+        },
+        None => res.await,
+    };
+
+    let latency_ms = start.elapsed().as_millis() as u64;
+    let _span = span.enter();
+    match &res {
+        Ok(res) => {
+            let status = res.status().as_u16();
+            if res.status().is_server_error() {
+                tracing::error!(status, latency_ms, "response");
+            } else if res.status().is_client_error() {
+                tracing::warn!(status, latency_ms, "response");
+            } else {
+                tracing::info!(status, latency_ms, "response");
+            }
+        }
+        Err(e) => tracing::error!(latency_ms, error = %e, "response failed"),
+    }
+    res
+}
+
 async fn handle(
     mut store: Store,
     _engine: nu::Engine, // TODO: potentially vestigial, will .process come back?
+    token: Option<Arc<String>>,
     req: Request<hyper::body::Incoming>,
 ) -> HTTPResult {
+    if let Some(token) = token {
+        if !is_authorized(req.headers(), &token) {
+            return response_401();
+        }
+    }
+
     let method = req.method();
     let path = req.uri().path();
     let headers = req.headers().clone();
     let query = req.uri().query();
+    let is_head = method == Method::HEAD;
 
-    let res = match match_route(method, path, &headers, query) {
+    let route = match_route(method, path, &headers, query);
+    // CAS content keeps its strong ETag and byte ranges, so it's served as it's stored
+    let encoding = (method == Method::GET && !matches!(route, Routes::CasGet(_)))
+        .then(|| ContentEncoding::negotiate(&headers))
+        .flatten();
+
+    let res = match route {
         Routes::Version => handle_version().await,
 
+        Routes::Stats => handle_stats(&store).await,
+
+        Routes::Health => handle_health(store.wait_for_gc()).await,
+
         Routes::StreamCat {
             accept_type,
             options,
@@ -176,24 +370,41 @@ async fn handle(
             topic,
             ttl,
             context_id,
-        } => handle_stream_append(&mut store, req, topic, ttl, context_id).await,
+            meta,
+            schema,
+        } => handle_stream_append(&mut store, req, topic, ttl, context_id, meta, schema).await,
 
-        Routes::CasGet(hash) => {
-            let reader = store.cas_reader(hash).await?;
-            let stream = ReaderStream::new(reader);
+        Routes::StreamAppendBatch {
+            topic,
+            ttl,
+            context_id,
+            meta,
+            schema,
+        } => {
+            handle_stream_append_batch(
+                &mut store,
+                req.into_body(),
+                topic,
+                ttl,
+                context_id,
+                meta,
+                schema,
+            )
+            .await
+        }
 
-            let stream = stream.map(|frame| {
-                let frame = frame.unwrap();
-                Ok(hyper::body::Frame::data(frame))
-            });
+        Routes::CasGet(hash) => handle_cas_get(&store, hash, &headers).await,
 
-            let body = StreamBody::new(stream).boxed();
-            Ok(Response::new(body))
-        }
+        Routes::CasHead(hash) => handle_cas_head(&store, hash, &headers).await,
 
         Routes::CasPost => handle_cas_post(&mut store, req.into_body()).await,
 
-        Routes::StreamItemGet(id) => response_frame_or_404(store.get(&id)),
+        Routes::CasBatch => handle_cas_batch(&store, req.into_body()).await,
+
+        Routes::StreamItemGet(id) => match store.get(&id) {
+            Ok(frame) => response_frame_or_404(frame),
+            Err(e) => Err(e.into()),
+        },
 
         Routes::StreamItemRemove(id) => handle_stream_item_remove(&mut store, id).await,
 
@@ -209,16 +420,305 @@ async fn handle(
         Routes::BadRequest(msg) => response_400(msg),
     };
 
-    res.or_else(|e| response_500(e.to_string()))
+    let res = res.or_else(response_error)?;
+
+    if is_head {
+        return head_response(res).await;
+    }
+    match encoding {
+        Some(encoding) if res.status() == StatusCode::OK => Ok(compress_response(res, encoding)),
+        _ => Ok(res),
+    }
+}
+
+/// A compression a client can ask for with `Accept-Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ContentEncoding {
+    Gzip,
+    /// zlib-wrapped deflate, which is what HTTP means by `deflate`.
+    Deflate,
+}
+
+impl ContentEncoding {
+    /// The encoding to compress a response with: gzip if the request accepts it, otherwise
+    /// deflate if it accepts that. An encoding given `q=0` isn't accepted.
+    fn negotiate(headers: &hyper::HeaderMap) -> Option<ContentEncoding> {
+        let accepted: Vec<_> = headers
+            .get_all(hyper::header::ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|item| {
+                let mut parts = item.split(';').map(str::trim);
+                let coding = parts.next()?.to_ascii_lowercase();
+                let refused = parts.any(|param| {
+                    param.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0)
+                });
+                (!refused).then_some(coding)
+            })
+            .collect();
+        if accepted.iter().any(|coding| coding == "gzip") {
+            Some(ContentEncoding::Gzip)
+        } else if accepted.iter().any(|coding| coding == "deflate") {
+            Some(ContentEncoding::Deflate)
+        } else {
+            None
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+}
+
+enum Encoder {
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(encoding: ContentEncoding) -> Self {
+        let level = flate2::Compression::default();
+        match encoding {
+            ContentEncoding::Gzip => {
+                Encoder::Gzip(flate2::write::GzEncoder::new(Vec::new(), level))
+            }
+            ContentEncoding::Deflate => {
+                Encoder::Deflate(flate2::write::ZlibEncoder::new(Vec::new(), level))
+            }
+        }
+    }
+
+    /// Compresses `chunk`, returning everything it compresses to so far. The encoder is flushed,
+    /// so the client can decompress the chunk without waiting for the next.
+    fn compress(&mut self, chunk: &[u8]) -> std::io::Result<Bytes> {
+        use std::io::Write;
+        let out = match self {
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            Encoder::Deflate(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(std::mem::take(out)))
+    }
+
+    /// What's left to send once the body has ended.
+    fn finish(self) -> std::io::Result<Bytes> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.finish().map(Bytes::from),
+            Encoder::Deflate(encoder) => encoder.finish().map(Bytes::from),
+        }
+    }
+}
+
+/// Compresses a response's body as it streams, chunk by chunk, so a followed stream's frames
+/// aren't held back waiting for more to compress alongside them.
+fn compress_response(
+    res: Response<BoxBody<Bytes, BoxError>>,
+    encoding: ContentEncoding,
+) -> Response<BoxBody<Bytes, BoxError>> {
+    let (mut parts, body) = res.into_parts();
+    parts.headers.remove(hyper::header::CONTENT_LENGTH);
+    parts.headers.insert(
+        hyper::header::CONTENT_ENCODING,
+        hyper::header::HeaderValue::from_static(encoding.as_str()),
+    );
+    parts.headers.insert(
+        hyper::header::VARY,
+        hyper::header::HeaderValue::from_static("accept-encoding"),
+    );
+
+    let state = Some((body.into_data_stream(), Encoder::new(encoding)));
+    let stream = futures::stream::unfold(state, |state| async move {
+        let (mut data, mut encoder) = state?;
+        let (chunk, state) = match data.next().await {
+            Some(Ok(chunk)) => (encoder.compress(&chunk), Some((data, encoder))),
+            Some(Err(e)) => return Some((Err(e), None)),
+            None => (encoder.finish(), None),
+        };
+        Some((
+            chunk.map(hyper::body::Frame::data).map_err(BoxError::from),
+            state,
+        ))
+    });
+
+    Response::from_parts(parts, StreamBody::new(stream).boxed())
+}
+
+/// Strips the body from a GET response to answer a HEAD request, setting Content-Length to the
+/// size the body would have been, unless the response already has one.
+async fn head_response(res: Response<BoxBody<Bytes, BoxError>>) -> HTTPResult {
+    let (mut parts, mut body) = res.into_parts();
+    if parts.headers.contains_key(hyper::header::CONTENT_LENGTH) {
+        return Ok(Response::from_parts(parts, empty()));
+    }
+    let mut len = 0;
+    while let Some(frame) = body.frame().await {
+        if let Ok(data) = frame?.into_data() {
+            len += data.len();
+        }
+    }
+    parts
+        .headers
+        .insert(hyper::header::CONTENT_LENGTH, len.into());
+    Ok(Response::from_parts(parts, empty()))
+}
+
+/// CAS content never changes for a given hash, so the hash doubles as a strong ETag, and the
+/// content can be cached indefinitely.
+async fn handle_cas_get(
+    store: &Store,
+    hash: ssri::Integrity,
+    headers: &hyper::HeaderMap,
+) -> HTTPResult {
+    let etag = format!("\"{}\"", hash);
+    let mut reader = match store.cas_reader(hash).await {
+        Ok(reader) => reader,
+        Err(e) if e.is_not_found() => return response_404(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut builder = Response::builder()
+        .header(hyper::header::ETAG, &etag)
+        .header(
+            hyper::header::CACHE_CONTROL,
+            "public, max-age=31536000, immutable",
+        );
+
+    if if_none_match(headers, &etag) {
+        return Ok(builder.status(StatusCode::NOT_MODIFIED).body(empty())?);
+    }
+
+    // Sniff the start of the content for its type, then send it ahead of the rest
+    let mut head = Vec::with_capacity(SNIFF_LEN as usize);
+    (&mut reader).take(SNIFF_LEN).read_to_end(&mut head).await?;
+    builder = builder.header(hyper::header::CONTENT_TYPE, sniff_content_type(&head));
+
+    let stream = tokio_stream::once(Ok(Bytes::from(head))).chain(ReaderStream::new(reader));
+
+    // Content that turns out not to match its hash fails at the end, which aborts the response
+    let stream = stream.map(|frame| frame.map(hyper::body::Frame::data).map_err(Into::into));
+
+    let body = StreamBody::new(stream).boxed();
+    Ok(builder.body(body)?)
+}
+
+/// Answers a HEAD request for CAS content with the headers a GET would have, other than
+/// Content-Type, without reading the content: its size is looked up rather than counted.
+async fn handle_cas_head(
+    store: &Store,
+    hash: ssri::Integrity,
+    headers: &hyper::HeaderMap,
+) -> HTTPResult {
+    let etag = format!("\"{}\"", hash);
+    let Some(size) = store.cas_size(&hash).await? else {
+        return response_404();
+    };
+
+    let builder = Response::builder()
+        .header(hyper::header::ETAG, &etag)
+        .header(
+            hyper::header::CACHE_CONTROL,
+            "public, max-age=31536000, immutable",
+        );
+
+    if if_none_match(headers, &etag) {
+        return Ok(builder.status(StatusCode::NOT_MODIFIED).body(empty())?);
+    }
+
+    Ok(builder
+        .header(hyper::header::CONTENT_LENGTH, size)
+        .body(empty())?)
 }
 
+/// How many bytes of CAS content are looked at to guess its type.
+const SNIFF_LEN: u64 = 512;
+
+/// Guesses a Content-Type from the first bytes of some content: known binary formats by their
+/// magic numbers, then JSON and plain text, falling back to `application/octet-stream`.
+fn sniff_content_type(head: &[u8]) -> &'static str {
+    if let Some(kind) = infer::get(head) {
+        return kind.mime_type();
+    }
+
+    // The sample may end partway through a multi-byte character
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).unwrap(),
+        Err(_) => return "application/octet-stream",
+    };
+
+    if text.contains('\0') {
+        return "application/octet-stream";
+    }
+
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        "application/json"
+    } else {
+        "text/plain; charset=utf-8"
+    }
+}
+
+/// Whether the request's `If-None-Match` header lists `etag`, or is `*`.
+fn if_none_match(headers: &hyper::HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(hyper::header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Prefixes an encoded frame with its length, so binary frames can be split back out of the
+/// stream.
+fn length_delimited(encoded: Vec<u8>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(4 + encoded.len());
+    bytes.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+    bytes.extend(encoded);
+    bytes
+}
+
+#[tracing::instrument(skip_all, fields(options = %options.to_query_string()))]
 async fn handle_stream_cat(
     store: &mut Store,
     options: ReadOptions,
     accept_type: AcceptType,
 ) -> HTTPResult {
-    let rx = store.read(options).await;
-    let stream = ReceiverStream::new(rx);
+    // A bounded read is a page: read it up front, so the response can say where the next one
+    // starts
+    let paginate = options.follow == FollowOption::Off && options.limit.is_some();
+    let limit = options.limit;
+    let mut rx = store.read(options).await;
+
+    let mut next = None;
+    let stream: std::pin::Pin<Box<dyn tokio_stream::Stream<Item = Frame> + Send + Sync>> =
+        if paginate {
+            let mut frames = Vec::new();
+            while let Some(frame) = rx.recv().await {
+                frames.push(frame);
+            }
+            let page: Vec<_> = frames
+                .iter()
+                .filter(|frame| frame.topic != "xs.eoh")
+                .collect();
+            if limit.is_some_and(|limit| page.len() >= limit) {
+                next = page.last().map(|frame| encode_page_token(&frame.id));
+            }
+            Box::pin(tokio_stream::iter(frames))
+        } else {
+            Box::pin(ReceiverStream::new(rx))
+        };
 
     let accept_type_clone = accept_type.clone();
     let stream = stream.map(move |frame| {
@@ -228,12 +728,13 @@ async fn handle_stream_cat(
                 encoded.push(b'\n');
                 encoded
             }
-            AcceptType::EventStream => format!(
-                "id: {}\ndata: {}\n\n",
-                frame.id,
-                serde_json::to_string(&frame).unwrap_or_default()
-            )
-            .into_bytes(),
+            AcceptType::EventStream => sse_event(&frame),
+            AcceptType::Cbor => {
+                let mut encoded = Vec::new();
+                ciborium::into_writer(&frame, &mut encoded).unwrap();
+                length_delimited(encoded)
+            }
+            AcceptType::Msgpack => length_delimited(rmp_serde::to_vec_named(&frame).unwrap()),
         };
         Ok(hyper::body::Frame::data(Bytes::from(bytes)))
     });
@@ -243,22 +744,75 @@ async fn handle_stream_cat(
     let content_type = match accept_type {
         AcceptType::Ndjson => "application/x-ndjson",
         AcceptType::EventStream => "text/event-stream",
+        AcceptType::Cbor => "application/cbor",
+        AcceptType::Msgpack => "application/msgpack",
     };
 
-    Ok(Response::builder()
+    let mut builder = Response::builder()
         .status(StatusCode::OK)
-        .header("Content-Type", content_type)
-        .body(body)?)
+        .header("Content-Type", content_type);
+    if let Some(next) = next {
+        builder = builder.header("xs-next", next);
+    }
+    Ok(builder.body(body)?)
+}
+
+/// The id an `EventSource` resumes from when it reconnects, sent as `Last-Event-ID`.
+fn last_event_id(
+    accept_type: &AcceptType,
+    headers: &hyper::HeaderMap,
+) -> Result<Option<Scru128Id>, String> {
+    if !matches!(accept_type, AcceptType::EventStream) {
+        return Ok(None);
+    }
+    let Some(value) = headers.get("last-event-id") else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .map(Some)
+        .ok_or_else(|| format!("Invalid Last-Event-ID: {:?}", value))
+}
+
+/// Formats a frame as a server-sent event, with the frame as JSON data. Heartbeats become
+/// comments, which keep the connection alive without reaching `onmessage`.
+fn sse_event(frame: &Frame) -> Vec<u8> {
+    if frame.topic == "xs.pulse" {
+        return b": ping\n\n".to_vec();
+    }
+    let data = serde_json::to_string(frame).unwrap_or_default();
+    // Synthetic markers aren't in the stream, so resuming from one could skip frames: leave
+    // the last event id where it was
+    if matches!(frame.topic.as_str(), "xs.threshold" | "xs.eoh" | "xs.gap") {
+        return format!("data: {}\n\n", data).into_bytes();
+    }
+    format!("id: {}\ndata: {}\n\n", frame.id, data).into_bytes()
+}
+
+/// Opaque token for the page after `id`, handed out in `xs-next` and accepted as `after`.
+fn encode_page_token(id: &Scru128Id) -> String {
+    base64::prelude::BASE64_URL_SAFE_NO_PAD.encode(id.as_bytes())
+}
+
+fn decode_page_token(token: &str) -> Option<Scru128Id> {
+    let bytes = base64::prelude::BASE64_URL_SAFE_NO_PAD.decode(token).ok()?;
+    Some(Scru128Id::from_bytes(bytes.try_into().ok()?))
 }
 
+/// Appends a frame with the request body as its content. The frame is only returned once it's
+/// been written to disk and broadcast to followers.
 async fn handle_stream_append(
     store: &mut Store,
     req: Request<hyper::body::Incoming>,
     topic: String,
     ttl: Option<TTL>,
     context_id: Scru128Id,
+    meta: Option<serde_json::Value>,
+    schema: Option<String>,
 ) -> HTTPResult {
-    let (parts, mut body) = req.into_parts();
+    let mut body = req.into_body();
 
     let hash = {
         let mut writer = store.cas_writer().await?;
@@ -266,7 +820,8 @@ async fn handle_stream_append(
 
         while let Some(frame) = body.frame().await {
             if let Ok(data) = frame?.into_data() {
-                writer.write_all(&data).await?;
+                // Fails once the content passes the store's limit
+                writer.write_all(&data).await.map_err(StoreError::from)?;
                 bytes_written += data.len();
             }
         }
@@ -278,46 +833,83 @@ async fn handle_stream_append(
         }
     };
 
-    let meta = match parts
-        .headers
-        .get("xs-meta")
-        .map(|x| x.to_str())
-        .transpose()
-        .unwrap()
-        .map(|s| {
-            // First decode the Base64-encoded string
-            base64::prelude::BASE64_STANDARD
-                .decode(s)
-                .map_err(|e| format!("xs-meta isn't valid Base64: {}", e))
-                .and_then(|decoded| {
-                    // Then parse the decoded bytes as UTF-8 string
-                    String::from_utf8(decoded)
-                        .map_err(|e| format!("xs-meta isn't valid UTF-8: {}", e))
-                        .and_then(|json_str| {
-                            // Finally parse the UTF-8 string as JSON
-                            serde_json::from_str(&json_str)
-                                .map_err(|e| format!("xs-meta isn't valid JSON: {}", e))
-                        })
-                })
-        })
-        .transpose()
-    {
-        Ok(meta) => meta,
-        Err(e) => return response_400(e.to_string()),
-    };
-
     let frame = store.append(
         Frame::builder(topic, context_id)
             .maybe_hash(hash)
             .maybe_meta(meta)
             .maybe_ttl(ttl)
+            .maybe_schema(schema)
             .build(),
     )?;
 
     Ok(Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "application/json")
-        .body(full(serde_json::to_string(&frame).unwrap()))?)
+        .body(full(serde_json::to_string(&frame)?))?)
+}
+
+/// Appends a frame per line of an NDJSON body, all or nothing. A line is either an object of the
+/// form `{"content": ..., "meta": ...}`, or is itself the content. Meta from the request applies
+/// to lines that don't carry their own.
+async fn handle_stream_append_batch(
+    store: &mut Store,
+    body: hyper::body::Incoming,
+    topic: String,
+    ttl: Option<TTL>,
+    context_id: Scru128Id,
+    meta: Option<serde_json::Value>,
+    schema: Option<String>,
+) -> HTTPResult {
+    let bytes = body.collect().await?.to_bytes();
+
+    let mut frames = Vec::new();
+    for line in bytes.split(|b| *b == b'\n') {
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            continue;
+        }
+        let (content, line_meta) = parse_batch_line(line);
+        let hash = store.cas_insert(&content).await?;
+        frames.push(
+            Frame::builder(topic.clone(), context_id)
+                .hash(hash)
+                .maybe_meta(line_meta.or_else(|| meta.clone()))
+                .maybe_ttl(ttl.clone())
+                .maybe_schema(schema.clone())
+                .build(),
+        );
+    }
+
+    let frames = store.append_batch(frames)?;
+
+    let mut body = Vec::new();
+    for frame in &frames {
+        serde_json::to_writer(&mut body, frame)?;
+        body.push(b'\n');
+    }
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/x-ndjson")
+        .body(full(body))?)
+}
+
+fn parse_batch_line(line: &[u8]) -> (Vec<u8>, Option<serde_json::Value>) {
+    #[derive(serde::Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Line {
+        content: serde_json::Value,
+        meta: Option<serde_json::Value>,
+    }
+
+    match serde_json::from_slice::<Line>(line) {
+        Ok(Line {
+            content: serde_json::Value::String(content),
+            meta,
+        }) => (content.into_bytes(), meta),
+        Ok(Line { content, meta }) => (content.to_string().into_bytes(), meta),
+        Err(_) => (line.to_vec(), None),
+    }
 }
 
 async fn handle_cas_post(store: &mut Store, mut body: hyper::body::Incoming) -> HTTPResult {
@@ -327,7 +919,8 @@ async fn handle_cas_post(store: &mut Store, mut body: hyper::body::Incoming) ->
 
         while let Some(frame) = body.frame().await {
             if let Ok(data) = frame?.into_data() {
-                writer.write_all(&data).await?;
+                // Fails once the content passes the store's limit
+                writer.write_all(&data).await.map_err(StoreError::from)?;
                 bytes_written += data.len();
             }
         }
@@ -345,6 +938,32 @@ async fn handle_cas_post(store: &mut Store, mut body: hyper::body::Incoming) ->
         .body(full(hash.to_string()))?)
 }
 
+/// The store's metrics, and how many followers it has.
+async fn handle_stats(store: &Store) -> HTTPResult {
+    let mut stats = serde_json::to_value(store.metrics())?;
+    stats["subscribers"] = store.subscriber_count().into();
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(full(stats.to_string()))?)
+}
+
+/// 200 if `ping`, a round trip through the store's gc worker, is answered within
+/// [`HEALTH_TIMEOUT`], and 503 if the worker has gone away or is stuck.
+async fn handle_health(
+    ping: impl std::future::Future<Output = Result<(), StoreError>>,
+) -> HTTPResult {
+    let (status, body) = match tokio::time::timeout(HEALTH_TIMEOUT, ping).await {
+        Ok(Ok(())) => (StatusCode::OK, "ok".to_string()),
+        Ok(Err(e)) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "store didn't respond in time".to_string(),
+        ),
+    };
+    Ok(Response::builder().status(status).body(full(body))?)
+}
+
 async fn handle_version() -> HTTPResult {
     let version = env!("CARGO_PKG_VERSION");
     let version_info = serde_json::json!({ "version": version });
@@ -354,11 +973,47 @@ async fn handle_version() -> HTTPResult {
         .body(full(serde_json::to_string(&version_info).unwrap()))?)
 }
 
+/// How [`serve`] exposes the API beyond the store's own socket. The store's socket is guarded by
+/// filesystem permissions, so the token and limits only apply to the exposed address.
+#[derive(Clone, Debug, Default, bon::Builder)]
+pub struct ServeOptions {
+    /// An additional address to listen on: `[HOST]:PORT` for TCP, or a path for a Unix socket.
+    #[builder(into)]
+    pub expose: Option<String>,
+    /// Require `Authorization: Bearer <token>` on requests.
+    #[builder(into)]
+    pub token: Option<String>,
+    /// How many connections can be open at once. Connections past the limit are closed as soon
+    /// as they're accepted.
+    pub max_connections: Option<usize>,
+    /// How long a client has to send a request's headers, and then its body, before the
+    /// connection is closed.
+    pub request_timeout: Option<std::time::Duration>,
+}
+
+/// What a listener asks of its connections.
+#[derive(Clone, Default)]
+struct ListenerPolicy {
+    token: Option<Arc<String>>,
+    connections: Option<Arc<tokio::sync::Semaphore>>,
+    request_timeout: Option<std::time::Duration>,
+}
+
+/// Serves the API until `shutdown` is cancelled. Listeners then stop accepting connections, and
+/// in-flight connections are given `SHUTDOWN_GRACE_PERIOD` to finish before `serve` returns.
 pub async fn serve(
     store: Store,
     engine: nu::Engine,
-    expose: Option<String>,
+    options: ServeOptions,
+    shutdown: CancellationToken,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let ServeOptions {
+        expose,
+        token,
+        max_connections,
+        request_timeout,
+    } = options;
+
     if let Err(e) = store.append(
         Frame::builder("xs.start", store::ZERO_CONTEXT)
             .maybe_meta(expose.as_ref().map(|e| serde_json::json!({"expose": e})))
@@ -370,21 +1025,31 @@ pub async fn serve(
     let path = store.path.join("sock").to_string_lossy().to_string();
     let listener = Listener::bind(&path).await?;
 
-    let mut listeners = vec![listener];
+    // The store's own socket is guarded by filesystem permissions: only the exposed address
+    // requires the token, and is limited
+    let mut listeners = vec![(listener, ListenerPolicy::default())];
 
     if let Some(expose) = expose {
-        listeners.push(Listener::bind(&expose).await?);
+        let policy = ListenerPolicy {
+            token: token.map(Arc::new),
+            connections: max_connections.map(|n| Arc::new(tokio::sync::Semaphore::new(n))),
+            request_timeout,
+        };
+        listeners.push((Listener::bind(&expose).await?, policy));
     }
 
     let mut tasks = Vec::new();
-    for listener in listeners {
+    for (listener, policy) in listeners {
         let store = store.clone();
         let engine = engine.clone();
-        let task = tokio::spawn(async move { listener_loop(listener, store, engine).await });
+        let shutdown = shutdown.clone();
+        let task =
+            tokio::spawn(
+                async move { listener_loop(listener, store, engine, policy, shutdown).await },
+            );
         tasks.push(task);
     }
 
-    // TODO: graceful shutdown and error handling
     // Wait for all listener tasks to complete (or until the first error)
     for task in tasks {
         task.await??;
@@ -397,20 +1062,54 @@ async fn listener_loop(
     mut listener: Listener,
     store: Store,
     engine: nu::Engine,
+    policy: ListenerPolicy,
+    shutdown: CancellationToken,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let graceful = GracefulShutdown::new();
+
     loop {
-        let (stream, _) = listener.accept().await?;
+        let (stream, _) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown.cancelled() => break,
+        };
+        let permit = match &policy.connections {
+            Some(connections) => match connections.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    tracing::warn!("{}: connection limit reached, closing connection", listener);
+                    drop(stream);
+                    continue;
+                }
+            },
+            None => None,
+        };
         let io = TokioIo::new(stream);
         let store = store.clone();
         let engine = engine.clone();
-        tokio::task::spawn(async move {
-            if let Err(err) = http1::Builder::new()
-                .serve_connection(
-                    io,
-                    service_fn(move |req| handle(store.clone(), engine.clone(), req)),
+        let token = policy.token.clone();
+        let request_timeout = policy.request_timeout;
+        let mut builder = http1::Builder::new();
+        if let Some(timeout) = request_timeout {
+            builder
+                .timer(TokioTimer::new())
+                .header_read_timeout(timeout);
+        }
+        let conn = builder.serve_connection(
+            io,
+            service_fn(move |req| {
+                handle_within(
+                    request_timeout,
+                    store.clone(),
+                    engine.clone(),
+                    token.clone(),
+                    req,
                 )
-                .await
-            {
+            }),
+        );
+        let conn = graceful.watch(conn);
+        tokio::task::spawn(async move {
+            let _permit = permit;
+            if let Err(err) = conn.await {
                 // Match against the error kind to selectively ignore `NotConnected` errors
                 if let Some(std::io::ErrorKind::NotConnected) = err.source().and_then(|source| {
                     source
@@ -425,6 +1124,16 @@ async fn listener_loop(
             }
         });
     }
+
+    if tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!("{}: timed out waiting for connections to close", listener);
+    }
+
+    listener.close();
+    Ok(())
 }
 
 fn response_frame_or_404(frame: Option<store::Frame>) -> HTTPResult {
@@ -439,6 +1148,9 @@ fn response_frame_or_404(frame: Option<store::Frame>) -> HTTPResult {
 }
 
 async fn handle_stream_item_remove(store: &mut Store, id: Scru128Id) -> HTTPResult {
+    if !store.exists(&id)? {
+        return response_404();
+    }
     match store.remove(&id) {
         Ok(()) => Ok(Response::builder()
             .status(StatusCode::NO_CONTENT)
@@ -459,7 +1171,7 @@ async fn handle_head_get(
     follow: bool,
     context_id: Scru128Id,
 ) -> HTTPResult {
-    let current_head = store.head(topic, context_id);
+    let current_head = store.head(topic, context_id)?;
 
     if !follow {
         return response_frame_or_404(current_head);
@@ -499,6 +1211,55 @@ async fn handle_head_get(
         .body(body)?)
 }
 
+/// Marks a hash [`handle_cas_batch`] has no content for, in place of its length.
+const CAS_BATCH_MISSING: u32 = u32::MAX;
+
+/// Streams the content of each hash in a JSON array, in order, each prefixed with its length as
+/// a big-endian u32. A hash with no content gets [`CAS_BATCH_MISSING`] in place of a length, and
+/// nothing after it. Each blob is only read once the previous one has been sent.
+async fn handle_cas_batch(store: &Store, body: hyper::body::Incoming) -> HTTPResult {
+    let bytes = body.collect().await?.to_bytes();
+    let hashes: Vec<String> = match serde_json::from_slice(&bytes) {
+        Ok(hashes) => hashes,
+        Err(e) => return response_400(format!("Expected a JSON array of hashes: {}", e)),
+    };
+    let hashes = match hashes
+        .iter()
+        .map(|hash| ssri::Integrity::from_str(hash))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(hashes) => hashes,
+        Err(e) => return response_400(format!("Invalid CAS hash: {}", e)),
+    };
+
+    let store = store.clone();
+    let stream = futures::stream::iter(hashes).then(move |hash| {
+        let store = store.clone();
+        async move {
+            let bytes = match store.cas_read(&hash).await {
+                Ok(content) => {
+                    let len = u32::try_from(content.len())
+                        .ok()
+                        .filter(|len| *len != CAS_BATCH_MISSING)
+                        .ok_or_else(|| format!("{} is too large to batch", hash))?;
+                    let mut bytes = Vec::with_capacity(4 + content.len());
+                    bytes.extend_from_slice(&len.to_be_bytes());
+                    bytes.extend(content);
+                    bytes
+                }
+                Err(e) if e.is_not_found() => CAS_BATCH_MISSING.to_be_bytes().to_vec(),
+                Err(e) => return Err(BoxError::from(e)),
+            };
+            Ok(hyper::body::Frame::data(Bytes::from(bytes)))
+        }
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/octet-stream")
+        .body(StreamBody::new(stream).boxed())?)
+}
+
 async fn handle_import(store: &mut Store, body: hyper::body::Incoming) -> HTTPResult {
     let bytes = body.collect().await?.to_bytes();
     let frame: Frame = match serde_json::from_slice(&bytes) {
@@ -516,6 +1277,20 @@ async fn handle_import(store: &mut Store, body: hyper::body::Incoming) -> HTTPRe
         .body(full(serde_json::to_string(&frame).unwrap()))?)
 }
 
+fn response_401() -> HTTPResult {
+    Ok(Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("WWW-Authenticate", "Bearer")
+        .body(empty())?)
+}
+
+fn response_408() -> HTTPResult {
+    Ok(Response::builder()
+        .status(StatusCode::REQUEST_TIMEOUT)
+        .header("Connection", "close")
+        .body(empty())?)
+}
+
 fn response_404() -> HTTPResult {
     Ok(Response::builder()
         .status(StatusCode::NOT_FOUND)
@@ -529,6 +1304,24 @@ fn response_400(message: String) -> HTTPResult {
         .body(body)?)
 }
 
+/// Maps a handler's error to a response: store errors the client caused get a 4xx, anything else
+/// is a 500.
+fn response_error(e: BoxError) -> HTTPResult {
+    match e.downcast_ref::<StoreError>() {
+        Some(e) if e.is_not_found() => response_404(),
+        Some(StoreError::InvalidFrame(msg)) => response_400(msg.clone()),
+        Some(e @ StoreError::ContentTooLarge(_)) => response_413(e.to_string()),
+        _ => response_500(e.to_string()),
+    }
+}
+
+fn response_413(message: String) -> HTTPResult {
+    Ok(Response::builder()
+        .status(StatusCode::PAYLOAD_TOO_LARGE)
+        .header("Connection", "close")
+        .body(full(message))?)
+}
+
 fn response_500(message: String) -> HTTPResult {
     let body = full(message);
     Ok(Response::builder()
@@ -552,6 +1345,1094 @@ fn empty() -> BoxBody<Bytes, BoxError> {
 mod tests {
     use super::*;
 
+    struct TestServer {
+        _temp_dir: tempfile::TempDir,
+        store: Store,
+        sock: std::path::PathBuf,
+        shutdown: CancellationToken,
+        task: tokio::task::JoinHandle<Result<(), BoxError>>,
+    }
+
+    async fn spawn_test_server() -> TestServer {
+        spawn_test_server_with(store::StoreOptions::default()).await
+    }
+
+    async fn spawn_test_server_with(options: store::StoreOptions) -> TestServer {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Store::open(temp_dir.path().to_path_buf(), options).unwrap();
+        let engine = nu::Engine::new().unwrap();
+        let sock = temp_dir.path().join("sock");
+
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn(serve(
+            store.clone(),
+            engine,
+            ServeOptions::default(),
+            shutdown.clone(),
+        ));
+
+        let start = std::time::Instant::now();
+        while !sock.exists() {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        TestServer {
+            _temp_dir: temp_dir,
+            store,
+            sock,
+            shutdown,
+            task,
+        }
+    }
+
+    /// Sends a raw HTTP/1.1 request, returning the status, headers and body of the response.
+    async fn raw_request(
+        sock: &std::path::Path,
+        method: &str,
+        path: &str,
+    ) -> (u16, HashMap<String, String>, Vec<u8>) {
+        raw_request_with_headers(sock, method, path, &[]).await
+    }
+
+    async fn raw_request_with_headers(
+        sock: &std::path::Path,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+    ) -> (u16, HashMap<String, String>, Vec<u8>) {
+        raw_request_with_body(sock, method, path, headers, b"").await
+    }
+
+    async fn raw_request_with_body(
+        sock: &std::path::Path,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> (u16, HashMap<String, String>, Vec<u8>) {
+        use tokio::io::AsyncReadExt;
+
+        let headers: String = headers
+            .iter()
+            .map(|(k, v)| format!("{}: {}\r\n", k, v))
+            .collect();
+        let mut stream = tokio::net::UnixStream::connect(sock).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n{}\r\n",
+                    method,
+                    path,
+                    body.len(),
+                    headers
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        stream.write_all(body).await.unwrap();
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).await.unwrap();
+
+        let split = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8(raw[..split].to_vec()).unwrap();
+        let body = raw[split + 4..].to_vec();
+
+        let mut lines = head.lines();
+        let status = lines.next().unwrap().split(' ').nth(1).unwrap();
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(": "))
+            .map(|(k, v)| (k.to_lowercase(), v.to_string()))
+            .collect();
+
+        let body = if headers.get("transfer-encoding").map(String::as_str) == Some("chunked") {
+            let mut decoded = Vec::new();
+            let mut rest = &body[..];
+            loop {
+                let eol = rest.windows(2).position(|w| w == b"\r\n").unwrap();
+                let size =
+                    usize::from_str_radix(std::str::from_utf8(&rest[..eol]).unwrap(), 16).unwrap();
+                if size == 0 {
+                    break decoded;
+                }
+                decoded.extend_from_slice(&rest[eol + 2..eol + 2 + size]);
+                rest = &rest[eol + 2 + size + 2..];
+            }
+        } else {
+            body
+        };
+
+        (status.parse().unwrap(), headers, body)
+    }
+
+    #[tokio::test]
+    async fn test_head_requests() {
+        let server = spawn_test_server().await;
+
+        let hash = server.store.cas_insert("hello world").await.unwrap();
+        let (status, headers, body) =
+            raw_request(&server.sock, "HEAD", &format!("/cas/{}", hash)).await;
+        assert_eq!(status, 200);
+        assert_eq!(headers["content-length"], "11");
+        assert!(body.is_empty());
+
+        let missing = ssri::Integrity::from("missing");
+        let (status, _, body) =
+            raw_request(&server.sock, "HEAD", &format!("/cas/{}", missing)).await;
+        assert_eq!(status, 404);
+        assert!(body.is_empty());
+
+        let frame = server
+            .store
+            .append(Frame::builder("topic", store::ZERO_CONTEXT).build())
+            .unwrap();
+        let (status, headers, body) =
+            raw_request(&server.sock, "HEAD", &format!("/{}", frame.id)).await;
+        assert_eq!(status, 200);
+        assert_eq!(
+            headers["content-length"],
+            serde_json::to_string(&frame).unwrap().len().to_string()
+        );
+        assert!(body.is_empty());
+
+        let (status, _, _) =
+            raw_request(&server.sock, "HEAD", &format!("/{}", scru128::new())).await;
+        assert_eq!(status, 404);
+
+        // GET still returns the body
+        let (status, _, body) = raw_request(&server.sock, "GET", &format!("/cas/{}", hash)).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, b"hello world");
+    }
+
+    /// Flips every bit of every blob in the store's CAS, keeping their sizes.
+    fn corrupt_blobs(store: &Store) {
+        let mut dirs = vec![store.path.join("cacache").join("content-v2")];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let mut perms = std::fs::metadata(&path).unwrap().permissions();
+                #[allow(clippy::permissions_set_readonly_false)]
+                perms.set_readonly(false);
+                std::fs::set_permissions(&path, perms).unwrap();
+                let content = std::fs::read(&path).unwrap();
+                std::fs::write(&path, content.iter().map(|b| !b).collect::<Vec<_>>()).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cas_get_corrupt_content() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        for compress_cas in [false, true] {
+            let server = spawn_test_server_with(
+                store::StoreOptions::builder()
+                    .compress_cas(compress_cas)
+                    .build(),
+            )
+            .await;
+            let small = server.store.cas_insert("hello world").await.unwrap();
+            let large: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+            let large = server.store.cas_insert(&large).await.unwrap();
+            corrupt_blobs(&server.store);
+
+            // Content that's read to the end while sniffing its type fails before the response
+            // starts
+            let (status, _, _) = raw_request(&server.sock, "GET", &format!("/cas/{}", small)).await;
+            assert_eq!(status, 500);
+
+            // Otherwise the response is cut short, without the chunk that would end it
+            let mut stream = tokio::net::UnixStream::connect(&server.sock).await.unwrap();
+            stream
+                .write_all(
+                    format!(
+                        "GET /cas/{} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                        large
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            let mut raw = Vec::new();
+            let _ = stream.read_to_end(&mut raw).await;
+            if raw.starts_with(b"HTTP/1.1 200") {
+                assert!(!raw.ends_with(b"\r\n0\r\n\r\n"));
+            } else {
+                // Corrupt compressed content may fail to inflate within the first chunk
+                assert!(compress_cas);
+                assert!(raw.starts_with(b"HTTP/1.1 500"));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head_cas_doesnt_read_content() {
+        for compress_cas in [false, true] {
+            let server = spawn_test_server_with(
+                store::StoreOptions::builder()
+                    .compress_cas(compress_cas)
+                    .build(),
+            )
+            .await;
+            let hash = server.store.cas_insert("hello world").await.unwrap();
+
+            // HEAD still answers, as it never opens the content
+            corrupt_blobs(&server.store);
+
+            let (status, headers, body) =
+                raw_request(&server.sock, "HEAD", &format!("/cas/{}", hash)).await;
+            assert_eq!(status, 200);
+            assert_eq!(headers["content-length"], "11");
+            assert_eq!(headers["etag"], format!("\"{}\"", hash));
+            assert!(body.is_empty());
+
+            let (status, _, _) = raw_request_with_headers(
+                &server.sock,
+                "HEAD",
+                &format!("/cas/{}", hash),
+                &[("If-None-Match", &format!("\"{}\"", hash))],
+            )
+            .await;
+            assert_eq!(status, 304);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let engine = nu::Engine::new().unwrap();
+        let sock = temp_dir.path().join("sock");
+        let exposed = temp_dir.path().join("exposed");
+
+        let shutdown = CancellationToken::new();
+        let _task = tokio::spawn(serve(
+            store,
+            engine,
+            ServeOptions::builder()
+                .expose(exposed.to_string_lossy())
+                .token("secret")
+                .build(),
+            shutdown.clone(),
+        ));
+        let start = std::time::Instant::now();
+        while !sock.exists() || !exposed.exists() {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let (status, headers, _) = raw_request(&exposed, "GET", "/version").await;
+        assert_eq!(status, 401);
+        assert_eq!(headers["www-authenticate"], "Bearer");
+
+        for wrong in ["Bearer nope", "Bearer secret2", "Basic secret", "secret"] {
+            let (status, _, _) =
+                raw_request_with_headers(&exposed, "GET", "/version", &[("Authorization", wrong)])
+                    .await;
+            assert_eq!(status, 401, "{}", wrong);
+        }
+
+        let (status, _, _) = raw_request_with_headers(
+            &exposed,
+            "GET",
+            "/version",
+            &[("Authorization", "Bearer secret")],
+        )
+        .await;
+        assert_eq!(status, 200);
+
+        // the store's own socket doesn't need the token
+        let (status, _, _) = raw_request(&sock, "GET", "/version").await;
+        assert_eq!(status, 200);
+
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let server = spawn_test_server().await;
+        let (status, _, body) = raw_request(&server.sock, "GET", "/health").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, b"ok");
+
+        // A gc worker that's gone, or stuck
+        let gone = handle_health(async { Err(StoreError::Closed) }).await;
+        assert_eq!(gone.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+        let stuck = handle_health(std::future::pending()).await;
+        assert_eq!(stuck.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_connection_limits() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let engine = nu::Engine::new().unwrap();
+        let exposed = temp_dir.path().join("exposed");
+
+        let shutdown = CancellationToken::new();
+        let _task = tokio::spawn(serve(
+            store,
+            engine,
+            ServeOptions::builder()
+                .expose(exposed.to_string_lossy())
+                .max_connections(2)
+                .request_timeout(std::time::Duration::from_millis(500))
+                .build(),
+            shutdown.clone(),
+        ));
+        let start = std::time::Instant::now();
+        while !exposed.exists() {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        // Two idle connections take up the limit, so the next is closed straight away
+        let mut idle = Vec::new();
+        for _ in 0..2 {
+            idle.push(tokio::net::UnixStream::connect(&exposed).await.unwrap());
+        }
+        let mut rejected = tokio::net::UnixStream::connect(&exposed).await.unwrap();
+        let read = tokio::time::timeout(
+            std::time::Duration::from_millis(300),
+            rejected.read(&mut [0; 1]),
+        )
+        .await
+        .expect("connection over the limit wasn't closed");
+        assert_eq!(read.unwrap(), 0);
+
+        // Idle connections are closed once they've taken too long to send a request, which
+        // frees up their slots
+        for mut conn in idle {
+            let mut buf = Vec::new();
+            let read = tokio::time::timeout(
+                std::time::Duration::from_secs(3),
+                conn.read_to_end(&mut buf),
+            )
+            .await
+            .expect("idle connection wasn't closed");
+            assert!(read.is_ok());
+        }
+        // The slot is given back once the connection's task has wound down
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let (status, _, _) = raw_request(&exposed, "GET", "/version").await;
+        assert_eq!(status, 200);
+
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_delete_frame() {
+        let server = spawn_test_server().await;
+
+        let frame = server
+            .store
+            .append(Frame::builder("topic", store::ZERO_CONTEXT).build())
+            .unwrap();
+        let (status, _, _) = raw_request(&server.sock, "DELETE", &format!("/{}", frame.id)).await;
+        assert_eq!(status, 204);
+        assert_eq!(server.store.get(&frame.id).unwrap(), None);
+
+        // gone already
+        let (status, _, _) = raw_request(&server.sock, "DELETE", &format!("/{}", frame.id)).await;
+        assert_eq!(status, 404);
+        let (status, _, _) =
+            raw_request(&server.sock, "DELETE", &format!("/{}", scru128::new())).await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_request_log() {
+        use std::sync::Mutex;
+        use tracing::span::{Attributes, Id};
+        use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+        type Fields = HashMap<String, String>;
+
+        // Collects each event's level and fields, along with those of its span
+        #[derive(Clone, Default)]
+        struct Events {
+            spans: Arc<Mutex<HashMap<u64, Fields>>>,
+            events: Arc<Mutex<Vec<(tracing::Level, Fields)>>>,
+        }
+
+        struct Visitor<'a>(&'a mut Fields);
+
+        impl tracing::field::Visit for Visitor<'_> {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .insert(field.name().to_string(), format!("{:?}", value));
+            }
+        }
+
+        impl<S> Layer<S> for Events
+        where
+            S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+        {
+            fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+                let mut fields = Fields::new();
+                attrs.record(&mut Visitor(&mut fields));
+                self.spans.lock().unwrap().insert(id.into_u64(), fields);
+            }
+
+            fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+                let mut fields = ctx
+                    .event_span(event)
+                    .and_then(|span| {
+                        self.spans
+                            .lock()
+                            .unwrap()
+                            .get(&span.id().into_u64())
+                            .cloned()
+                    })
+                    .unwrap_or_default();
+                event.record(&mut Visitor(&mut fields));
+                self.events
+                    .lock()
+                    .unwrap()
+                    .push((*event.metadata().level(), fields));
+            }
+        }
+
+        let events = Events::default();
+        let _default =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+
+        let server = spawn_test_server().await;
+        let (status, _, _) = raw_request(&server.sock, "GET", "/version").await;
+        assert_eq!(status, 200);
+        let missing = format!("/{}", scru128::new());
+        let (status, _, _) = raw_request(&server.sock, "DELETE", &missing).await;
+        assert_eq!(status, 404);
+
+        let events = events.events.lock().unwrap();
+        let responses: Vec<_> = events
+            .iter()
+            .filter(|(_, fields)| fields.get("message").is_some_and(|m| m == "response"))
+            .map(|(level, fields)| {
+                assert!(fields["latency_ms"].parse::<u64>().is_ok());
+                (
+                    *level,
+                    fields["method"].as_str(),
+                    fields["path"].as_str(),
+                    fields["status"].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            responses,
+            [
+                (tracing::Level::INFO, "GET", "/version", "200"),
+                (tracing::Level::WARN, "DELETE", missing.as_str(), "404"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_content_bytes() {
+        let server =
+            spawn_test_server_with(store::StoreOptions::builder().max_content_bytes(10).build())
+                .await;
+
+        for path in ["/topic", "/cas"] {
+            let (status, _, body) =
+                raw_request_with_body(&server.sock, "POST", path, &[], b"01234567890").await;
+            assert_eq!(status, 413, "{}", path);
+            assert_eq!(body, b"content exceeds the limit of 10 bytes");
+        }
+        assert!(server
+            .store
+            .read_sync(ReadOptions::builder().topic("topic").build())
+            .next()
+            .is_none());
+
+        // Nothing is left of the rejected writes, committed or not
+        assert!(server.store.cas_list().unwrap().is_empty());
+        let tmp = server.store.path.join("cacache").join("tmp");
+        assert!(!tmp.exists() || std::fs::read_dir(&tmp).unwrap().next().is_none());
+
+        // Content right at the limit is fine
+        let (status, _, _) =
+            raw_request_with_body(&server.sock, "POST", "/topic", &[], b"0123456789").await;
+        assert_eq!(status, 200);
+        assert_eq!(server.store.cas_list().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cas_get_etag() {
+        let server = spawn_test_server().await;
+
+        let hash = server.store.cas_insert("hello world").await.unwrap();
+        let path = format!("/cas/{}", hash);
+
+        let (status, headers, body) = raw_request(&server.sock, "GET", &path).await;
+        assert_eq!(status, 200);
+        assert_eq!(headers["etag"], format!("\"{}\"", hash));
+        assert!(headers["cache-control"].contains("immutable"));
+        assert_eq!(body, b"hello world");
+
+        let etag = headers["etag"].clone();
+        let (status, headers, body) =
+            raw_request_with_headers(&server.sock, "GET", &path, &[("If-None-Match", &etag)]).await;
+        assert_eq!(status, 304);
+        assert_eq!(headers["etag"], etag);
+        assert!(body.is_empty());
+
+        // a different tag gets the content
+        let other = format!("\"{}\"", ssri::Integrity::from("other"));
+        let (status, _, body) =
+            raw_request_with_headers(&server.sock, "GET", &path, &[("If-None-Match", &other)])
+                .await;
+        assert_eq!(status, 200);
+        assert_eq!(body, b"hello world");
+    }
+
+    #[tokio::test]
+    async fn test_cas_get_content_type() {
+        let server = spawn_test_server().await;
+
+        let png = [
+            0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, b'I', b'H',
+            b'D', b'R',
+        ];
+        let cases: [(&[u8], &str); 5] = [
+            (&png, "image/png"),
+            (b"  {\"key\": \"value\"}", "application/json"),
+            (b"[1, 2, 3]", "application/json"),
+            (
+                "plain text, caf\u{e9}".as_bytes(),
+                "text/plain; charset=utf-8",
+            ),
+            (&[0x00, 0xFF, 0xFE, 0x01], "application/octet-stream"),
+        ];
+
+        for (content, expected) in cases {
+            let hash = server.store.cas_insert(content).await.unwrap();
+            let (status, headers, body) =
+                raw_request(&server.sock, "GET", &format!("/cas/{}", hash)).await;
+            assert_eq!(status, 200);
+            assert_eq!(headers["content-type"], expected);
+            assert_eq!(body, content);
+        }
+
+        // content longer than the sniffed prefix comes back whole
+        let long = format!("[{}]", vec!["1"; 1000].join(","));
+        let hash = server.store.cas_insert(&long).await.unwrap();
+        let (_, headers, body) = raw_request(&server.sock, "GET", &format!("/cas/{}", hash)).await;
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(body, long.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_cas_batch() {
+        let server = spawn_test_server().await;
+
+        let contents: [&[u8]; 3] = [b"first", b"second", b"third"];
+        let mut hashes = Vec::new();
+        for content in contents {
+            hashes.push(server.store.cas_insert(content).await.unwrap().to_string());
+        }
+        let missing = ssri::Integrity::from("missing").to_string();
+        hashes.insert(2, missing);
+
+        let (status, headers, body) = raw_request_with_body(
+            &server.sock,
+            "POST",
+            "/cas/batch",
+            &[],
+            serde_json::to_string(&hashes).unwrap().as_bytes(),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(headers["content-type"], "application/octet-stream");
+
+        let mut blobs = Vec::new();
+        let mut rest = &body[..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap());
+            rest = &rest[4..];
+            if len == CAS_BATCH_MISSING {
+                blobs.push(None);
+                continue;
+            }
+            blobs.push(Some(rest[..len as usize].to_vec()));
+            rest = &rest[len as usize..];
+        }
+        assert_eq!(
+            blobs,
+            [
+                Some(b"first".to_vec()),
+                Some(b"second".to_vec()),
+                None,
+                Some(b"third".to_vec())
+            ]
+        );
+
+        for body in [&b"not json"[..], br#"["not a hash"]"#] {
+            let (status, _, _) =
+                raw_request_with_body(&server.sock, "POST", "/cas/batch", &[], body).await;
+            assert_eq!(status, 400);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_cat_binary_encodings() {
+        let server = spawn_test_server().await;
+
+        let hash = server.store.cas_insert("content").await.unwrap();
+        let frames = vec![
+            server
+                .store
+                .append(
+                    Frame::builder("topic", store::ZERO_CONTEXT)
+                        .hash(hash)
+                        .meta(serde_json::json!({"key": "value", "n": [1, 2]}))
+                        .ttl(TTL::Head(3))
+                        .build(),
+                )
+                .unwrap(),
+            server
+                .store
+                .append(Frame::builder("other", store::ZERO_CONTEXT).build())
+                .unwrap(),
+        ];
+
+        type Decode = fn(&[u8]) -> Frame;
+        let cases: [(&str, Decode); 2] = [
+            ("application/cbor", |bytes| {
+                ciborium::from_reader(bytes).unwrap()
+            }),
+            ("application/msgpack", |bytes| {
+                rmp_serde::from_slice(bytes).unwrap()
+            }),
+        ];
+
+        for (accept, decode) in cases {
+            let (status, headers, body) =
+                raw_request_with_headers(&server.sock, "GET", "/", &[("Accept", accept)]).await;
+            assert_eq!(status, 200);
+            assert_eq!(headers["content-type"], accept);
+
+            let mut decoded = Vec::new();
+            let mut rest = &body[..];
+            while !rest.is_empty() {
+                let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+                decoded.push(decode(&rest[4..4 + len]));
+                rest = &rest[4 + len..];
+            }
+            decoded.retain(|frame| frame.topic != "xs.start");
+            assert_eq!(decoded, frames, "accept={}", accept);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_cat_sse() {
+        let server = spawn_test_server().await;
+
+        let frames: Vec<_> = (0..3)
+            .map(|_| {
+                server
+                    .store
+                    .append(Frame::builder("sse", store::ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+        let events = |frames: &[Frame]| {
+            frames
+                .iter()
+                .map(|frame| {
+                    format!(
+                        "id: {}\ndata: {}\n\n",
+                        frame.id,
+                        serde_json::to_string(frame).unwrap()
+                    )
+                })
+                .collect::<String>()
+        };
+
+        let accept = ("Accept", "text/event-stream");
+        let (status, headers, body) =
+            raw_request_with_headers(&server.sock, "GET", "/?topic=sse", &[accept]).await;
+        assert_eq!(status, 200);
+        assert_eq!(headers["content-type"], "text/event-stream");
+        assert_eq!(String::from_utf8(body).unwrap(), events(&frames));
+
+        // A reconnecting EventSource picks up after the last event it saw
+        let last_event_id = frames[0].id.to_string();
+        let (status, _, body) = raw_request_with_headers(
+            &server.sock,
+            "GET",
+            "/?topic=sse",
+            &[accept, ("Last-Event-ID", &last_event_id)],
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(String::from_utf8(body).unwrap(), events(&frames[1..]));
+
+        let (status, _, _) = raw_request_with_headers(
+            &server.sock,
+            "GET",
+            "/",
+            &[accept, ("Last-Event-ID", "nope")],
+        )
+        .await;
+        assert_eq!(status, 400);
+
+        let pulse = Frame::builder("xs.pulse", store::ZERO_CONTEXT).build();
+        assert_eq!(sse_event(&pulse), b": ping\n\n");
+        let threshold = Frame::builder("xs.threshold", store::ZERO_CONTEXT).build();
+        assert!(sse_event(&threshold).starts_with(b"data: "));
+    }
+
+    #[tokio::test]
+    async fn test_compressed_responses() {
+        use std::io::Read;
+
+        let server = spawn_test_server().await;
+        for n in 0..20 {
+            server
+                .store
+                .append(
+                    Frame::builder("compressible", store::ZERO_CONTEXT)
+                        .meta(serde_json::json!({"n": n}))
+                        .build(),
+                )
+                .unwrap();
+        }
+
+        let (status, headers, plain) = raw_request(&server.sock, "GET", "/").await;
+        assert_eq!(status, 200);
+        assert!(!headers.contains_key("content-encoding"));
+
+        let (status, headers, body) = raw_request_with_headers(
+            &server.sock,
+            "GET",
+            "/",
+            &[("Accept-Encoding", "deflate, gzip;q=0.8")],
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(headers["content-encoding"], "gzip");
+        assert_eq!(headers["vary"], "accept-encoding");
+        assert!(body.len() < plain.len());
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+
+        let (_, headers, body) = raw_request_with_headers(
+            &server.sock,
+            "GET",
+            "/",
+            &[("Accept-Encoding", "gzip;q=0, deflate")],
+        )
+        .await;
+        assert_eq!(headers["content-encoding"], "deflate");
+        let mut decoded = Vec::new();
+        flate2::read::ZlibDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+
+        // CAS content is served as it's stored
+        let hash = server.store.cas_insert("hello world").await.unwrap();
+        let (_, headers, body) = raw_request_with_headers(
+            &server.sock,
+            "GET",
+            &format!("/cas/{}", hash),
+            &[("Accept-Encoding", "gzip")],
+        )
+        .await;
+        assert!(!headers.contains_key("content-encoding"));
+        assert_eq!(body, b"hello world");
+
+        // a followed stream's frames come through as they're appended, without waiting for the
+        // stream to end
+        let mut stream = tokio::net::UnixStream::connect(&server.sock).await.unwrap();
+        stream
+            .write_all(
+                b"GET /?follow=true&tail=true HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        // wait for the response to start, so the read is following
+        let mut raw = Vec::new();
+        while !raw.windows(4).any(|w| w == b"\r\n\r\n") {
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).await.unwrap();
+            raw.extend_from_slice(&buf[..n]);
+        }
+        let live = server
+            .store
+            .append(Frame::builder("live", store::ZERO_CONTEXT).build())
+            .unwrap();
+        let expected = serde_json::to_string(&live).unwrap();
+
+        let mut decoder = flate2::write::GzDecoder::new(Vec::new());
+        let mut consumed = raw.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                // feed the decoder each complete chunk that's arrived
+                while let Some(eol) = raw[consumed..].windows(2).position(|w| w == b"\r\n") {
+                    let size = std::str::from_utf8(&raw[consumed..consumed + eol]).unwrap();
+                    let size = usize::from_str_radix(size, 16).unwrap();
+                    let start = consumed + eol + 2;
+                    if raw.len() < start + size + 2 {
+                        break;
+                    }
+                    std::io::Write::write_all(&mut decoder, &raw[start..start + size]).unwrap();
+                    std::io::Write::flush(&mut decoder).unwrap();
+                    consumed = start + size + 2;
+                }
+                if String::from_utf8_lossy(decoder.get_ref()).contains(&expected) {
+                    break;
+                }
+                let mut buf = [0; 1024];
+                let n = stream.read(&mut buf).await.unwrap();
+                raw.extend_from_slice(&buf[..n]);
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stream_cat_pagination() {
+        let server = spawn_test_server().await;
+
+        let frames: Vec<_> = (0..120)
+            .map(|_| {
+                server
+                    .store
+                    .append(Frame::builder("page", store::ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+
+        let mut pages = Vec::new();
+        let mut path = "/?topic=page&limit=50".to_string();
+        loop {
+            let (status, headers, body) = raw_request(&server.sock, "GET", &path).await;
+            assert_eq!(status, 200);
+            let page: Vec<Frame> = serde_json::Deserializer::from_slice(&body)
+                .into_iter()
+                .map(|frame| frame.unwrap())
+                .collect();
+            pages.push(page);
+
+            match headers.get("xs-next") {
+                Some(next) => path = format!("/?topic=page&limit=50&after={}", next),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), [50, 50, 20]);
+        assert_eq!(pages.concat(), frames);
+
+        // the token stands in for last-id, and has to be one we handed out
+        let (status, _, _) = raw_request(&server.sock, "GET", "/?limit=50&after=not-a-token").await;
+        assert_eq!(status, 400);
+        let (status, _, _) = raw_request(
+            &server.sock,
+            "GET",
+            &format!(
+                "/?limit=50&after=AAAAAAAAAAAAAAAAAAAAAA&last-id={}",
+                frames[0].id
+            ),
+        )
+        .await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_append_batch() {
+        let server = spawn_test_server().await;
+
+        let body = concat!(
+            "plain content\n",
+            "{\"content\": \"with meta\", \"meta\": {\"n\": 2}}\n",
+            "\n",
+            "{\"content\": {\"json\": true}}\n",
+        );
+        let (status, headers, body) = raw_request_with_body(
+            &server.sock,
+            "POST",
+            "/batch/topic?meta=%7B%22base%22%3Atrue%7D",
+            &[],
+            body.as_bytes(),
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert_eq!(headers["content-type"], "application/x-ndjson");
+
+        let frames: Vec<Frame> = body
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(frames.len(), 3);
+
+        let mut contents = Vec::new();
+        for frame in &frames {
+            assert_eq!(frame.topic, "topic");
+            assert_eq!(server.store.get(&frame.id).unwrap().as_ref(), Some(frame));
+            let content = server
+                .store
+                .cas_read(frame.hash.as_ref().unwrap())
+                .await
+                .unwrap();
+            contents.push(String::from_utf8(content).unwrap());
+        }
+        assert_eq!(contents, ["plain content", "with meta", r#"{"json":true}"#]);
+        assert_eq!(
+            frames.iter().map(|f| f.meta.clone()).collect::<Vec<_>>(),
+            [
+                Some(serde_json::json!({"base": true})),
+                Some(serde_json::json!({"n": 2})),
+                Some(serde_json::json!({"base": true})),
+            ]
+        );
+
+        // nothing is appended if any frame is rejected
+        let (status, _, _) = raw_request_with_body(
+            &server.sock,
+            "POST",
+            &format!("/batch/topic?context={}", scru128::new()),
+            &[],
+            b"one\ntwo\n",
+        )
+        .await;
+        assert_eq!(status, 400);
+        let count = server
+            .store
+            .read_sync(ReadOptions::builder().topic("topic").build())
+            .count();
+        assert_eq!(count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_append_ttl() {
+        let server = spawn_test_server().await;
+
+        let cases = [
+            ("forever", Some(TTL::Forever)),
+            ("ephemeral", Some(TTL::Ephemeral)),
+            (
+                "time:60000",
+                Some(TTL::Time(std::time::Duration::from_secs(60))),
+            ),
+            ("head:10", Some(TTL::Head(10))),
+            ("head:abc", None),
+            ("head:0", None),
+            ("time:soon", None),
+            ("never", None),
+        ];
+
+        for (ttl, expected) in cases {
+            let (status, _, body) = raw_request_with_body(
+                &server.sock,
+                "POST",
+                &format!("/topic?ttl={}", ttl),
+                &[],
+                b"content",
+            )
+            .await;
+            match expected {
+                Some(expected) => {
+                    assert_eq!(status, 200, "ttl={}", ttl);
+                    let frame: Frame = serde_json::from_slice(&body).unwrap();
+                    assert_eq!(frame.ttl, Some(expected));
+                }
+                None => assert_eq!(status, 400, "ttl={}", ttl),
+            }
+        }
+
+        // only the forever, time and head frames were stored
+        let stored: Vec<_> = server
+            .store
+            .read_sync(ReadOptions::default())
+            .filter(|frame| frame.topic == "topic")
+            .map(|frame| frame.ttl.unwrap())
+            .collect();
+        assert_eq!(
+            stored,
+            [
+                TTL::Forever,
+                TTL::Time(std::time::Duration::from_secs(60)),
+                TTL::Head(10)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_append_is_durable_and_visible() {
+        let server = spawn_test_server().await;
+
+        let mut follower = server
+            .store
+            .read(
+                ReadOptions::builder()
+                    .follow(store::FollowOption::On)
+                    .topic("topic")
+                    .build(),
+            )
+            .await;
+        assert_eq!(follower.recv().await.unwrap().topic, "xs.threshold");
+
+        let sock = server.sock.clone();
+        let post = tokio::spawn(async move {
+            raw_request_with_body(&sock, "POST", "/topic", &[], b"content").await
+        });
+        let followed = tokio::time::timeout(std::time::Duration::from_secs(5), follower.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (status, _, body) = post.await.unwrap();
+        assert_eq!(status, 200);
+        let frame: Frame = serde_json::from_slice(&body).unwrap();
+        assert_eq!(followed, frame);
+        // By the time the response arrives, the frame and its content are on disk
+        assert_eq!(server.store.get(&frame.id).unwrap(), Some(frame.clone()));
+        assert_eq!(
+            server
+                .store
+                .cas_read(frame.hash.as_ref().unwrap())
+                .await
+                .unwrap(),
+            b"content"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serve_shutdown() {
+        let TestServer {
+            _temp_dir,
+            sock,
+            shutdown,
+            task: server,
+            ..
+        } = spawn_test_server().await;
+        let addr = sock.to_string_lossy().to_string();
+        crate::client::version(&addr).await.unwrap();
+
+        shutdown.cancel();
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("serve didn't return after shutdown")
+            .unwrap()
+            .unwrap();
+
+        assert!(!sock.exists());
+        assert!(tokio::net::UnixStream::connect(&sock).await.is_err());
+    }
+
     #[test]
     fn test_match_route_head_follow() {
         let headers = hyper::HeaderMap::new();
@@ -566,4 +2447,73 @@ mod tests {
             Routes::HeadGet { topic, follow: true, context_id: _ } if topic == "test"
         ));
     }
+
+    #[test]
+    fn test_match_route_append_meta() {
+        use base64::Engine;
+
+        let mut headers = hyper::HeaderMap::new();
+        assert!(matches!(
+            match_route(&Method::POST, "/topic", &headers, None),
+            Routes::StreamAppend { meta: None, .. }
+        ));
+
+        // via the query string
+        let route = match_route(
+            &Method::POST,
+            "/topic",
+            &headers,
+            Some("meta=%7B%22key%22%3A%22value%22%7D"),
+        );
+        assert!(matches!(
+            route,
+            Routes::StreamAppend { meta: Some(meta), .. } if meta == serde_json::json!({"key": "value"})
+        ));
+
+        // via the xs-meta header, with unicode
+        let encoded = base64::prelude::BASE64_STANDARD.encode(r#"{"name":"Información"}"#);
+        headers.insert("xs-meta", encoded.parse().unwrap());
+        let route = match_route(&Method::POST, "/topic", &headers, None);
+        assert!(matches!(
+            route,
+            Routes::StreamAppend { meta: Some(meta), .. } if meta == serde_json::json!({"name": "Información"})
+        ));
+
+        // both at once is ambiguous
+        assert!(matches!(
+            match_route(&Method::POST, "/topic", &headers, Some("meta=%7B%7D")),
+            Routes::BadRequest(_)
+        ));
+
+        // invalid meta is rejected
+        assert!(matches!(
+            match_route(
+                &Method::POST,
+                "/topic",
+                &hyper::HeaderMap::new(),
+                Some("meta=%7Bnope")
+            ),
+            Routes::BadRequest(_)
+        ));
+        for bad in [
+            "not base64!",
+            &base64::prelude::BASE64_STANDARD.encode([0xff, 0xfe]),
+        ] {
+            let mut headers = hyper::HeaderMap::new();
+            headers.insert("xs-meta", bad.parse().unwrap());
+            assert!(matches!(
+                match_route(&Method::POST, "/topic", &headers, None),
+                Routes::BadRequest(_)
+            ));
+        }
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert(
+            "xs-meta",
+            hyper::header::HeaderValue::from_bytes(&[0xff]).unwrap(),
+        );
+        assert!(matches!(
+            match_route(&Method::POST, "/topic", &headers, None),
+            Routes::BadRequest(_)
+        ));
+    }
 }
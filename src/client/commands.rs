@@ -127,7 +127,7 @@ where
             // Direct CAS access for local path
             let store_path = path.parent().unwrap_or(&path).to_path_buf();
             let cas_path = store_path.join("cacache");
-            let mut reader = cacache::Reader::open_hash(&cas_path, integrity).await?;
+            let mut reader = crate::store::cas_open(&cas_path, integrity).await?;
             tokio::io::copy(&mut reader, writer).await?;
             writer.flush().await?;
             Ok(())
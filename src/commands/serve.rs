@@ -56,8 +56,17 @@ pub async fn serve(
     // Add core commands to base engine
     base_engine.add_commands(vec![
         Box::new(commands::cas_command::CasCommand::new(store.clone())),
+        Box::new(commands::cas_read_command::CasReadCommand::new(
+            store.clone(),
+        )),
+        Box::new(commands::cas_ls_command::CasLsCommand::new(store.clone())),
         Box::new(commands::get_command::GetCommand::new(store.clone())),
         Box::new(commands::remove_command::RemoveCommand::new(store.clone())),
+        Box::new(commands::ttl_parse_command::TtlParseCommand::new()),
+        Box::new(commands::info_command::InfoCommand::new(store.clone())),
+        Box::new(commands::meta_command::MetaCommand::new(store.clone())),
+        Box::new(commands::export_command::ExportCommand::new(store.clone())),
+        Box::new(commands::import_command::ImportCommand::new(store.clone())),
     ])?;
 
     let mut commands = HashMap::new();
@@ -121,10 +130,18 @@ async fn register_command(
             store.clone(),
             frame.context_id,
         )),
+        Box::new(commands::follow_command::FollowCommand::new(
+            store.clone(),
+            frame.context_id,
+        )),
         Box::new(commands::head_command::HeadCommand::new(
             store.clone(),
             frame.context_id,
         )),
+        Box::new(commands::ls_command::LsCommand::new(
+            store.clone(),
+            frame.context_id,
+        )),
     ])?;
 
     // Parse the command configuration to extract return_options (ignore the process closure here)
@@ -160,13 +177,23 @@ async fn execute_command(command: Command, frame: &Frame, store: &Store) -> Resu
 
         let mut engine = command.engine;
 
-        engine.add_commands(vec![Box::new(
-            commands::append_command::AppendCommand::new(
+        engine.add_commands(vec![
+            Box::new(commands::append_command::AppendCommand::new(
+                store.clone(),
+                frame.context_id,
+                base_meta.clone(),
+            )),
+            Box::new(commands::set_command::SetCommand::new(
+                store.clone(),
+                frame.context_id,
+                base_meta.clone(),
+            )),
+            Box::new(commands::tee_command::TeeCommand::new(
                 store.clone(),
                 frame.context_id,
                 base_meta,
-            ),
-        )])?;
+            )),
+        ])?;
 
         let (closure, _) = parse_command_definition(&mut engine, &command.definition)?;
 
@@ -320,7 +320,7 @@ async fn setup_test_environment() -> (Store, Frame) {
 
     {
         let store = store.clone();
-        let _ = tokio::spawn(async move {
+        tokio::spawn(async move {
             crate::commands::serve::serve(store, engine.clone())
                 .await
                 .unwrap();
@@ -43,20 +43,15 @@ struct HandlerConfig {
     return_options: Option<ReturnOptions>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 enum ResumeFrom {
     Head,
+    #[default]
     Tail,
     After(Scru128Id),
 }
 
-impl Default for ResumeFrom {
-    fn default() -> Self {
-        Self::Tail
-    }
-}
-
 impl Handler {
     pub async fn new(
         id: Scru128Id,
@@ -76,6 +71,10 @@ impl Handler {
                 store.clone(),
                 context_id,
             )),
+            Box::new(commands::ls_command::LsCommand::new(
+                store.clone(),
+                context_id,
+            )),
             Box::new(commands::append_command_buffered::AppendCommand::new(
                 store.clone(),
                 output.clone(),
@@ -42,8 +42,17 @@ pub async fn serve(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     engine.add_commands(vec![
         Box::new(commands::cas_command::CasCommand::new(store.clone())),
+        Box::new(commands::cas_read_command::CasReadCommand::new(
+            store.clone(),
+        )),
+        Box::new(commands::cas_ls_command::CasLsCommand::new(store.clone())),
         Box::new(commands::get_command::GetCommand::new(store.clone())),
         Box::new(commands::remove_command::RemoveCommand::new(store.clone())),
+        Box::new(commands::ttl_parse_command::TtlParseCommand::new()),
+        Box::new(commands::info_command::InfoCommand::new(store.clone())),
+        Box::new(commands::meta_command::MetaCommand::new(store.clone())),
+        Box::new(commands::export_command::ExportCommand::new(store.clone())),
+        Box::new(commands::import_command::ImportCommand::new(store.clone())),
     ])?;
     engine.add_alias(".rm", ".remove")?;
 
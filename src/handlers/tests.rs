@@ -462,7 +462,7 @@ async fn test_return_options() {
     assert_eq!(meta["frame_id"], frame2.id.to_string());
 
     // Only newest response should be in store
-    store.wait_for_gc().await;
+    store.wait_for_gc().await.unwrap();
     let options = ReadOptions::default();
     let recver = store.read(options).await;
     use tokio_stream::StreamExt;
@@ -949,7 +949,11 @@ async fn test_handler_context_isolation() -> Result<(), Error> {
 
     // assert ZERO_CONTEXT has been isolated
     let frames: Vec<scru128::Scru128Id> = store
-        .read_sync(None, None, Some(crate::store::ZERO_CONTEXT))
+        .read_sync(
+            ReadOptions::builder()
+                .context_id(crate::store::ZERO_CONTEXT)
+                .build(),
+        )
         .map(|f| f.id)
         .collect();
     assert_eq!(
@@ -980,7 +984,7 @@ async fn setup_test_environment() -> (Store, TempDir) {
 
     {
         let store = store.clone();
-        let _ = tokio::spawn(async move {
+        tokio::spawn(async move {
             serve(store, engine).await.unwrap();
         });
     }
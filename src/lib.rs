@@ -7,4 +7,6 @@ pub mod listener;
 pub mod nu;
 pub mod store;
 pub mod tasks;
+pub mod thread_pool;
 pub mod trace;
+pub mod transforms;
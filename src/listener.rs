@@ -46,6 +46,19 @@ impl Listener {
         }
     }
 
+    /// Stops listening, removing the socket file for Unix listeners.
+    pub fn close(self) {
+        if let Listener::Unix(listener) = &self {
+            if let Some(path) = listener
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(|p| p.to_path_buf()))
+            {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
     #[allow(dead_code)]
     pub async fn connect(&self) -> io::Result<AsyncReadWriteBox> {
         match self {
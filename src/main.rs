@@ -7,7 +7,7 @@ use clap::{Parser, Subcommand};
 use tokio::io::AsyncWriteExt;
 
 use xs::nu;
-use xs::store::{parse_ttl, FollowOption, ReadOptions, Store, ZERO_CONTEXT};
+use xs::store::{parse_ttl, Codec, FollowOption, ReadOptions, Store, StoreOptions, ZERO_CONTEXT};
 
 #[derive(Parser, Debug)]
 #[clap(version)]
@@ -50,6 +50,90 @@ struct CommandServe {
     /// Can be [HOST]:PORT for TCP or <PATH> for Unix domain socket
     #[clap(long, value_parser, value_name = "LISTEN_ADDR")]
     expose: Option<String>,
+
+    /// Require `Authorization: Bearer <TOKEN>` on requests to the --expose address
+    #[clap(long, value_parser, requires = "expose")]
+    token: Option<String>,
+
+    /// Store CAS content compressed. Hashes and reads are unaffected
+    #[clap(long)]
+    compress_cas: bool,
+
+    /// Announce followers coming and going on xs.connect and xs.disconnect
+    #[clap(long)]
+    audit_subscribers: bool,
+
+    /// Leave an xs.remove tombstone for each frame removed
+    #[clap(long)]
+    tombstones: bool,
+
+    /// How frames are encoded on disk: json or bincode. Must match the codec the store was
+    /// created with
+    #[clap(long, value_parser = parse_codec, default_value = "json")]
+    codec: Codec,
+
+    /// Cap the CAS at this many bytes, evicting the least recently used content no frame refers
+    /// to once it's exceeded
+    #[clap(long)]
+    cas_max_bytes: Option<u64>,
+
+    /// Largest content, in bytes, a single append or CAS write can carry. Larger writes are
+    /// rejected
+    #[clap(long)]
+    max_content_bytes: Option<u64>,
+
+    /// Hash algorithm CAS content is stored under: sha256, sha384 or sha512. Content stored
+    /// under another algorithm still reads back
+    #[clap(long, value_parser = parse_cas_algorithm, default_value = "sha256")]
+    cas_algorithm: ssri::Algorithm,
+
+    /// Size in bytes of the block cache shared by the store's partitions (default 16 MiB)
+    #[clap(long)]
+    block_cache_bytes: Option<u64>,
+
+    /// Bytes of writes held in memory before they're flushed to disk (default 64 MiB)
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1024 * 1024..))]
+    write_buffer_bytes: Option<u64>,
+
+    /// How partitions compress blocks on disk: none or lz4 (default lz4). Fixed for a store when
+    /// it's created
+    #[clap(long, value_parser = parse_compression)]
+    compression: Option<fjall::CompressionType>,
+
+    /// Most connections the --expose address holds open at once. Connections past it are closed
+    #[clap(long, requires = "expose")]
+    max_connections: Option<usize>,
+
+    /// Milliseconds a client of the --expose address has to send a request's headers, and then
+    /// its body
+    #[clap(long, value_name = "MS", requires = "expose")]
+    request_timeout: Option<u64>,
+}
+
+fn parse_codec(s: &str) -> Result<Codec, String> {
+    Codec::parse(s).ok_or_else(|| format!("unknown codec: {} (expected json or bincode)", s))
+}
+
+fn parse_cas_algorithm(s: &str) -> Result<ssri::Algorithm, String> {
+    match s.parse() {
+        Ok(
+            algorithm @ (ssri::Algorithm::Sha256
+            | ssri::Algorithm::Sha384
+            | ssri::Algorithm::Sha512),
+        ) => Ok(algorithm),
+        _ => Err(format!(
+            "unknown hash algorithm: {} (expected sha256, sha384 or sha512)",
+            s
+        )),
+    }
+}
+
+fn parse_compression(s: &str) -> Result<fjall::CompressionType, String> {
+    match s {
+        "none" => Ok(fjall::CompressionType::None),
+        "lz4" => Ok(fjall::CompressionType::Lz4),
+        _ => Err(format!("unknown compression: {} (expected none or lz4)", s)),
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -89,6 +173,14 @@ struct CommandCat {
     /// Retrieve all frames, across contexts
     #[clap(long, short = 'a')]
     all: bool,
+
+    /// Only retrieve frames whose topic matches; `*` matches any run of characters
+    #[clap(long, short = 'T')]
+    topic: Option<String>,
+
+    /// Only retrieve frames on one of these topics, given comma-separated
+    #[clap(long, value_delimiter = ',')]
+    topics: Option<Vec<String>>,
 }
 
 #[derive(Parser, Debug)]
@@ -200,7 +292,21 @@ async fn serve(args: CommandServe) -> Result<(), Box<dyn std::error::Error + Sen
 
     tracing::trace!("Starting server with path: {:?}", args.path);
 
-    let store = Store::new(args.path);
+    let store = Store::open(
+        args.path,
+        StoreOptions::builder()
+            .compress_cas(args.compress_cas)
+            .audit_subscribers(args.audit_subscribers)
+            .tombstones(args.tombstones)
+            .codec(args.codec)
+            .cas_algorithm(args.cas_algorithm)
+            .maybe_cas_max_bytes(args.cas_max_bytes)
+            .maybe_max_content_bytes(args.max_content_bytes)
+            .maybe_block_cache_bytes(args.block_cache_bytes)
+            .maybe_write_buffer_bytes(args.write_buffer_bytes)
+            .maybe_compression(args.compression)
+            .build(),
+    )?;
     let engine = nu::Engine::new()?;
 
     {
@@ -234,8 +340,34 @@ async fn serve(args: CommandServe) -> Result<(), Box<dyn std::error::Error + Sen
         });
     }
 
-    // TODO: graceful shutdown
-    xs::api::serve(store, engine.clone(), args.expose).await?;
+    {
+        let store = store.clone();
+        let engine = engine.clone();
+        let pool = xs::thread_pool::ThreadPool::new(
+            std::thread::available_parallelism().map_or(4, |n| n.get()),
+        );
+        tokio::spawn(async move {
+            let _ = xs::transforms::serve(store, engine, pool).await;
+        });
+    }
+
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    {
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                shutdown.cancel();
+            }
+        });
+    }
+
+    let options = xs::api::ServeOptions::builder()
+        .maybe_expose(args.expose)
+        .maybe_token(args.token)
+        .maybe_max_connections(args.max_connections)
+        .maybe_request_timeout(args.request_timeout.map(std::time::Duration::from_millis))
+        .build();
+    xs::api::serve(store, engine.clone(), options, shutdown).await?;
 
     Ok(())
 }
@@ -268,6 +400,8 @@ async fn cat(args: CommandCat) -> Result<(), Box<dyn std::error::Error + Send +
         .maybe_last_id(last_id)
         .maybe_limit(args.limit.map(|l| l as usize))
         .maybe_context_id(context_id)
+        .maybe_topic(args.topic)
+        .maybe_topics(args.topics.map(|topics| topics.into_iter().collect()))
         .build();
     let mut receiver = xs::client::cat(&args.addr, options, args.sse).await?;
     let mut stdout = tokio::io::stdout();
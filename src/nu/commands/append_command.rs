@@ -51,6 +51,30 @@ impl Command for AppendCommand {
                 "context ID (defaults to system context)",
                 None,
             )
+            .named(
+                "hash",
+                SyntaxShape::String,
+                "reference content already in the CAS by its hash, instead of writing the input",
+                None,
+            )
+            .named(
+                "file",
+                SyntaxShape::Filepath,
+                "stream the content from this file, instead of the input",
+                None,
+            )
+            .named(
+                "schema",
+                SyntaxShape::String,
+                "name of a registered schema the meta must match",
+                None,
+            )
+            .named(
+                "if-head",
+                SyntaxShape::OneOf(vec![SyntaxShape::String, SyntaxShape::Nothing]),
+                "only append if this is the ID of the topic's latest frame, or null for an empty topic",
+                None,
+            )
             .category(Category::Experimental)
     }
 
@@ -102,7 +126,79 @@ impl Command for AppendCommand {
             None => None,
         };
 
-        let hash = util::write_pipeline_to_cas(input, &store, span)?;
+        let hash: Option<String> = call.get_flag(engine_state, stack, "hash")?;
+        let file: Option<String> = call.get_flag(engine_state, stack, "file")?;
+        let conflict = match (&hash, &file) {
+            (Some(_), Some(_)) => Some("--hash can't be combined with --file"),
+            (Some(_), None) if !input.is_nothing() => {
+                Some("--hash can't be combined with pipeline input")
+            }
+            (None, Some(_)) if !input.is_nothing() => {
+                Some("--file can't be combined with pipeline input")
+            }
+            _ => None,
+        };
+        if let Some(msg) = conflict {
+            return Err(ShellError::GenericError {
+                error: "Conflicting content".into(),
+                msg: msg.into(),
+                span: Some(call.head),
+                help: None,
+                inner: vec![],
+            });
+        }
+
+        if let Some(file) = &file {
+            // Guessed from the extension: the file's content never passes through Nushell
+            if let (Some(mime), JsonValue::Object(meta)) =
+                (mime_guess::from_path(file).first(), &mut final_meta)
+            {
+                meta.entry("content_type")
+                    .or_insert_with(|| mime.essence_str().into());
+            }
+        }
+
+        let hash = match (hash, file) {
+            (None, Some(file)) => {
+                let path = engine_state.cwd(Some(stack))?.join(&file);
+                let mut reader =
+                    std::fs::File::open(&path).map_err(|e| ShellError::GenericError {
+                        error: "Can't open file".into(),
+                        msg: format!("{}: {}", file, e),
+                        span: Some(call.head),
+                        help: None,
+                        inner: vec![],
+                    })?;
+                let mut writer = store.cas_writer_sync()?;
+                std::io::copy(&mut reader, &mut writer)
+                    .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
+                Some(
+                    writer
+                        .commit()
+                        .map_err(|e| ShellError::IOError { msg: e.to_string() })?,
+                )
+            }
+            (Some(hash), _) => {
+                let hash: ssri::Integrity = hash.parse().map_err(|e| ShellError::GenericError {
+                    error: "Malformed hash".into(),
+                    msg: format!("{}: {}", hash, e),
+                    span: Some(call.head),
+                    help: Some("Expected an ssri integrity string, e.g. sha256-...".into()),
+                    inner: vec![],
+                })?;
+                if !store.cas_exists_sync(&hash)? {
+                    return Err(ShellError::GenericError {
+                        error: "Content not found".into(),
+                        msg: format!("No content found for hash: {}", hash),
+                        span: Some(call.head),
+                        help: None,
+                        inner: vec![],
+                    });
+                }
+                Some(hash)
+            }
+            (None, None) => util::write_pipeline_to_cas(input, &store, span)?,
+        };
         let context_str: Option<String> = call.get_flag(engine_state, stack, "context")?;
         let context_id = context_str
             .map(|ctx| ctx.parse::<scru128::Scru128Id>())
@@ -116,13 +212,37 @@ impl Command for AppendCommand {
             })?
             .unwrap_or(self.context_id);
 
-        let frame = store.append(
-            Frame::builder(topic, context_id)
-                .maybe_hash(hash)
-                .meta(final_meta)
-                .maybe_ttl(ttl)
-                .build(),
-        )?;
+        let schema: Option<String> = call.get_flag(engine_state, stack, "schema")?;
+
+        // Some(Nothing) when the flag is given as null, for an empty topic
+        let if_head: Option<Value> = call.get_flag(engine_state, stack, "if-head")?;
+        let expected_head = if_head
+            .map(|value| match value {
+                Value::Nothing { .. } => Ok(None),
+                value => {
+                    let span = value.span();
+                    value
+                        .coerce_into_string()?
+                        .parse::<scru128::Scru128Id>()
+                        .map(Some)
+                        .map_err(|e| ShellError::TypeMismatch {
+                            err_message: format!("Invalid ID format: {}", e),
+                            span,
+                        })
+                }
+            })
+            .transpose()?;
+
+        let frame = Frame::builder(topic, context_id)
+            .maybe_hash(hash)
+            .meta(final_meta)
+            .maybe_ttl(ttl)
+            .maybe_schema(schema)
+            .build();
+        let frame = match expected_head {
+            Some(expected_head) => store.append_if(frame, expected_head)?,
+            None => store.append(frame)?,
+        };
 
         Ok(PipelineData::Value(
             util::frame_to_value(&frame, span),
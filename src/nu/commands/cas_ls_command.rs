@@ -0,0 +1,67 @@
+use std::collections::HashSet;
+
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{Category, PipelineData, Record, ShellError, Signature, Type, Value};
+
+use crate::store::{ReadOptions, Store};
+
+#[derive(Clone)]
+pub struct CasLsCommand {
+    store: Store,
+}
+
+impl CasLsCommand {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Command for CasLsCommand {
+    fn name(&self) -> &str {
+        ".cas-ls"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".cas-ls")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "List the blobs in the CAS with their size on disk, and whether any frame still refers to them"
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let referenced: HashSet<ssri::Integrity> = self
+            .store
+            .read_sync(ReadOptions::default())
+            .filter_map(|frame| frame.hash)
+            .collect();
+
+        let rows = self
+            .store
+            .cas_list()?
+            .into_iter()
+            .map(|entry| {
+                let mut record = Record::new();
+                record.push(
+                    "referenced",
+                    Value::bool(referenced.contains(&entry.hash), span),
+                );
+                record.push("hash", Value::string(entry.hash.to_string(), span));
+                record.push("size", Value::filesize(entry.size as i64, span));
+                record.push("compressed", Value::bool(entry.compressed, span));
+                Value::record(record, span)
+            })
+            .collect();
+
+        Ok(PipelineData::Value(Value::list(rows, span), None))
+    }
+}
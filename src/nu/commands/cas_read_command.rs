@@ -0,0 +1,75 @@
+use nu_engine::CallExt;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{Category, PipelineData, ShellError, Signature, SyntaxShape, Type, Value};
+
+use crate::store::Store;
+
+#[derive(Clone)]
+pub struct CasReadCommand {
+    store: Store,
+}
+
+impl CasReadCommand {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Command for CasReadCommand {
+    fn name(&self) -> &str {
+        ".cas-read"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".cas-read")
+            .input_output_types(vec![
+                (Type::Nothing, Type::String),
+                (Type::Nothing, Type::Binary),
+            ])
+            .required("hash", SyntaxShape::String, "hash of the content to read")
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Read content from the CAS by hash, as a string if it's valid UTF-8, otherwise as binary"
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let hash: String = call.req(engine_state, stack, 0)?;
+        let hash: ssri::Integrity = hash.parse().map_err(|e| ShellError::GenericError {
+            error: "Malformed hash".into(),
+            msg: format!("{}: {}", hash, e),
+            span: Some(span),
+            help: Some("Expected an ssri integrity string, e.g. sha256-...".into()),
+            inner: vec![],
+        })?;
+
+        let contents = self.store.cas_read_sync(&hash).map_err(|e| {
+            if e.is_not_found() {
+                ShellError::GenericError {
+                    error: "Content not found".into(),
+                    msg: format!("No content found for hash: {}", hash),
+                    span: Some(span),
+                    help: None,
+                    inner: vec![],
+                }
+            } else {
+                ShellError::IOError { msg: e.to_string() }
+            }
+        })?;
+
+        let value = match String::from_utf8(contents) {
+            Ok(string) => Value::string(string, span),
+            Err(e) => Value::binary(e.into_bytes(), span),
+        };
+
+        Ok(PipelineData::Value(value, None))
+    }
+}
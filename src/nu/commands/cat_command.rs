@@ -1,8 +1,10 @@
 use nu_engine::CallExt;
 use nu_protocol::engine::{Call, Command, EngineState, Stack};
-use nu_protocol::{Category, PipelineData, ShellError, Signature, SyntaxShape, Type};
+use nu_protocol::{
+    ByteStream, ByteStreamType, Category, PipelineData, ShellError, Signature, SyntaxShape, Type,
+};
 
-use crate::store::Store;
+use crate::store::{ReadOptions, Store};
 
 #[derive(Clone)]
 pub struct CatCommand {
@@ -36,6 +38,16 @@ impl Command for CatCommand {
                 "start from a specific frame ID",
                 None,
             )
+            .switch(
+                "json",
+                "emit each frame as a line of JSON, as it's stored, instead of a record",
+                None,
+            )
+            .switch(
+                "raw",
+                "emit the frames' content, one after another, instead of the frames. Frames without content are skipped",
+                None,
+            )
             .category(Category::Experimental)
     }
 
@@ -51,6 +63,8 @@ impl Command for CatCommand {
         _input: PipelineData,
     ) -> Result<PipelineData, ShellError> {
         let limit: Option<usize> = call.get_flag(engine_state, stack, "limit")?;
+        let json = call.has_flag(engine_state, stack, "json")?;
+        let raw = call.has_flag(engine_state, stack, "raw")?;
 
         let last_id: Option<String> = call.get_flag(engine_state, stack, "last-id")?;
         let last_id: Option<scru128::Scru128Id> = last_id
@@ -59,16 +73,46 @@ impl Command for CatCommand {
 
         let frames = self
             .store
-            .read_sync(last_id.as_ref(), limit, Some(self.context_id))
+            .read_sync(
+                ReadOptions::builder()
+                    .maybe_last_id(last_id)
+                    .maybe_limit(limit)
+                    .context_id(self.context_id)
+                    .build(),
+            )
             .collect::<Vec<_>>();
 
+        // Each frame's content is only read once the stream gets to it
+        if raw {
+            let store = self.store.clone();
+            let content = frames
+                .into_iter()
+                .filter_map(|frame| frame.hash)
+                .map(move |hash| Ok(store.cas_read_sync(&hash)?));
+            return Ok(PipelineData::ByteStream(
+                ByteStream::from_result_iter(
+                    content,
+                    call.head,
+                    engine_state.signals().clone(),
+                    ByteStreamType::Unknown,
+                ),
+                None,
+            ));
+        }
+
         use nu_protocol::Value;
 
         let output = Value::list(
             frames
                 .into_iter()
-                .map(|frame| crate::nu::util::frame_to_value(&frame, call.head))
-                .collect(),
+                .map(|frame| {
+                    if json {
+                        crate::nu::util::frame_to_json_value(&frame, call.head)
+                    } else {
+                        Ok(crate::nu::util::frame_to_value(&frame, call.head))
+                    }
+                })
+                .collect::<Result<_, _>>()?,
             call.head,
         );
 
@@ -0,0 +1,126 @@
+use std::collections::{HashSet, VecDeque};
+
+use base64::Engine;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{
+    ByteStream, ByteStreamType, Category, PipelineData, ShellError, Signature, Type,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::store::{Frame, ReadOptions, Store};
+
+// How many frames are read from the store at a time as the output is consumed
+const PAGE_SIZE: usize = 1000;
+
+/// A line of `.export` output carrying CAS content, base64 encoded. It comes ahead of the first
+/// frame that refers to the content, so `.import` has it stored by the time it inserts the frame.
+#[derive(Serialize, Deserialize)]
+pub struct ContentLine {
+    pub cas: ssri::Integrity,
+    pub content: String,
+}
+
+#[derive(Clone)]
+pub struct ExportCommand {
+    store: Store,
+}
+
+impl ExportCommand {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Command for ExportCommand {
+    fn name(&self) -> &str {
+        ".export"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".export")
+            .input_output_types(vec![(Type::Nothing, Type::String)])
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Streams every frame in the store, and the content they refer to, as newline-delimited JSON, for .import"
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let store = self.store.clone();
+        let mut last_id = None;
+        let mut page = Vec::new().into_iter();
+        // Each piece of content goes out once, however many frames refer to it
+        let mut exported = HashSet::new();
+        let mut pending = VecDeque::new();
+        let lines = std::iter::from_fn(move || {
+            if let Some(line) = pending.pop_front() {
+                return Some(Ok(line));
+            }
+            if page.len() == 0 {
+                let frames: Vec<_> = store
+                    .read_sync(
+                        ReadOptions::builder()
+                            .maybe_last_id(last_id)
+                            .limit(PAGE_SIZE)
+                            .build(),
+                    )
+                    .collect();
+                last_id = frames.last().map(|frame| frame.id);
+                page = frames.into_iter();
+            }
+            let frame = page.next()?;
+            Some(
+                export_frame(&store, frame, &mut exported, &mut pending)
+                    .map(|()| pending.pop_front().unwrap()),
+            )
+        });
+
+        Ok(PipelineData::ByteStream(
+            ByteStream::from_result_iter(
+                lines,
+                call.head,
+                engine_state.signals().clone(),
+                ByteStreamType::String,
+            ),
+            None,
+        ))
+    }
+}
+
+// Queues the lines for `frame`: its content, unless that's already gone out, then the frame itself
+fn export_frame(
+    store: &Store,
+    frame: Frame,
+    exported: &mut HashSet<ssri::Integrity>,
+    pending: &mut VecDeque<Vec<u8>>,
+) -> Result<(), ShellError> {
+    if let Some(hash) = frame.hash.clone() {
+        if exported.insert(hash.clone()) {
+            match store.cas_read_sync(&hash) {
+                Ok(content) => pending.push_back(to_line(&ContentLine {
+                    cas: hash,
+                    content: base64::prelude::BASE64_STANDARD.encode(content),
+                })?),
+                // The frame goes out regardless, as it's stored
+                Err(e) if e.is_not_found() => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    pending.push_back(to_line(&frame)?);
+    Ok(())
+}
+
+fn to_line(value: &impl Serialize) -> Result<Vec<u8>, ShellError> {
+    let mut line =
+        serde_json::to_vec(value).map_err(|e| ShellError::IOError { msg: e.to_string() })?;
+    line.push(b'\n');
+    Ok(line)
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use nu_engine::CallExt;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{
+    Category, ListStream, PipelineData, ShellError, Signature, SyntaxShape, Type, Value,
+};
+
+use crate::store::{FollowOption, Frame, ReadOptions, Store};
+
+// How often the subscription wakes up to check for an interrupt, or the timeout, when no frames
+// are arriving
+const HEARTBEAT: Duration = Duration::from_millis(250);
+
+// Commands run within the server, out of reach of Ctrl-C, so a follow has to end on its own
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+pub struct FollowCommand {
+    store: Store,
+    context_id: scru128::Scru128Id,
+}
+
+impl FollowCommand {
+    pub fn new(store: Store, context_id: scru128::Scru128Id) -> Self {
+        Self { store, context_id }
+    }
+}
+
+impl Command for FollowCommand {
+    fn name(&self) -> &str {
+        ".follow"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".follow")
+            .input_output_types(vec![(Type::Nothing, Type::list(Type::String))])
+            .optional(
+                "topic",
+                SyntaxShape::String,
+                "only follow frames whose topic matches",
+            )
+            .named(
+                "limit",
+                SyntaxShape::Int,
+                "stop after this many frames",
+                None,
+            )
+            .named(
+                "timeout",
+                SyntaxShape::Duration,
+                "stop after following for this long (default: 1min)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Follows frames appended from now on, a line per frame, until interrupted, or until the limit or timeout is reached"
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let topic: Option<String> = call.opt(engine_state, stack, 0)?;
+        let limit: Option<usize> = call.get_flag(engine_state, stack, "limit")?;
+        let timeout = match call.get_flag::<Value>(engine_state, stack, "timeout")? {
+            Some(timeout) => Duration::from_nanos(timeout.as_duration()?.max(0) as u64),
+            None => DEFAULT_TIMEOUT,
+        };
+
+        let handle =
+            tokio::runtime::Handle::try_current().map_err(|e| ShellError::GenericError {
+                error: "No async runtime".into(),
+                msg: e.to_string(),
+                span: Some(span),
+                help: Some(".follow has to run within the xs server".into()),
+                inner: vec![],
+            })?;
+
+        let options = ReadOptions::builder()
+            .follow(FollowOption::WithHeartbeat(HEARTBEAT))
+            .tail(true)
+            .context_id(self.context_id)
+            .maybe_topic(topic)
+            .build();
+        let mut recver = handle.block_on(self.store.read(options));
+
+        // Dropping the stream, once it's interrupted or its consumer is done, drops the
+        // receiver, which unsubscribes
+        let signals = engine_state.signals().clone();
+        let iter = {
+            let signals = signals.clone();
+            let deadline = std::time::Instant::now() + timeout;
+            std::iter::from_fn(move || loop {
+                let frame = recver.blocking_recv()?;
+                if signals.interrupted() || std::time::Instant::now() >= deadline {
+                    return None;
+                }
+                if frame.topic != "xs.pulse" {
+                    return Some(Value::string(format_frame(&frame), span));
+                }
+            })
+            .take(limit.unwrap_or(usize::MAX))
+        };
+
+        Ok(PipelineData::ListStream(
+            ListStream::new(iter, span, signals),
+            None,
+        ))
+    }
+}
+
+fn format_frame(frame: &Frame) -> String {
+    let mut line = format!("{} {}", frame.id, frame.topic);
+    if let Some(hash) = &frame.hash {
+        line.push(' ');
+        line.push_str(&hash.to_string());
+    }
+    if let Some(meta) = &frame.meta {
+        line.push(' ');
+        line.push_str(&meta.to_string());
+    }
+    line
+}
@@ -25,6 +25,11 @@ impl Command for GetCommand {
         Signature::build(".get")
             .input_output_types(vec![(Type::Nothing, Type::Any)])
             .required("id", SyntaxShape::String, "The ID of the frame to retrieve")
+            .switch(
+                "json",
+                "emit the frame as JSON, as it's stored, instead of a record",
+                None,
+            )
             .category(Category::Experimental)
     }
 
@@ -45,13 +50,17 @@ impl Command for GetCommand {
             span: call.span(),
         })?;
 
+        let json = call.has_flag(engine_state, stack, "json")?;
+
         let store = self.store.clone();
 
-        if let Some(frame) = store.get(&id) {
-            Ok(PipelineData::Value(
-                util::frame_to_value(&frame, call.head),
-                None,
-            ))
+        if let Some(frame) = store.get(&id)? {
+            let value = if json {
+                util::frame_to_json_value(&frame, call.head)?
+            } else {
+                util::frame_to_value(&frame, call.head)
+            };
+            Ok(PipelineData::Value(value, None))
         } else {
             Err(ShellError::GenericError {
                 error: "Frame not found".into(),
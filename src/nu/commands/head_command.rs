@@ -32,6 +32,12 @@ impl Command for HeadCommand {
                 "context ID (defaults to system context)",
                 None,
             )
+            .named(
+                "key",
+                SyntaxShape::String,
+                "the most recent frame set for this key with `.set`",
+                None,
+            )
             .category(Category::Experimental)
     }
 
@@ -60,9 +66,26 @@ impl Command for HeadCommand {
         } else {
             self.context_id
         };
+        let key: Option<String> = call.get_flag(engine_state, stack, "key")?;
         let span = call.head;
 
-        if let Some(frame) = self.store.head(&topic, context_id) {
+        let frame = match key {
+            Some(key) => self
+                .store
+                .latest_by_key(&topic, context_id)?
+                .into_iter()
+                .find(|frame| {
+                    frame
+                        .meta
+                        .as_ref()
+                        .and_then(|meta| meta.get("key"))
+                        .and_then(|k| k.as_str())
+                        == Some(key.as_str())
+                }),
+            None => self.store.head(&topic, context_id)?,
+        };
+
+        if let Some(frame) = frame {
             Ok(PipelineData::Value(
                 util::frame_to_value(&frame, span),
                 None,
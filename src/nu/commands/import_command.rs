@@ -0,0 +1,112 @@
+use std::io::BufRead;
+
+use base64::Engine;
+use nu_engine::CallExt;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{Category, PipelineData, ShellError, Signature, Type, Value};
+
+use crate::nu::commands::export_command::ContentLine;
+use crate::store::Store;
+
+#[derive(Clone)]
+pub struct ImportCommand {
+    store: Store,
+}
+
+impl ImportCommand {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Command for ImportCommand {
+    fn name(&self) -> &str {
+        ".import"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".import")
+            .input_output_types(vec![
+                (Type::String, Type::Int),
+                (Type::Binary, Type::Int),
+                (Type::Any, Type::Int),
+            ])
+            .switch(
+                "overwrite",
+                "replace frames whose ids are already in the store, instead of failing",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Inserts frames, and the content they refer to, from newline-delimited JSON, as output by .export, keeping their ids"
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let overwrite = call.has_flag(engine_state, stack, "overwrite")?;
+
+        let count = match input {
+            PipelineData::Value(Value::String { val, .. }, _) => {
+                self.import(val.as_bytes(), overwrite)?
+            }
+            PipelineData::Value(Value::Binary { val, .. }, _) => {
+                self.import(val.as_slice(), overwrite)?
+            }
+            PipelineData::ByteStream(stream, ..) => match stream.reader() {
+                Some(reader) => self.import(std::io::BufReader::new(reader), overwrite)?,
+                None => 0,
+            },
+            PipelineData::Empty | PipelineData::Value(Value::Nothing { .. }, _) => 0,
+            input => {
+                return Err(ShellError::PipelineMismatch {
+                    exp_input_type: "string or binary".into(),
+                    dst_span: span,
+                    src_span: input.span().unwrap_or(span),
+                })
+            }
+        };
+
+        Ok(PipelineData::Value(Value::int(count as i64, span), None))
+    }
+}
+
+impl ImportCommand {
+    // Stores content as it comes, and inserts frames a line at a time, returning how many frames
+    // were inserted. Lines that aren't content are frames, as `Store::import` reads them.
+    fn import(&self, r: impl BufRead, overwrite: bool) -> Result<usize, ShellError> {
+        let mut count = 0;
+        for line in r.lines() {
+            let line = line.map_err(|e| ShellError::IOError { msg: e.to_string() })?;
+            let Ok(ContentLine { cas, content }) = serde_json::from_str(&line) else {
+                count += self.store.import(line.as_bytes(), overwrite)?;
+                continue;
+            };
+            let content = base64::prelude::BASE64_STANDARD
+                .decode(content)
+                .map_err(|e| ShellError::IOError {
+                    msg: format!("invalid content for {}: {}", cas, e),
+                })?;
+            cas.check(&content).map_err(|e| ShellError::IOError {
+                msg: format!("content doesn't match its hash: {}", e),
+            })?;
+            let hash = self.store.cas_insert_sync(&content)?;
+            if cas.matches(&hash).is_none() {
+                return Err(ShellError::IOError {
+                    msg: format!(
+                        "content for {} was stored as {}: the stores hash content with different algorithms",
+                        cas, hash
+                    ),
+                });
+            }
+        }
+        Ok(count)
+    }
+}
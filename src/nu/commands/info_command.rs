@@ -0,0 +1,62 @@
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{Category, PipelineData, Record, ShellError, Signature, Type, Value};
+
+use crate::store::Store;
+
+#[derive(Clone)]
+pub struct InfoCommand {
+    store: Store,
+}
+
+impl InfoCommand {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Command for InfoCommand {
+    fn name(&self) -> &str {
+        ".info"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".info")
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Describe the store: its path, first and last frame ids, frame count and uptime"
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let info = self.store.info()?;
+
+        let id_value = |id: Option<scru128::Scru128Id>| match id {
+            Some(id) => Value::string(id.to_string(), span),
+            None => Value::nothing(span),
+        };
+        let started_at: chrono::DateTime<chrono::Utc> = info.started_at.into();
+        let uptime = info.started_at.elapsed().unwrap_or_default();
+
+        let mut record = Record::new();
+        record.push(
+            "path",
+            Value::string(info.path.to_string_lossy().into_owned(), span),
+        );
+        record.push("first_id", id_value(info.first_id));
+        record.push("last_id", id_value(info.last_id));
+        record.push("frame_count", Value::int(info.frame_count as i64, span));
+        record.push("started_at", Value::date(started_at.fixed_offset(), span));
+        record.push("uptime", Value::duration(uptime.as_nanos() as i64, span));
+
+        Ok(PipelineData::Value(Value::record(record, span), None))
+    }
+}
@@ -0,0 +1,57 @@
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{Category, PipelineData, Record, ShellError, Signature, Type, Value};
+
+use crate::store::Store;
+
+#[derive(Clone)]
+pub struct LsCommand {
+    store: Store,
+    context_id: scru128::Scru128Id,
+}
+
+impl LsCommand {
+    pub fn new(store: Store, context_id: scru128::Scru128Id) -> Self {
+        Self { store, context_id }
+    }
+}
+
+impl Command for LsCommand {
+    fn name(&self) -> &str {
+        ".ls"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".ls")
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "List the distinct topics in the stream, with their frame counts and latest frame ID"
+    }
+
+    fn run(
+        &self,
+        _engine_state: &EngineState,
+        _stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+
+        let rows = self
+            .store
+            .topic_summaries(self.context_id)?
+            .into_iter()
+            .map(|summary| {
+                let mut record = Record::new();
+                record.push("topic", Value::string(summary.topic, span));
+                record.push("count", Value::int(summary.count as i64, span));
+                record.push("last_id", Value::string(summary.last_id.to_string(), span));
+                Value::record(record, span)
+            })
+            .collect();
+
+        Ok(PipelineData::Value(Value::list(rows, span), None))
+    }
+}
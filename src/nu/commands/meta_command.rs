@@ -0,0 +1,89 @@
+use nu_engine::CallExt;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{Category, PipelineData, ShellError, Signature, SyntaxShape, Type, Value};
+
+use crate::nu::util;
+use crate::store::Store;
+
+#[derive(Clone)]
+pub struct MetaCommand {
+    store: Store,
+}
+
+impl MetaCommand {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl Command for MetaCommand {
+    fn name(&self) -> &str {
+        ".meta"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".meta")
+            .input_output_types(vec![
+                (Type::Nothing, Type::Any),
+                (Type::record(), Type::Any),
+            ])
+            .required("id", SyntaxShape::String, "The ID of the frame to patch")
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Patches a frame's meta with the record piped in, by appending an xs.meta frame, and returns the frame with its patches merged in"
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let id_str: String = call.req(engine_state, stack, 0)?;
+        let id = id_str.parse().map_err(|e| ShellError::TypeMismatch {
+            err_message: format!("Invalid ID format: {}", e),
+            span: call.span(),
+        })?;
+
+        match input {
+            PipelineData::Empty | PipelineData::Value(Value::Nothing { .. }, _) => {}
+            PipelineData::Value(value @ Value::Record { .. }, _) => {
+                self.store.patch_meta(&id, util::value_to_json(&value))?;
+            }
+            PipelineData::Value(value, _) => {
+                return Err(ShellError::PipelineMismatch {
+                    exp_input_type: format!(
+                        "expected: record or nothing :: received: {:?}",
+                        value.get_type()
+                    ),
+                    dst_span: call.head,
+                    src_span: value.span(),
+                })
+            }
+            _ => {
+                return Err(ShellError::PipelineMismatch {
+                    exp_input_type: "expected: record or nothing :: received: stream".into(),
+                    dst_span: call.head,
+                    src_span: call.head,
+                })
+            }
+        }
+
+        match self.store.get_patched(&id)? {
+            Some(frame) => Ok(PipelineData::Value(
+                util::frame_to_value(&frame, call.head),
+                None,
+            )),
+            None => Err(ShellError::GenericError {
+                error: "Frame not found".into(),
+                msg: format!("No frame found with ID: {}", id_str),
+                span: Some(call.head),
+                help: None,
+                inner: vec![],
+            }),
+        }
+    }
+}
@@ -1,7 +1,18 @@
 pub mod append_command;
 pub mod append_command_buffered;
 pub mod cas_command;
+pub mod cas_ls_command;
+pub mod cas_read_command;
 pub mod cat_command;
+pub mod export_command;
+pub mod follow_command;
 pub mod get_command;
 pub mod head_command;
+pub mod import_command;
+pub mod info_command;
+pub mod ls_command;
+pub mod meta_command;
 pub mod remove_command;
+pub mod set_command;
+pub mod tee_command;
+pub mod ttl_parse_command;
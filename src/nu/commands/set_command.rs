@@ -0,0 +1,84 @@
+use nu_engine::CallExt;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{Category, PipelineData, ShellError, Signature, SyntaxShape, Type};
+
+use serde_json::Value as JsonValue;
+
+use crate::nu::util;
+use crate::store::{Frame, Store};
+
+#[derive(Clone)]
+pub struct SetCommand {
+    store: Store,
+    context_id: scru128::Scru128Id,
+    base_meta: JsonValue,
+}
+
+impl SetCommand {
+    pub fn new(store: Store, context_id: scru128::Scru128Id, base_meta: JsonValue) -> Self {
+        Self {
+            store,
+            context_id,
+            base_meta,
+        }
+    }
+}
+
+impl Command for SetCommand {
+    fn name(&self) -> &str {
+        ".set"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".set")
+            .input_output_types(vec![(Type::Any, Type::Any)])
+            .required("topic", SyntaxShape::String, "topic holding the keys")
+            .required("key", SyntaxShape::String, "key to set")
+            .switch(
+                "prune",
+                "remove the frames previously set for this key",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Appends its input to the given topic as the latest value for a key, superseding the key's earlier frames. Read the values back with `.head <topic> --key <key>`."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+
+        let topic: String = call.req(engine_state, stack, 0)?;
+        let key: String = call.req(engine_state, stack, 1)?;
+        let prune = call.has_flag(engine_state, stack, "prune")?;
+
+        let mut meta = self.base_meta.clone();
+        if let JsonValue::Object(obj) = &mut meta {
+            obj.insert("key".into(), JsonValue::String(key.clone()));
+        }
+
+        let hash = util::write_pipeline_to_cas(input, &self.store, span)?;
+        let frame = self.store.append(
+            Frame::builder(topic.clone(), self.context_id)
+                .maybe_hash(hash)
+                .meta(meta)
+                .build(),
+        )?;
+
+        if prune {
+            self.store.prune_key(&topic, self.context_id, &key)?;
+        }
+
+        Ok(PipelineData::Value(
+            util::frame_to_value(&frame, span),
+            None,
+        ))
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::{Read, Write};
+
+use nu_engine::CallExt;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{
+    ByteStream, Category, ListStream, PipelineData, Reader, ShellError, Signature, SyntaxShape,
+    Type, Value,
+};
+
+use serde_json::Value as JsonValue;
+
+use crate::nu::util;
+use crate::store::{CasSyncWriter, Frame, Store};
+
+#[derive(Clone)]
+pub struct TeeCommand {
+    store: Store,
+    context_id: scru128::Scru128Id,
+    base_meta: JsonValue,
+}
+
+impl TeeCommand {
+    pub fn new(store: Store, context_id: scru128::Scru128Id, base_meta: JsonValue) -> Self {
+        Self {
+            store,
+            context_id,
+            base_meta,
+        }
+    }
+}
+
+impl Command for TeeCommand {
+    fn name(&self) -> &str {
+        ".tee"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".tee")
+            .input_output_types(vec![(Type::Any, Type::Any)])
+            .required("topic", SyntaxShape::String, "topic to append to")
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Appends its input to the given topic while passing it through unchanged: a frame per item of a list stream, or a single frame for a value or byte stream. Byte streams are appended once they've been read to the end."
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let topic: String = call.req(engine_state, stack, 0)?;
+
+        match input {
+            PipelineData::Empty => Ok(PipelineData::Empty),
+            PipelineData::Value(value, metadata) => {
+                self.append_value(&topic, &value)?;
+                Ok(PipelineData::Value(value, metadata))
+            }
+            // Each item is appended as it passes through
+            PipelineData::ListStream(stream, metadata) => {
+                let command = self.clone();
+                let signals = engine_state.signals().clone();
+                let iter = stream.into_iter().map(move |value| {
+                    if value.is_error() {
+                        return value;
+                    }
+                    match command.append_value(&topic, &value) {
+                        Ok(_) => value,
+                        Err(e) => Value::error(e, span),
+                    }
+                });
+                Ok(PipelineData::ListStream(
+                    ListStream::new(iter, span, signals),
+                    metadata,
+                ))
+            }
+            PipelineData::ByteStream(stream, metadata) => {
+                let type_ = stream.type_();
+                let signals = engine_state.signals().clone();
+                let Some(reader) = stream.reader() else {
+                    return Ok(PipelineData::Empty);
+                };
+                let reader = TeeReader {
+                    reader,
+                    writer: Some(self.store.cas_writer_sync()?),
+                    command: self.clone(),
+                    topic,
+                };
+                Ok(PipelineData::ByteStream(
+                    ByteStream::read(reader, span, signals, type_),
+                    metadata,
+                ))
+            }
+        }
+    }
+}
+
+impl TeeCommand {
+    fn append_value(&self, topic: &str, value: &Value) -> Result<Frame, ShellError> {
+        let hash = match value {
+            Value::Nothing { .. } => None,
+            Value::String { val, .. } => Some(self.store.cas_insert_sync(val)?),
+            Value::Binary { val, .. } => Some(self.store.cas_insert_sync(val)?),
+            value => {
+                let json = serde_json::to_vec(&util::value_to_json(value))
+                    .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
+                Some(self.store.cas_insert_sync(json)?)
+            }
+        };
+        self.append(topic, hash)
+    }
+
+    fn append(&self, topic: &str, hash: Option<ssri::Integrity>) -> Result<Frame, ShellError> {
+        Ok(self.store.append(
+            Frame::builder(topic, self.context_id)
+                .maybe_hash(hash)
+                .meta(self.base_meta.clone())
+                .build(),
+        )?)
+    }
+}
+
+/// Copies a byte stream into the CAS as it's read, appending a frame for it at the end.
+struct TeeReader {
+    reader: Reader,
+    writer: Option<CasSyncWriter>,
+    command: TeeCommand,
+    topic: String,
+}
+
+impl Read for TeeReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        match n {
+            0 => {
+                if let Some(writer) = self.writer.take() {
+                    let hash = writer.commit().map_err(std::io::Error::other)?;
+                    self.command
+                        .append(&self.topic, Some(hash))
+                        .map_err(|e| std::io::Error::other(e.to_string()))?;
+                }
+            }
+            n => {
+                if let Some(writer) = self.writer.as_mut() {
+                    writer.write_all(&buf[..n])?;
+                }
+            }
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,87 @@
+use nu_engine::CallExt;
+use nu_protocol::engine::{Call, Command, EngineState, Stack};
+use nu_protocol::{
+    Category, PipelineData, Record, ShellError, Signature, SyntaxShape, Type, Value,
+};
+
+use crate::store::{parse_ttl, TTL};
+
+#[derive(Clone)]
+pub struct TtlParseCommand;
+
+impl TtlParseCommand {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for TtlParseCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Command for TtlParseCommand {
+    fn name(&self) -> &str {
+        ".ttl-parse"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(".ttl-parse")
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .required(
+                "ttl",
+                SyntaxShape::String,
+                r#"TTL specification: 'forever', 'ephemeral', 'time:<milliseconds>', or 'head:<n>'"#,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Validate a TTL specification, returning its kind and parameters"
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let span = call.head;
+        let ttl_str: String = call.req(engine_state, stack, 0)?;
+        let ttl = parse_ttl(&ttl_str).map_err(|e| ShellError::GenericError {
+            error: "Invalid TTL".into(),
+            msg: e,
+            span: Some(call.arguments_span()),
+            help: Some(
+                "Expected 'forever', 'ephemeral', 'time:<milliseconds>', or 'head:<n>'".into(),
+            ),
+            inner: vec![],
+        })?;
+
+        let mut record = Record::new();
+        // Normalized, in the form it's serialized in
+        record.push(
+            "ttl",
+            Value::string(ttl.to_query().trim_start_matches("ttl="), span),
+        );
+        match ttl {
+            TTL::Forever => record.push("kind", Value::string("forever", span)),
+            TTL::Ephemeral => record.push("kind", Value::string("ephemeral", span)),
+            TTL::Time(duration) => {
+                record.push("kind", Value::string("time", span));
+                record.push(
+                    "duration",
+                    Value::duration(duration.as_nanos() as i64, span),
+                );
+            }
+            TTL::Head(n) => {
+                record.push("kind", Value::string("head", span));
+                record.push("n", Value::int(n as i64, span));
+            }
+        }
+
+        Ok(PipelineData::Value(Value::record(record, span), None))
+    }
+}
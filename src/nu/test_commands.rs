@@ -6,7 +6,7 @@ mod tests {
 
     use crate::error::Error;
     use crate::nu::{commands, util, Engine};
-    use crate::store::{Frame, Store, ZERO_CONTEXT};
+    use crate::store::{Frame, ReadOptions, Store, StoreOptions, ZERO_CONTEXT};
 
     fn setup_test_env() -> (Store, Engine, Frame) {
         let temp_dir = TempDir::new().unwrap();
@@ -95,6 +95,169 @@ mod tests {
         assert!(frame.hash.is_none());
     }
 
+    #[test]
+    fn test_append_command_streams_input() {
+        use std::io::Read;
+
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(
+                commands::append_command::AppendCommand::new(store.clone(), ctx.id, json!({})),
+            )])
+            .unwrap();
+
+        // A large payload that's never held in memory all at once
+        const SIZE: u64 = 64 * 1024 * 1024;
+        let payload = || std::io::repeat(b'x').take(SIZE);
+        let mut expected = ssri::IntegrityOpts::new().algorithm(ssri::Algorithm::Sha256);
+        let mut reader = payload();
+        let mut buffer = [0; 8192];
+        loop {
+            let n = reader.read(&mut buffer).unwrap();
+            if n == 0 {
+                break;
+            }
+            expected.input(&buffer[..n]);
+        }
+
+        let input = PipelineData::ByteStream(
+            nu_protocol::ByteStream::read(
+                payload(),
+                Span::test_data(),
+                nu_protocol::Signals::empty(),
+                nu_protocol::ByteStreamType::Binary,
+            ),
+            None,
+        );
+        let frame = value_to_frame(nu_eval(&engine, input, ".append big"));
+        assert_eq!(frame.hash.unwrap(), expected.result());
+
+        // List streams are written a line of JSON per item
+        let frame = value_to_frame(nu_eval(
+            &engine,
+            PipelineData::empty(),
+            r#"1..3 | each {|n| {n: $n} } | .append items"#,
+        ));
+        let content = store.cas_read_sync(&frame.hash.unwrap()).unwrap();
+        assert_eq!(
+            String::from_utf8(content).unwrap(),
+            "{\"n\":1}\n{\"n\":2}\n{\"n\":3}\n"
+        );
+    }
+
+    #[test]
+    fn test_append_command_file() {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(
+                commands::append_command::AppendCommand::new(store.clone(), ctx.id, json!({})),
+            )])
+            .unwrap();
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("data.json");
+        std::fs::write(&path, r#"{"from": "file"}"#).unwrap();
+
+        let frame = value_to_frame(nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".append data --file '{}'", path.display()),
+        ));
+        assert_eq!(
+            frame.meta,
+            Some(json!({"content_type": "application/json"}))
+        );
+        let content = store.cas_read_sync(&frame.hash.unwrap()).unwrap();
+        assert_eq!(content, br#"{"from": "file"}"#);
+
+        let missing = dir.path().join("missing.txt");
+        assert!(engine
+            .eval(
+                PipelineData::empty(),
+                format!(".append data --file '{}'", missing.display()),
+            )
+            .is_err());
+        assert!(engine
+            .eval(
+                PipelineData::empty(),
+                format!(r#""input" | .append data --file '{}'"#, path.display()),
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_append_command_if_head() {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(
+                commands::append_command::AppendCommand::new(store.clone(), ctx.id, json!({})),
+            )])
+            .unwrap();
+
+        let first = value_to_frame(nu_eval(
+            &engine,
+            PipelineData::empty(),
+            ".append counter --if-head null",
+        ));
+        let second = value_to_frame(nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".append counter --if-head {}", first.id),
+        ));
+        assert_eq!(store.head("counter", ctx.id).unwrap(), Some(second));
+
+        for stale in [first.id.to_string(), "null".to_string()] {
+            assert!(engine
+                .eval(
+                    PipelineData::empty(),
+                    format!(".append counter --if-head {}", stale),
+                )
+                .is_err());
+        }
+        assert!(engine
+            .eval(
+                PipelineData::empty(),
+                ".append counter --if-head nope".into()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_append_command_hash() {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(
+                commands::append_command::AppendCommand::new(store.clone(), ctx.id, json!({})),
+            )])
+            .unwrap();
+
+        let hash = store.cas_insert_sync("existing content").unwrap();
+        let frame = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".append topic --hash {}", hash),
+        );
+        let frame = value_to_frame(frame);
+        assert_eq!(frame.hash, Some(hash.clone()));
+        let content = store.cas_read_sync(&frame.hash.unwrap()).unwrap();
+        assert_eq!(content, b"existing content");
+
+        let eval = |command: String| {
+            let engine = engine.clone();
+            std::thread::spawn(move || engine.eval(PipelineData::empty(), command).is_err())
+                .join()
+                .unwrap()
+        };
+        // unknown content
+        let missing = ssri::Integrity::from("missing");
+        assert!(eval(format!(".append topic --hash {}", missing)));
+        // both input and a hash
+        assert!(eval(format!(
+            r#""content" | .append topic --hash {}"#,
+            hash
+        )));
+    }
+
     #[test]
     fn test_cas_command_string() {
         let (store, mut engine, _ctx) = setup_test_env();
@@ -133,6 +296,86 @@ mod tests {
         assert_eq!(retrieved_data, &binary_data);
     }
 
+    #[test]
+    fn test_cas_ls_command() {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(commands::cas_ls_command::CasLsCommand::new(
+                store.clone(),
+            ))])
+            .unwrap();
+
+        let referenced = store.cas_insert_sync("referenced").unwrap();
+        let orphan = store.cas_insert_sync("orphan!").unwrap();
+        store
+            .append(
+                Frame::builder("topic", ctx.id)
+                    .hash(referenced.clone())
+                    .build(),
+            )
+            .unwrap();
+
+        let rows = nu_eval(&engine, PipelineData::empty(), ".cas-ls");
+        let rows = rows.as_list().unwrap();
+        let row = |hash: &ssri::Integrity| {
+            rows.iter()
+                .map(|row| row.as_record().unwrap())
+                .find(|row| row.get("hash").unwrap().as_str().unwrap() == hash.to_string())
+                .unwrap_or_else(|| panic!("{} isn't listed", hash))
+        };
+
+        let listed = row(&referenced);
+        assert!(listed.get("referenced").unwrap().as_bool().unwrap());
+        assert_eq!(listed.get("size").unwrap().as_filesize().unwrap().get(), 10);
+
+        let listed = row(&orphan);
+        assert!(!listed.get("referenced").unwrap().as_bool().unwrap());
+        assert_eq!(listed.get("size").unwrap().as_filesize().unwrap().get(), 7);
+    }
+
+    #[test]
+    fn test_cas_read_command() {
+        let (store, mut engine, _ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(
+                commands::cas_read_command::CasReadCommand::new(store.clone()),
+            )])
+            .unwrap();
+
+        let hash = store.cas_insert_sync("test content").unwrap();
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".cas-read {}", hash),
+        );
+        assert_eq!(value.as_str().unwrap(), "test content");
+
+        let hash = store.cas_insert_sync([0, 159, 146, 150]).unwrap();
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".cas-read {}", hash),
+        );
+        assert_eq!(value.as_binary().unwrap(), &[0, 159, 146, 150]);
+
+        // Malformed and missing hashes are errors
+        let engine_clone = engine.clone();
+        let result = std::thread::spawn(move || {
+            engine_clone.eval(PipelineData::empty(), ".cas-read not-a-hash".into())
+        })
+        .join()
+        .unwrap();
+        assert!(result.is_err());
+
+        let missing = ssri::Integrity::from("never inserted");
+        let result = std::thread::spawn(move || {
+            engine.eval(PipelineData::empty(), format!(".cas-read {}", missing))
+        })
+        .join()
+        .unwrap();
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_head_command() -> Result<(), Error> {
         let (store, mut engine, ctx) = setup_test_env();
@@ -204,6 +447,314 @@ mod tests {
         let frames = value.as_list().unwrap();
         assert_eq!(frames.len(), 1);
 
+        // --raw emits the content, skipping frames without any
+        store
+            .append(Frame::builder("empty", ctx.id).build())
+            .unwrap();
+        store
+            .append(
+                Frame::builder("topic", ctx.id)
+                    .hash(store.cas_insert_sync("content3")?)
+                    .build(),
+            )
+            .unwrap();
+        let value = nu_eval(&engine, PipelineData::empty(), ".cat --raw");
+        assert_eq!(value, Value::test_string("content1content2content3"));
+        let value = nu_eval(&engine, PipelineData::empty(), ".cat --raw --limit 1");
+        assert_eq!(value, Value::test_string("content1"));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_follow_command() {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(
+                commands::follow_command::FollowCommand::new(store.clone(), ctx.id),
+            )])
+            .unwrap();
+
+        let before = store.append(Frame::builder("a", ctx.id).build()).unwrap();
+
+        let follow = tokio::task::spawn_blocking(move || {
+            engine
+                .eval(PipelineData::empty(), ".follow a | first 2".into())
+                .unwrap()
+                .into_value(Span::test_data())
+                .unwrap()
+        });
+
+        // keep appending until the follow has picked up two frames
+        let mut appended = Vec::new();
+        while !follow.is_finished() {
+            store.append(Frame::builder("b", ctx.id).build()).unwrap();
+            appended.push(store.append(Frame::builder("a", ctx.id).build()).unwrap());
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        let lines = follow.await.unwrap();
+        let lines: Vec<_> = lines
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|line| line.as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        for line in &lines {
+            let id = line.split(' ').next().unwrap();
+            assert_ne!(id, before.id.to_string());
+            assert!(appended.iter().any(|frame| frame.id.to_string() == id));
+            assert!(line.ends_with(" a"), "{}", line);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_follow_command_bounds() {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(
+                commands::follow_command::FollowCommand::new(store.clone(), ctx.id),
+            )])
+            .unwrap();
+
+        // Nothing arrives, so only the timeout ends the follow
+        let engine_clone = engine.clone();
+        let lines = tokio::task::spawn_blocking(move || {
+            engine_clone
+                .eval(PipelineData::empty(), ".follow --timeout 500ms".into())
+                .unwrap()
+                .into_value(Span::test_data())
+                .unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(lines.as_list().unwrap().len(), 0);
+
+        let follow = tokio::task::spawn_blocking(move || {
+            engine
+                .eval(PipelineData::empty(), ".follow --limit 1".into())
+                .unwrap()
+                .into_value(Span::test_data())
+                .unwrap()
+        });
+        while !follow.is_finished() {
+            store.append(Frame::builder("a", ctx.id).build()).unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(follow.await.unwrap().as_list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_json_flag() -> Result<(), Error> {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![
+                Box::new(commands::cat_command::CatCommand::new(
+                    store.clone(),
+                    ctx.id,
+                )),
+                Box::new(commands::get_command::GetCommand::new(store.clone())),
+            ])
+            .unwrap();
+
+        let frames = vec![
+            store.append(
+                Frame::builder("topic", ctx.id)
+                    .hash(store.cas_insert_sync("content")?)
+                    .meta(json!({"big": u64::MAX, "nested": {"list": [1, 2]}}))
+                    .build(),
+            )?,
+            store.append(Frame::builder("other", ctx.id).build())?,
+        ];
+
+        let value = nu_eval(&engine, PipelineData::empty(), ".cat --json");
+        let lines: Vec<Frame> = value
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|line| serde_json::from_str(line.as_str().unwrap()).unwrap())
+            .collect();
+        assert_eq!(lines, frames);
+
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".get {} --json", frames[0].id),
+        );
+        let line = value.as_str().unwrap();
+        assert_eq!(line, serde_json::to_string(&frames[0]).unwrap());
+        assert_eq!(serde_json::from_str::<Frame>(line).unwrap(), frames[0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_meta_command() -> Result<(), Error> {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(commands::meta_command::MetaCommand::new(
+                store.clone(),
+            ))])
+            .unwrap();
+
+        let frame = store.append(
+            Frame::builder("topic", ctx.id)
+                .meta(json!({"a": 1, "b": 2, "c": {"d": 1}}))
+                .build(),
+        )?;
+        let other = store.append(Frame::builder("topic", ctx.id).build())?;
+        store.patch_meta(&other.id, json!({"other": true}))?;
+
+        nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!("{{b: 3, a: null}} | .meta {}", frame.id),
+        );
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!("{{c: {{e: 2}}}} | .meta {} | get meta", frame.id),
+        );
+        // Merges are shallow
+        assert_eq!(util::value_to_json(&value), json!({"b": 3, "c": {"e": 2}}));
+
+        // The frame itself is unchanged: the patches are frames of their own
+        assert_eq!(store.get(&frame.id)?, Some(frame.clone()));
+        let patches: Vec<_> = store
+            .read_sync(ReadOptions::builder().topic("xs.meta").build())
+            .collect();
+        assert_eq!(patches.len(), 3);
+        assert!(patches.iter().all(|patch| patch.context_id == ctx.id));
+
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".meta {} | get meta", other.id),
+        );
+        assert_eq!(util::value_to_json(&value), json!({"other": true}));
+
+        assert!(store.patch_meta(&frame.id, json!([1])).is_err());
+        assert!(store.patch_meta(&scru128::new(), json!({})).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_command() -> Result<(), Error> {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![
+                Box::new(commands::set_command::SetCommand::new(
+                    store.clone(),
+                    ctx.id,
+                    json!({}),
+                )),
+                Box::new(commands::head_command::HeadCommand::new(
+                    store.clone(),
+                    ctx.id,
+                )),
+            ])
+            .unwrap();
+
+        let content = |frame: &Frame| {
+            String::from_utf8(store.cas_read_sync(frame.hash.as_ref().unwrap()).unwrap()).unwrap()
+        };
+
+        nu_eval(&engine, PipelineData::empty(), r#""1" | .set kv a"#);
+        nu_eval(&engine, PipelineData::empty(), r#""2" | .set kv b"#);
+        let value = nu_eval(&engine, PipelineData::empty(), r#""3" | .set kv a"#);
+        let latest_a = value_to_frame(value);
+        assert_eq!(latest_a.meta, Some(json!({"key": "a"})));
+
+        // only the latest for each key
+        let latest = store.latest_by_key("kv", ctx.id)?;
+        assert_eq!(latest.iter().map(content).collect::<Vec<_>>(), ["2", "3"]);
+        assert_eq!(latest[1], latest_a);
+
+        let value = nu_eval(&engine, PipelineData::empty(), ".head kv --key a");
+        assert_eq!(value_to_frame(value), latest_a);
+        let value = nu_eval(&engine, PipelineData::empty(), ".head kv --key missing");
+        assert!(value.is_nothing());
+
+        // superseded frames stay on disk until pruned
+        assert_eq!(store.read_sync(Default::default()).count(), 4);
+        nu_eval(&engine, PipelineData::empty(), r#""4" | .set kv a --prune"#);
+        let remaining: Vec<_> = store
+            .read_sync(ReadOptions::builder().context_id(ctx.id).build())
+            .map(|frame| content(&frame))
+            .collect();
+        assert_eq!(remaining, ["2", "4"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee_command() -> Result<(), Error> {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(commands::tee_command::TeeCommand::new(
+                store.clone(),
+                ctx.id,
+                json!({"base": "meta"}),
+            ))])
+            .unwrap();
+
+        let content = |frame: &Frame| store.cas_read_sync(frame.hash.as_ref().unwrap()).unwrap();
+        let read_topic = |topic: &str| -> Vec<Frame> {
+            store
+                .read_sync(ReadOptions::builder().context_id(ctx.id).build())
+                .filter(|frame| frame.topic == topic)
+                .collect()
+        };
+
+        // list streams: a frame per item, and the items flow on unchanged
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            r#"1..3 | each {|n| {n: $n} } | .tee items | where n > 1 | get n"#,
+        );
+        assert_eq!(
+            value,
+            Value::test_list(vec![Value::test_int(2), Value::test_int(3)])
+        );
+        let frames = read_topic("items");
+        assert_eq!(frames.len(), 3);
+        for (frame, n) in frames.iter().zip(1..) {
+            assert_eq!(frame.meta, Some(json!({"base": "meta"})));
+            assert_eq!(
+                serde_json::from_slice::<serde_json::Value>(&content(frame)).unwrap(),
+                json!({"n": n})
+            );
+        }
+
+        // a single value
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            r#""hello" | .tee greeting | str upcase"#,
+        );
+        assert_eq!(value, Value::test_string("HELLO"));
+        let frames = read_topic("greeting");
+        assert_eq!(frames.len(), 1);
+        assert_eq!(content(&frames[0]), b"hello");
+
+        // byte streams are copied to the CAS as they're read
+        let input = PipelineData::ByteStream(
+            nu_protocol::ByteStream::read(
+                std::io::Cursor::new(b"some bytes".to_vec()),
+                Span::test_data(),
+                nu_protocol::Signals::empty(),
+                nu_protocol::ByteStreamType::Binary,
+            ),
+            None,
+        );
+        let value = nu_eval(&engine, input, ".tee bytes");
+        assert_eq!(value, Value::test_binary(b"some bytes".to_vec()));
+        let frames = read_topic("bytes");
+        assert_eq!(frames.len(), 1);
+        assert_eq!(content(&frames[0]), b"some bytes");
+
         Ok(())
     }
 
@@ -230,10 +781,220 @@ mod tests {
             format!(".remove {}", frame.id),
         );
 
-        assert!(store.get(&frame.id).is_none());
+        assert!(store.get(&frame.id).unwrap().is_none());
         Ok(())
     }
 
+    #[test]
+    fn test_frame_to_value_timestamp() {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(commands::get_command::GetCommand::new(
+                store.clone(),
+            ))])
+            .unwrap();
+
+        let frame = store
+            .append(Frame::builder("topic", ctx.id).build())
+            .unwrap();
+
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".get {} | get timestamp", frame.id),
+        );
+        let timestamp = value.as_date().unwrap();
+        assert_eq!(timestamp.timestamp_millis() as u64, frame.id.timestamp());
+
+        // The timestamp is usable in date arithmetic
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            format!(".get {} | $in.timestamp < (date now)", frame.id),
+        );
+        assert!(value.as_bool().unwrap());
+
+        // Reading the record back as a frame ignores the derived field
+        let value = util::frame_to_value(&frame, Span::test_data());
+        assert_eq!(value_to_frame(value), frame);
+    }
+
+    // Deterministic xorshift, so failures reproduce
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn json(&mut self, depth: u32) -> serde_json::Value {
+            let kinds = if depth == 0 { 6 } else { 8 };
+            match self.next() % kinds {
+                0 => serde_json::Value::Null,
+                1 => json!(self.next() & 1 == 0),
+                2 => json!(self.next() as i64),
+                // past i64::MAX, but still exactly representable as an f64
+                3 => json!((self.next() | 1 << 63) & !0x7ff),
+                4 => json!((self.next() as i64 >> 20) as f64 / 1000.0),
+                5 => json!(format!("s{}\u{e9}\u{1f600}", self.next() % 100)),
+                6 => serde_json::Value::Array(
+                    (0..self.next() % 4).map(|_| self.json(depth - 1)).collect(),
+                ),
+                _ => serde_json::Value::Object(
+                    (0..self.next() % 4)
+                        .map(|i| (format!("k{}", i), self.json(depth - 1)))
+                        .collect(),
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_value_round_trip() {
+        let corpus = [
+            json!(null),
+            json!({"a": null, "b": [null, [null]]}),
+            json!([[[]], [1, [2.5, [{"deep": [true]}]]]]),
+            json!(i64::MIN),
+            json!(i64::MAX),
+            json!(1u64 << 63),
+            json!(u64::MAX - 2047),
+            json!(0.1),
+            json!(-2.5e-300),
+            json!(1e300),
+            json!(3.0),
+            json!({"nested": {"objects": {"all": {"the": {"way": "down"}}}}}),
+        ];
+
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let generated: Vec<_> = (0..500).map(|_| rng.json(4)).collect();
+
+        for json in corpus.iter().chain(generated.iter()) {
+            let value = util::json_to_value(json, Span::test_data());
+            assert_eq!(&util::value_to_json(&value), json);
+
+            // and as a frame's meta
+            let frame = Frame::builder("topic", ZERO_CONTEXT)
+                .id(scru128::new())
+                .meta(json.clone())
+                .build();
+            let value = util::frame_to_value(&frame, Span::test_data());
+            let meta = value.get_data_by_key("meta").unwrap();
+            assert_eq!(&util::value_to_json(&meta), json);
+        }
+    }
+
+    #[test]
+    fn test_value_to_json_nu_types() {
+        let engine = Engine::new().unwrap();
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            "{size: 2kB, took: 3ms, bytes: 0x[01 ff], glob: ('*.rs' | into glob)}",
+        );
+        assert_eq!(
+            util::value_to_json(&value),
+            json!({"size": 2000, "took": 3_000_000, "bytes": [1, 255], "glob": "*.rs"})
+        );
+    }
+
+    #[test]
+    fn test_ls_command() {
+        let (store, mut engine, ctx) = setup_test_env();
+        engine
+            .add_commands(vec![Box::new(commands::ls_command::LsCommand::new(
+                store.clone(),
+                ctx.id,
+            ))])
+            .unwrap();
+
+        let mut last = std::collections::HashMap::new();
+        for topic in ["b", "a", "c", "a", "c", "c"] {
+            let frame = store.append(Frame::builder(topic, ctx.id).build()).unwrap();
+            last.insert(topic, frame.id.to_string());
+        }
+        // other contexts aren't listed
+        store
+            .append(Frame::builder("other", ZERO_CONTEXT).build())
+            .unwrap();
+
+        let value = nu_eval(&engine, PipelineData::empty(), ".ls");
+        let rows: Vec<(String, i64, String)> = value
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|row| {
+                let row = row.as_record().unwrap();
+                (
+                    row.get("topic").unwrap().as_str().unwrap().to_string(),
+                    row.get("count").unwrap().as_int().unwrap(),
+                    row.get("last_id").unwrap().as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            rows,
+            vec![
+                ("a".to_string(), 2, last["a"].clone()),
+                ("b".to_string(), 1, last["b"].clone()),
+                ("c".to_string(), 3, last["c"].clone()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ttl_parse_command() {
+        let mut engine = Engine::new().unwrap();
+        engine
+            .add_commands(vec![Box::new(
+                commands::ttl_parse_command::TtlParseCommand::new(),
+            )])
+            .unwrap();
+
+        let parse = |ttl: &str| {
+            let value = nu_eval(
+                &engine,
+                PipelineData::empty(),
+                format!(".ttl-parse '{}'", ttl),
+            );
+            util::value_to_json(&value)
+        };
+        assert_eq!(
+            parse("forever"),
+            json!({"ttl": "forever", "kind": "forever"})
+        );
+        assert_eq!(
+            parse("ephemeral"),
+            json!({"ttl": "ephemeral", "kind": "ephemeral"})
+        );
+        assert_eq!(
+            parse("head:5"),
+            json!({"ttl": "head:5", "kind": "head", "n": 5})
+        );
+        let value = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            ".ttl-parse time:3600 | $in.duration == 3600ms",
+        );
+        assert!(value.as_bool().unwrap());
+        assert_eq!(parse("time:3600")["kind"], "time");
+
+        for malformed in [
+            "head:abc", "head:0", "head:", "time:-1", "time:1.5", "never", "",
+        ] {
+            let engine = engine.clone();
+            let command = format!(".ttl-parse '{}'", malformed);
+            let result = std::thread::spawn(move || engine.eval(PipelineData::empty(), command))
+                .join()
+                .unwrap();
+            assert!(result.is_err(), "{:?} should be rejected", malformed);
+        }
+    }
+
     #[test]
     fn test_get_command() -> Result<(), Error> {
         let (store, mut engine, ctx) = setup_test_env();
@@ -264,4 +1025,131 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_export_import_commands() -> Result<(), Error> {
+        use base64::Engine as _;
+
+        let (source, mut engine, ctx) = setup_test_env();
+        let (target, _, _) = setup_test_env();
+        engine
+            .add_commands(vec![
+                Box::new(commands::export_command::ExportCommand::new(source.clone())),
+                Box::new(commands::import_command::ImportCommand::new(target.clone())),
+            ])
+            .unwrap();
+
+        source.append(
+            Frame::builder("topic", ctx.id)
+                .hash(source.cas_insert_sync("content")?)
+                .build(),
+        )?;
+        source.append(
+            Frame::builder("topic", ctx.id)
+                .meta(json!({"n": 2}))
+                .build(),
+        )?;
+
+        let count = nu_eval(&engine, PipelineData::empty(), ".export | .import");
+        assert_eq!(count.as_int().unwrap(), 3);
+
+        let exported: Vec<_> = source.read_sync(ReadOptions::default()).collect();
+        for frame in &exported {
+            assert_eq!(target.get(&frame.id)?.as_ref(), Some(frame));
+        }
+        // along with their content
+        let hash = exported[1].hash.clone().unwrap();
+        assert_eq!(target.cas_read_sync(&hash)?, b"content");
+        // Alongside the target's own context
+        assert_eq!(target.read_sync(ReadOptions::default()).count(), 4);
+
+        // Ids collide on a second import, unless they're to be overwritten
+        let engine_clone = engine.clone();
+        let result = std::thread::spawn(move || {
+            engine_clone.eval(PipelineData::empty(), ".export | .import".to_string())
+        })
+        .join()
+        .unwrap();
+        assert!(result.is_err());
+
+        let count = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            ".export | .import --overwrite",
+        );
+        assert_eq!(count.as_int().unwrap(), 3);
+        assert_eq!(target.read_sync(ReadOptions::default()).count(), 4);
+
+        // Frames appended after an import follow the imported ones
+        let next = target.append(Frame::builder("topic", ctx.id).build())?;
+        assert!(next.id > exported.last().unwrap().id);
+        assert!(next.seq > exported.last().unwrap().seq);
+
+        // The output streams, a page of frames at a time
+        source.append_batch(
+            (0..1500)
+                .map(|_| Frame::builder("bulk", ctx.id).build())
+                .collect(),
+        )?;
+        let output = engine.eval(PipelineData::empty(), ".export".to_string())?;
+        assert!(matches!(output, PipelineData::ByteStream(..)));
+        // with each piece of content once, however many frames refer to it
+        source.append(Frame::builder("topic", ctx.id).hash(hash).build())?;
+        let count = nu_eval(&engine, PipelineData::empty(), ".export | lines | length");
+        assert_eq!(count.as_int().unwrap(), 1505);
+
+        // Content that doesn't match its hash isn't imported
+        let result = engine.eval(
+            PipelineData::empty(),
+            format!(
+                r#"'{{"cas":"{}","content":"{}"}}' | .import"#,
+                ssri::Integrity::from("other"),
+                base64::prelude::BASE64_STANDARD.encode("tampered")
+            ),
+        );
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_append_command_max_content_bytes() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::open(
+            temp_dir.into_path(),
+            StoreOptions::builder().max_content_bytes(10).build(),
+        )
+        .unwrap();
+        let mut engine = Engine::new().unwrap();
+        engine
+            .add_commands(vec![Box::new(
+                commands::append_command::AppendCommand::new(
+                    store.clone(),
+                    ZERO_CONTEXT,
+                    json!({}),
+                ),
+            )])
+            .unwrap();
+
+        let engine_clone = engine.clone();
+        let result = std::thread::spawn(move || {
+            engine_clone.eval(
+                PipelineData::empty(),
+                r#""01234567890" | .append topic"#.to_string(),
+            )
+        })
+        .join()
+        .unwrap();
+        let err = format!("{:?}", result.err().unwrap());
+        assert!(err.contains("limit of 10 bytes"), "{}", err);
+        assert!(store.read_sync(ReadOptions::default()).next().is_none());
+        assert!(store.cas_list().unwrap().is_empty());
+
+        let frame = nu_eval(
+            &engine,
+            PipelineData::empty(),
+            r#""0123456789" | .append topic"#,
+        );
+        assert!(value_to_frame(frame).hash.is_some());
+    }
 }
@@ -1,11 +1,14 @@
-use std::io::Read;
 use std::io::Write;
 
 use nu_protocol::{PipelineData, Record, ShellError, Span, Value};
 
 use crate::store::Frame;
 use crate::store::Store;
+use crate::store::StoreError;
 
+/// Converts JSON to a Nushell value. Integers beyond the range of `i64` become floats, which
+/// [`value_to_json`] turns back into integers: they round-trip exactly as long as an `f64` can
+/// hold them.
 pub fn json_to_value(json: &serde_json::Value, span: Span) -> Value {
     match json {
         serde_json::Value::Null => Value::nothing(span),
@@ -34,12 +37,30 @@ pub fn json_to_value(json: &serde_json::Value, span: Span) -> Value {
     }
 }
 
+impl From<StoreError> for ShellError {
+    fn from(e: StoreError) -> Self {
+        ShellError::GenericError {
+            error: "Store error".into(),
+            msg: e.to_string(),
+            span: None,
+            help: None,
+            inner: vec![],
+        }
+    }
+}
+
 pub fn frame_to_value(frame: &Frame, span: Span) -> Value {
     let mut record = Record::new();
 
     record.push("id", Value::string(frame.id.to_string(), span));
     record.push("topic", Value::string(frame.topic.clone(), span));
     record.push("context_id", Value::string(frame.context_id, span));
+    record.push("seq", Value::int(frame.seq as i64, span));
+
+    // Derived from the id, for convenience: it's dropped when the record is read back as a frame
+    if let Some(timestamp) = chrono::DateTime::from_timestamp_millis(frame.id.timestamp() as i64) {
+        record.push("timestamp", Value::date(timestamp.fixed_offset(), span));
+    }
 
     if let Some(hash) = &frame.hash {
         record.push("hash", Value::string(hash.to_string(), span));
@@ -49,22 +70,41 @@ pub fn frame_to_value(frame: &Frame, span: Span) -> Value {
         record.push("meta", json_to_value(meta, span));
     }
 
+    if let Some(schema) = &frame.schema {
+        record.push("schema", Value::string(schema.clone(), span));
+    }
+
     Value::record(record, span)
 }
 
+/// The frame as a string of JSON, exactly as it's stored.
+pub fn frame_to_json_value(frame: &Frame, span: Span) -> Result<Value, ShellError> {
+    let json =
+        serde_json::to_string(frame).map_err(|e| ShellError::IOError { msg: e.to_string() })?;
+    Ok(Value::string(json, span))
+}
+
 pub fn frame_to_pipeline(frame: &Frame) -> PipelineData {
     PipelineData::Value(frame_to_value(frame, Span::unknown()), None)
 }
 
+/// Converts a Nushell value to JSON. Filesizes become bytes, durations nanoseconds and binary an
+/// array of bytes. Values JSON can't express, such as closures or non-finite floats, become null.
 pub fn value_to_json(value: &Value) -> serde_json::Value {
     match value {
         Value::Nothing { .. } => serde_json::Value::Null,
         Value::Bool { val, .. } => serde_json::Value::Bool(*val),
         Value::Int { val, .. } => serde_json::Value::Number((*val).into()),
-        Value::Float { val, .. } => serde_json::Number::from_f64(*val)
-            .map(serde_json::Value::Number)
-            .unwrap_or(serde_json::Value::Null),
+        Value::Float { val, .. } => float_to_json(*val),
+        Value::Filesize { val, .. } => serde_json::Value::Number(val.get().into()),
+        Value::Duration { val, .. } => serde_json::Value::Number((*val).into()),
         Value::String { val, .. } => serde_json::Value::String(val.clone()),
+        Value::Glob { val, .. } => serde_json::Value::String(val.clone()),
+        Value::CellPath { val, .. } => serde_json::Value::String(val.to_string()),
+        Value::Binary { val, .. } => {
+            serde_json::Value::Array(val.iter().map(|byte| (*byte).into()).collect())
+        }
+        Value::Date { val, .. } => serde_json::Value::String(val.to_rfc3339()),
         Value::List { vals, .. } => {
             serde_json::Value::Array(vals.iter().map(value_to_json).collect())
         }
@@ -79,91 +119,80 @@ pub fn value_to_json(value: &Value) -> serde_json::Value {
     }
 }
 
+fn float_to_json(val: f64) -> serde_json::Value {
+    // Whole numbers past i64::MAX came from JSON integers too large for an i64: see
+    // `json_to_value`
+    const I64_END: f64 = 9_223_372_036_854_775_808.0;
+    const U64_END: f64 = 18_446_744_073_709_551_616.0;
+    if val.fract() == 0.0 && (I64_END..U64_END).contains(&val) {
+        return serde_json::Value::Number((val as u64).into());
+    }
+
+    serde_json::Number::from_f64(val)
+        .map(serde_json::Value::Number)
+        .unwrap_or(serde_json::Value::Null)
+}
+
 pub fn write_pipeline_to_cas(
     input: PipelineData,
     store: &Store,
     span: Span,
 ) -> Result<Option<ssri::Integrity>, ShellError> {
+    let io_error = |e: std::io::Error| ShellError::IOError { msg: e.to_string() };
+
     let mut writer = store
         .cas_writer_sync()
         .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
 
     match input {
         PipelineData::Value(value, _) => match value {
-            Value::Nothing { .. } => Ok(None),
-            Value::String { val, .. } => {
-                writer
-                    .write_all(val.as_bytes())
-                    .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-
-                let hash = writer
-                    .commit()
-                    .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-
-                Ok(Some(hash))
+            Value::Nothing { .. } => return Ok(None),
+            Value::String { val, .. } => write_chunked(&mut writer, val.as_bytes())?,
+            Value::Binary { val, .. } => write_chunked(&mut writer, &val)?,
+            Value::Record { .. } => serde_json::to_writer(&mut writer, &value_to_json(&value))
+                .map_err(|e| ShellError::IOError { msg: e.to_string() })?,
+            _ => {
+                return Err(ShellError::PipelineMismatch {
+                    exp_input_type: format!(
+                        "expected: string, binary, record, or nothing :: received: {:?}",
+                        value.get_type()
+                    ),
+                    dst_span: span,
+                    src_span: value.span(),
+                })
             }
-            Value::Binary { val, .. } => {
-                writer
-                    .write_all(&val)
-                    .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-
-                let hash = writer
-                    .commit()
-                    .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-
-                Ok(Some(hash))
-            }
-            Value::Record { .. } => {
-                let json = value_to_json(&value);
-                let json_string = serde_json::to_string(&json)
-                    .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-
-                writer
-                    .write_all(json_string.as_bytes())
-                    .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-
-                let hash = writer
-                    .commit()
+        },
+        // Each item is written as a line of JSON, as it arrives
+        PipelineData::ListStream(stream, ..) => {
+            for value in stream {
+                if let Value::Error { error, .. } = value {
+                    return Err(*error);
+                }
+                serde_json::to_writer(&mut writer, &value_to_json(&value))
                     .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-
-                Ok(Some(hash))
+                writer.write_all(b"\n").map_err(io_error)?;
             }
-            _ => Err(ShellError::PipelineMismatch {
-                exp_input_type: format!(
-                    "expected: string, binary, record, or nothing :: received: {:?}",
-                    value.get_type()
-                ),
-                dst_span: span,
-                src_span: value.span(),
-            }),
-        },
-        PipelineData::ListStream(_stream, ..) => {
-            panic!("ListStream handling is not yet implemented");
         }
         PipelineData::ByteStream(stream, ..) => {
             if let Some(mut reader) = stream.reader() {
-                let mut buffer = [0; 8192];
-                loop {
-                    let bytes_read = reader
-                        .read(&mut buffer)
-                        .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-
-                    if bytes_read == 0 {
-                        break;
-                    }
-
-                    writer
-                        .write_all(&buffer[..bytes_read])
-                        .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
-                }
+                std::io::copy(&mut reader, &mut writer).map_err(io_error)?;
             }
+        }
+        PipelineData::Empty => return Ok(None),
+    }
 
-            let hash = writer
-                .commit()
-                .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
+    let hash = writer
+        .commit()
+        .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
+    Ok(Some(hash))
+}
 
-            Ok(Some(hash))
-        }
-        PipelineData::Empty => Ok(None),
+/// Writes `bytes` a chunk at a time, so the writer never has to buffer all of them at once.
+fn write_chunked(writer: &mut impl Write, bytes: &[u8]) -> Result<(), ShellError> {
+    for chunk in bytes.chunks(8192) {
+        writer
+            .write_all(chunk)
+            .map_err(|e| ShellError::IOError { msg: e.to_string() })?;
     }
+    Ok(())
 }
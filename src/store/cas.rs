@@ -0,0 +1,756 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use ssri::{Algorithm, Integrity, IntegrityChecker, IntegrityOpts};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Called with the hash and size, in bytes of original content, of what a writer committed.
+pub type OnCommit = Box<dyn FnOnce(&Integrity, u64) -> cacache::Result<()> + Send + Sync>;
+
+/// The error a writer fails with, wrapped in an `io::Error`, once it's given more content than
+/// its limit. Nothing is committed: the partial blob is cleaned up when the writer is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentTooLarge {
+    pub limit: u64,
+}
+
+impl std::fmt::Display for ContentTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "content exceeds the limit of {} bytes", self.limit)
+    }
+}
+
+impl std::error::Error for ContentTooLarge {}
+
+// Fails a write that would take the content written past `limit`, before any of it is written
+fn check_limit(limit: Option<u64>, written: u64, buf: &[u8]) -> std::io::Result<()> {
+    match limit {
+        Some(limit) if written + buf.len() as u64 > limit => {
+            Err(std::io::Error::other(ContentTooLarge { limit }))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Compressed blobs are stored content-addressed like any other blob, with an index entry, keyed on
+// the integrity of the original content, pointing at them. This keeps the hashes recorded on
+// frames stable: they always describe the original, uncompressed content.
+const COMPRESSED_KEY_PREFIX: &str = "xs.deflate:";
+
+fn compressed_key(hash: &Integrity) -> String {
+    format!("{}{}", COMPRESSED_KEY_PREFIX, hash)
+}
+
+// The index entry for a compressed blob. It records the size of the original content in its
+// metadata, so that can be looked up without decompressing the blob.
+fn compressed_entry(integrity: Integrity, compressed_size: usize, size: u64) -> cacache::WriteOpts {
+    cacache::WriteOpts::new()
+        .integrity(integrity)
+        .size(compressed_size)
+        .metadata(serde_json::json!({ "size": size }))
+}
+
+fn io_error(e: std::io::Error) -> cacache::Error {
+    let context = e.to_string();
+    cacache::Error::IoError(e, context)
+}
+
+fn compress(content: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+    encoder.finish()
+}
+
+fn decompress(compressed: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    DeflateDecoder::new(compressed).read_to_end(&mut content)?;
+    Ok(content)
+}
+
+// cacache checks blobs against the hash they're stored under, but for compressed content that's
+// the hash of the compressed bytes, so always check what we hand back against what was asked for.
+fn verify(hash: &Integrity, content: Vec<u8>) -> cacache::Result<Vec<u8>> {
+    hash.check(&content)
+        .map_err(cacache::Error::IntegrityError)?;
+    Ok(content)
+}
+
+fn hasher(algorithm: Algorithm) -> IntegrityOpts {
+    IntegrityOpts::new().algorithm(algorithm)
+}
+
+pub async fn cas_insert(
+    cache: &Path,
+    compress_cas: bool,
+    algorithm: Algorithm,
+    content: &[u8],
+) -> cacache::Result<Integrity> {
+    if !compress_cas {
+        return cacache::write_hash_with_algo(algorithm, cache, content).await;
+    }
+    let hash = hasher(algorithm).chain(content).result();
+    let compressed = compress(content).map_err(io_error)?;
+    let integrity = cacache::write_hash(cache, &compressed).await?;
+    let entry = compressed_entry(integrity, compressed.len(), content.len() as u64);
+    cacache::index::insert_async(cache, &compressed_key(&hash), entry).await?;
+    Ok(hash)
+}
+
+pub fn cas_insert_sync(
+    cache: &Path,
+    compress_cas: bool,
+    algorithm: Algorithm,
+    content: &[u8],
+) -> cacache::Result<Integrity> {
+    if !compress_cas {
+        return cacache::write_hash_sync_with_algo(algorithm, cache, content);
+    }
+    let hash = hasher(algorithm).chain(content).result();
+    let compressed = compress(content).map_err(io_error)?;
+    let integrity = cacache::write_hash_sync(cache, &compressed)?;
+    let entry = compressed_entry(integrity, compressed.len(), content.len() as u64);
+    cacache::index::insert(cache, &compressed_key(&hash), entry)?;
+    Ok(hash)
+}
+
+pub async fn cas_read(cache: &Path, hash: &Integrity) -> cacache::Result<Vec<u8>> {
+    let content = match cacache::metadata(cache, compressed_key(hash)).await? {
+        Some(_) => {
+            let compressed = cacache::read(cache, compressed_key(hash)).await?;
+            decompress(&compressed).map_err(io_error)?
+        }
+        None => cacache::read_hash(cache, hash).await?,
+    };
+    verify(hash, content)
+}
+
+pub fn cas_read_sync(cache: &Path, hash: &Integrity) -> cacache::Result<Vec<u8>> {
+    let content = match cacache::metadata_sync(cache, compressed_key(hash))? {
+        Some(_) => {
+            let compressed = cacache::read_sync(cache, compressed_key(hash))?;
+            decompress(&compressed).map_err(io_error)?
+        }
+        None => cacache::read_hash_sync(cache, hash)?,
+    };
+    verify(hash, content)
+}
+
+/// Whether content with the given hash is stored, compressed or not.
+pub async fn cas_exists(cache: &Path, hash: &Integrity) -> cacache::Result<bool> {
+    Ok(cacache::metadata(cache, compressed_key(hash))
+        .await?
+        .is_some()
+        || cacache::exists(cache, hash).await)
+}
+
+/// The blocking counterpart of [`cas_exists`].
+pub fn cas_exists_sync(cache: &Path, hash: &Integrity) -> cacache::Result<bool> {
+    Ok(
+        cacache::metadata_sync(cache, compressed_key(hash))?.is_some()
+            || cacache::exists_sync(cache, hash),
+    )
+}
+
+/// The size of the content with the given hash, or `None` if it isn't stored. This doesn't read
+/// the content: it comes from the index entry of compressed content, and from the blob's file
+/// otherwise. Only compressed content written before its size was recorded has to be read.
+pub async fn cas_size(cache: &Path, hash: &Integrity) -> cacache::Result<Option<u64>> {
+    match cacache::metadata(cache, compressed_key(hash)).await? {
+        Some(metadata) => match recorded_size(&metadata) {
+            Some(size) => Ok(Some(size)),
+            None => {
+                let mut reader = cas_open(cache, hash.clone()).await?;
+                let size = tokio::io::copy(&mut reader, &mut tokio::io::sink())
+                    .await
+                    .map_err(io_error)?;
+                Ok(Some(size))
+            }
+        },
+        None => match tokio::fs::metadata(content_path(cache, hash)).await {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(e)),
+        },
+    }
+}
+
+fn recorded_size(metadata: &cacache::Metadata) -> Option<u64> {
+    metadata.metadata.get("size")?.as_u64()
+}
+
+// Where cacache keeps a blob: content-v2/<algorithm>/<hex[..2]>/<hex[2..4]>/<hex[4..]>
+fn content_path(cache: &Path, hash: &Integrity) -> PathBuf {
+    let (algorithm, hex) = hash.to_hex();
+    cache
+        .join("content-v2")
+        .join(algorithm.to_string())
+        .join(&hex[..2])
+        .join(&hex[2..4])
+        .join(&hex[4..])
+}
+
+/// Opens a reader for the content with the given hash, transparently decompressing it, as it's
+/// read, if it was stored compressed. Either way, the reader fails with `InvalidData` once it gets
+/// to the end if the content doesn't match the hash.
+pub async fn cas_open(cache: &Path, hash: Integrity) -> cacache::Result<CasReader> {
+    if cacache::metadata(cache, compressed_key(&hash))
+        .await?
+        .is_some()
+    {
+        let reader = cacache::Reader::open(cache, compressed_key(&hash)).await?;
+        return Ok(CasReader::Decompressed(Box::new(Decompressor::new(
+            reader, hash,
+        ))));
+    }
+    Ok(CasReader::Plain(Checked::new(
+        cacache::Reader::open_hash(cache, hash).await?,
+    )))
+}
+
+pub fn cas_open_sync(cache: &Path, hash: Integrity) -> cacache::Result<CasSyncReader> {
+    if cacache::metadata_sync(cache, compressed_key(&hash))?.is_some() {
+        let reader = cacache::SyncReader::open(cache, compressed_key(&hash))?;
+        return Ok(CasSyncReader::Decompressed(Box::new(Decompressor::new(
+            reader, hash,
+        ))));
+    }
+    Ok(CasSyncReader::Plain(Checked::new(
+        cacache::SyncReader::open_hash(cache, hash)?,
+    )))
+}
+
+pub enum CasReader {
+    Plain(Checked<cacache::Reader>),
+    Decompressed(Box<Decompressor<cacache::Reader>>),
+}
+
+impl AsyncRead for CasReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            CasReader::Plain(reader) => Pin::new(reader).poll_read(cx, buf),
+            CasReader::Decompressed(reader) => Pin::new(reader.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+pub enum CasSyncReader {
+    Plain(Checked<cacache::SyncReader>),
+    Decompressed(Box<Decompressor<cacache::SyncReader>>),
+}
+
+impl Read for CasSyncReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            CasSyncReader::Plain(reader) => reader.read(buf),
+            CasSyncReader::Decompressed(reader) => reader.read(buf),
+        }
+    }
+}
+
+/// Reads plain content, checking it against its hash once it gets to the end: cacache only does
+/// that when asked to.
+pub struct Checked<R> {
+    // Taken to check it, at the end
+    reader: Option<R>,
+}
+
+impl<R> Checked<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader: Some(reader),
+        }
+    }
+}
+
+fn invalid_data(e: cacache::Error) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+}
+
+impl Read for Checked<cacache::SyncReader> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(0);
+        };
+        let n = reader.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.reader.take().unwrap().check().map_err(invalid_data)?;
+        }
+        Ok(n)
+    }
+}
+
+impl AsyncRead for Checked<cacache::Reader> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let Some(reader) = this.reader.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+        let filled = buf.filled().len();
+        ready!(Pin::new(reader).poll_read(cx, buf))?;
+        if buf.filled().len() == filled && buf.remaining() > 0 {
+            this.reader.take().unwrap().check().map_err(invalid_data)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+// How much compressed content a decompressor reads at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Inflates compressed content as it's read, a chunk at a time, checking what it hands back
+/// against the hash of the original content once it gets to the end.
+pub struct Decompressor<R> {
+    reader: R,
+    // Inflated output lands in the decoder's buffer, and is handed out from `pos` on
+    decoder: flate2::write::DeflateDecoder<Vec<u8>>,
+    pos: usize,
+    checker: Option<IntegrityChecker>,
+    eof: bool,
+}
+
+impl<R> Decompressor<R> {
+    fn new(reader: R, hash: Integrity) -> Self {
+        Self {
+            reader,
+            decoder: flate2::write::DeflateDecoder::new(Vec::new()),
+            pos: 0,
+            checker: Some(IntegrityChecker::new(hash)),
+            eof: false,
+        }
+    }
+
+    fn has_output(&self) -> bool {
+        self.pos < self.decoder.get_ref().len()
+    }
+
+    fn take_output(&mut self, buf: &mut [u8]) -> usize {
+        let output = &self.decoder.get_ref()[self.pos..];
+        let n = output.len().min(buf.len());
+        buf[..n].copy_from_slice(&output[..n]);
+        if let Some(checker) = self.checker.as_mut() {
+            checker.input(&output[..n]);
+        }
+        self.pos += n;
+        if !self.has_output() {
+            self.decoder.get_mut().clear();
+            self.pos = 0;
+        }
+        n
+    }
+
+    // An empty chunk is the end of the compressed content
+    fn inflate(&mut self, compressed: &[u8]) -> std::io::Result<()> {
+        if compressed.is_empty() {
+            self.decoder.try_finish()?;
+            self.eof = true;
+        } else {
+            self.decoder.write_all(compressed)?;
+        }
+        Ok(())
+    }
+
+    fn check(&mut self) -> std::io::Result<()> {
+        match self.checker.take() {
+            Some(checker) => checker
+                .result()
+                .map(|_| ())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if self.has_output() {
+                return Ok(self.take_output(buf));
+            }
+            if self.eof {
+                self.check()?;
+                return Ok(0);
+            }
+            let mut chunk = [0; CHUNK_SIZE];
+            let n = self.reader.read(&mut chunk)?;
+            self.inflate(&chunk[..n])?;
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Decompressor<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.has_output() {
+                let n = this.take_output(buf.initialize_unfilled());
+                buf.advance(n);
+                return Poll::Ready(Ok(()));
+            }
+            if this.eof {
+                return Poll::Ready(this.check());
+            }
+            let mut chunk = [0; CHUNK_SIZE];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.reader).poll_read(cx, &mut chunk))?;
+            this.inflate(chunk.filled())?;
+        }
+    }
+}
+
+/// Compresses content as it's written, streaming it into a blob of its own, and hashes the
+/// original bytes so `commit` can index the blob under the integrity of the uncompressed content.
+pub struct Compressor<W> {
+    cache: PathBuf,
+    writer: W,
+    // Compressed output lands in the encoder's buffer, and is passed on to `writer` from there
+    encoder: DeflateEncoder<Vec<u8>>,
+    hasher: IntegrityOpts,
+    compressed_size: usize,
+}
+
+impl<W> Compressor<W> {
+    fn new(cache: &Path, writer: W, algorithm: Algorithm) -> Self {
+        Self {
+            cache: cache.to_path_buf(),
+            writer,
+            encoder: DeflateEncoder::new(Vec::new(), Compression::default()),
+            hasher: hasher(algorithm),
+            compressed_size: 0,
+        }
+    }
+
+    fn compress(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.encoder.write(buf)?;
+        self.hasher.input(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl Compressor<cacache::Writer> {
+    // Passes on compressed output, until there's none left
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let output = self.encoder.get_mut();
+        while !output.is_empty() {
+            let n = ready!(Pin::new(&mut self.writer).poll_write(cx, output))?;
+            if n == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            output.drain(..n);
+            self.compressed_size += n;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        ready!(self.poll_drain(cx))?;
+        Poll::Ready(self.compress(buf))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ready!(self.poll_drain(cx))?;
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    async fn commit(mut self) -> cacache::Result<Integrity> {
+        self.encoder.try_finish().map_err(io_error)?;
+        std::future::poll_fn(|cx| self.poll_drain(cx))
+            .await
+            .map_err(io_error)?;
+        let hash = self.hasher.result();
+        let entry = compressed_entry(
+            self.writer.commit().await?,
+            self.compressed_size,
+            self.encoder.total_in(),
+        );
+        cacache::index::insert_async(&self.cache, &compressed_key(&hash), entry).await?;
+        Ok(hash)
+    }
+}
+
+impl Compressor<cacache::SyncWriter> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.compress(buf)?;
+        self.drain()?;
+        Ok(n)
+    }
+
+    fn drain(&mut self) -> std::io::Result<()> {
+        let output = self.encoder.get_mut();
+        self.writer.write_all(output)?;
+        self.compressed_size += output.len();
+        output.clear();
+        Ok(())
+    }
+
+    fn commit(mut self) -> cacache::Result<Integrity> {
+        self.encoder.try_finish().map_err(io_error)?;
+        self.drain().map_err(io_error)?;
+        let hash = self.hasher.result();
+        let entry = compressed_entry(
+            self.writer.commit()?,
+            self.compressed_size,
+            self.encoder.total_in(),
+        );
+        cacache::index::insert(&self.cache, &compressed_key(&hash), entry)?;
+        Ok(hash)
+    }
+}
+
+enum WriterSink {
+    Plain(Box<cacache::Writer>),
+    Compressed(Box<Compressor<cacache::Writer>>),
+}
+
+/// Writes content to the CAS as it arrives, letting the store know once it's committed. Writes
+/// past `limit` bytes fail with [`ContentTooLarge`].
+pub struct CasWriter {
+    sink: WriterSink,
+    written: u64,
+    limit: Option<u64>,
+    on_commit: OnCommit,
+}
+
+impl CasWriter {
+    pub async fn open(
+        cache: &Path,
+        compress_cas: bool,
+        algorithm: Algorithm,
+        limit: Option<u64>,
+        on_commit: OnCommit,
+    ) -> cacache::Result<Self> {
+        let writer = cacache::WriteOpts::new()
+            .algorithm(algorithm)
+            .open_hash(cache)
+            .await?;
+        let sink = if compress_cas {
+            WriterSink::Compressed(Box::new(Compressor::new(cache, writer, algorithm)))
+        } else {
+            WriterSink::Plain(Box::new(writer))
+        };
+        Ok(Self {
+            sink,
+            written: 0,
+            limit,
+            on_commit,
+        })
+    }
+
+    pub async fn commit(self) -> cacache::Result<Integrity> {
+        let hash = match self.sink {
+            WriterSink::Plain(writer) => (*writer).commit().await?,
+            WriterSink::Compressed(compressor) => compressor.commit().await?,
+        };
+        (self.on_commit)(&hash, self.written)?;
+        Ok(hash)
+    }
+}
+
+impl AsyncWrite for CasWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        if let Err(e) = check_limit(this.limit, this.written, buf) {
+            return Poll::Ready(Err(e));
+        }
+        let poll = match &mut this.sink {
+            WriterSink::Plain(writer) => Pin::new(writer).poll_write(cx, buf),
+            WriterSink::Compressed(compressor) => compressor.poll_write(cx, buf),
+        };
+        if let Poll::Ready(Ok(n)) = poll {
+            this.written += n as u64;
+        }
+        poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match &mut self.get_mut().sink {
+            WriterSink::Plain(writer) => Pin::new(writer).poll_flush(cx),
+            WriterSink::Compressed(compressor) => compressor.poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match &mut self.get_mut().sink {
+            WriterSink::Plain(writer) => Pin::new(writer).poll_shutdown(cx),
+            // Compressed content is only finished off on commit
+            WriterSink::Compressed(compressor) => compressor.poll_flush(cx),
+        }
+    }
+}
+
+enum SyncWriterSink {
+    Plain(Box<cacache::SyncWriter>),
+    Compressed(Box<Compressor<cacache::SyncWriter>>),
+}
+
+/// The blocking counterpart of [`CasWriter`].
+pub struct CasSyncWriter {
+    sink: SyncWriterSink,
+    written: u64,
+    limit: Option<u64>,
+    on_commit: OnCommit,
+}
+
+impl CasSyncWriter {
+    pub fn open(
+        cache: &Path,
+        compress_cas: bool,
+        algorithm: Algorithm,
+        limit: Option<u64>,
+        on_commit: OnCommit,
+    ) -> cacache::Result<Self> {
+        let writer = cacache::WriteOpts::new()
+            .algorithm(algorithm)
+            .open_hash_sync(cache)?;
+        let sink = if compress_cas {
+            SyncWriterSink::Compressed(Box::new(Compressor::new(cache, writer, algorithm)))
+        } else {
+            SyncWriterSink::Plain(Box::new(writer))
+        };
+        Ok(Self {
+            sink,
+            written: 0,
+            limit,
+            on_commit,
+        })
+    }
+
+    pub fn commit(self) -> cacache::Result<Integrity> {
+        let hash = match self.sink {
+            SyncWriterSink::Plain(writer) => (*writer).commit()?,
+            SyncWriterSink::Compressed(compressor) => compressor.commit()?,
+        };
+        (self.on_commit)(&hash, self.written)?;
+        Ok(hash)
+    }
+}
+
+impl Write for CasSyncWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        check_limit(self.limit, self.written, buf)?;
+        let n = match &mut self.sink {
+            SyncWriterSink::Plain(writer) => writer.write(buf)?,
+            SyncWriterSink::Compressed(compressor) => compressor.write(buf)?,
+        };
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.sink {
+            SyncWriterSink::Plain(writer) => writer.flush(),
+            SyncWriterSink::Compressed(compressor) => compressor.writer.flush(),
+        }
+    }
+}
+
+/// Removes the content with the given hash, whether it was stored compressed or not.
+pub fn cas_remove_sync(cache: &Path, hash: &Integrity) -> cacache::Result<()> {
+    if let Some(metadata) = cacache::metadata_sync(cache, compressed_key(hash))? {
+        cacache::remove_sync(cache, compressed_key(hash))?;
+        return cacache::remove_hash_sync(cache, &metadata.integrity);
+    }
+    cacache::remove_hash_sync(cache, hash)
+}
+
+/// A blob in the CAS, as listed by [`cas_list_sync`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CasEntry {
+    /// The hash of the original content, as recorded on frames.
+    pub hash: Integrity,
+    /// Bytes on disk, which for compressed blobs is less than the original content.
+    pub size: u64,
+    pub compressed: bool,
+}
+
+/// Lists every blob in the CAS. Compressed blobs are found through their index entries, and
+/// everything else by walking the content directory, as plain writes leave no index entry.
+pub fn cas_list_sync(cache: &Path) -> cacache::Result<Vec<CasEntry>> {
+    let mut entries = Vec::new();
+    // The compressed bytes' own hashes, so they aren't listed a second time as plain blobs
+    let mut compressed = std::collections::HashSet::new();
+    // cacache fails to list an index it never created
+    let index = cache
+        .join("index-v5")
+        .exists()
+        .then(|| cacache::list_sync(cache));
+    for metadata in index.into_iter().flatten() {
+        let metadata = metadata?;
+        let Some(hash) = metadata.key.strip_prefix(COMPRESSED_KEY_PREFIX) else {
+            continue;
+        };
+        let Ok(hash) = hash.parse() else {
+            continue;
+        };
+        compressed.insert(metadata.integrity);
+        entries.push(CasEntry {
+            hash,
+            size: metadata.size as u64,
+            compressed: true,
+        });
+    }
+
+    // Laid out as `content_path` describes
+    let content = cache.join("content-v2");
+    for algorithm in read_dir_or_empty(&content)? {
+        let Ok(algo) = algorithm.file_name().to_string_lossy().parse::<Algorithm>() else {
+            continue;
+        };
+        for first in read_dir_or_empty(&algorithm.path())? {
+            for second in read_dir_or_empty(&first.path())? {
+                for blob in read_dir_or_empty(&second.path())? {
+                    let hex = format!(
+                        "{}{}{}",
+                        first.file_name().to_string_lossy(),
+                        second.file_name().to_string_lossy(),
+                        blob.file_name().to_string_lossy()
+                    );
+                    let Ok(hash) = Integrity::from_hex(hex, algo) else {
+                        continue;
+                    };
+                    if compressed.contains(&hash) {
+                        continue;
+                    }
+                    entries.push(CasEntry {
+                        hash,
+                        size: blob.metadata().map_err(io_error)?.len(),
+                        compressed: false,
+                    });
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn read_dir_or_empty(path: &Path) -> cacache::Result<Vec<std::fs::DirEntry>> {
+    match std::fs::read_dir(path) {
+        Ok(entries) => entries
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(io_error),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(io_error(e)),
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use fjall::PartitionHandle;
+use ssri::Integrity;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::store::GCTask;
+
+/// Tracks when each blob in the CAS was last written or read, and how big it is, so that once
+/// the CAS grows past [`StoreOptions::cas_max_bytes`](crate::store::StoreOptions) the least
+/// recently used blobs can be evicted. Sizes are of the original, uncompressed content.
+#[derive(Clone)]
+pub struct CasLru {
+    partition: PartitionHandle,
+    // The last tick handed out: wall clock microseconds, nudged forward so that every access
+    // gets a distinct one
+    clock: Arc<AtomicU64>,
+    gc_tx: UnboundedSender<GCTask>,
+}
+
+/// A tracked blob, as returned by [`CasLru::entries`].
+pub struct LruEntry {
+    pub hash: Integrity,
+    pub tick: u64,
+    pub size: u64,
+}
+
+impl CasLru {
+    pub(super) fn new(partition: PartitionHandle, gc_tx: UnboundedSender<GCTask>) -> Self {
+        Self {
+            partition,
+            clock: Arc::new(AtomicU64::new(0)),
+            gc_tx,
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+        let prev = self
+            .clock
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap();
+        now.max(prev + 1)
+    }
+
+    /// Records a blob that was just written, then has the gc worker evict blobs if the CAS is
+    /// now over its cap.
+    pub fn inserted(&self, hash: &Integrity, size: u64) -> Result<(), fjall::Error> {
+        self.record(hash, size)?;
+        let _ = self.gc_tx.send(GCTask::EvictCas);
+        Ok(())
+    }
+
+    /// Marks a blob as just read. Blobs that aren't tracked, such as ones written before the
+    /// store had a cap, are only picked up when their size is known.
+    pub fn touched(&self, hash: &Integrity, size: Option<u64>) -> Result<(), fjall::Error> {
+        let size = match size {
+            Some(size) => size,
+            None => match self.partition.get(hash.to_string())? {
+                Some(value) => decode(&value).1,
+                None => return Ok(()),
+            },
+        };
+        self.record(hash, size)
+    }
+
+    fn record(&self, hash: &Integrity, size: u64) -> Result<(), fjall::Error> {
+        let mut value = [0u8; 16];
+        value[..8].copy_from_slice(&self.tick().to_be_bytes());
+        value[8..].copy_from_slice(&size.to_be_bytes());
+        self.partition.insert(hash.to_string(), value)
+    }
+
+    pub fn remove(&self, hash: &Integrity) -> Result<(), fjall::Error> {
+        self.partition.remove(hash.to_string())
+    }
+
+    /// Every tracked blob, least recently used first.
+    pub fn entries(&self) -> Result<Vec<LruEntry>, fjall::Error> {
+        let mut entries = Vec::new();
+        for kv in self.partition.iter() {
+            let (key, value) = kv?;
+            let Ok(hash) = std::str::from_utf8(&key).unwrap_or_default().parse() else {
+                continue;
+            };
+            let (tick, size) = decode(&value);
+            entries.push(LruEntry { hash, tick, size });
+        }
+        entries.sort_by_key(|entry| entry.tick);
+        Ok(entries)
+    }
+}
+
+fn decode(value: &[u8]) -> (u64, u64) {
+    (
+        u64::from_be_bytes(value[..8].try_into().unwrap()),
+        u64::from_be_bytes(value[8..16].try_into().unwrap()),
+    )
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+
+use scru128::Scru128Id;
+use serde::{Deserialize, Serialize};
+
+use crate::store::{parse_ttl, Frame, StoreError};
+
+/// How frames are encoded on disk. A store keeps the codec it was created with: opening it with
+/// another fails with [`StoreError::CodecMismatch`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Json,
+    /// Smaller and quicker to encode than JSON, for write-heavy stores.
+    Bincode,
+}
+
+impl Codec {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Codec::Json => "json",
+            Codec::Bincode => "bincode",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Codec> {
+        match s {
+            "json" => Some(Codec::Json),
+            "bincode" => Some(Codec::Bincode),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, frame: &Frame) -> Result<Vec<u8>, StoreError> {
+        match self {
+            Codec::Json => Ok(serde_json::to_vec(frame)?),
+            Codec::Bincode => Ok(bincode::serialize(&BincodeFrame::from(frame))?),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<Frame, StoreError> {
+        match self {
+            Codec::Json => Ok(serde_json::from_slice(bytes)?),
+            Codec::Bincode => bincode::deserialize::<BincodeFrame>(bytes)?.try_into(),
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// bincode isn't self-describing, so it can't decode `serde_json::Value` or skipped fields: meta
+// is kept as JSON text, and every field is always written
+#[derive(Serialize, Deserialize)]
+struct BincodeFrame {
+    topic: String,
+    context_id: [u8; 16],
+    id: [u8; 16],
+    seq: u64,
+    hash: Option<String>,
+    meta: Option<String>,
+    ttl: Option<String>,
+    schema: Option<String>,
+}
+
+impl From<&Frame> for BincodeFrame {
+    fn from(frame: &Frame) -> Self {
+        BincodeFrame {
+            topic: frame.topic.clone(),
+            context_id: frame.context_id.to_bytes(),
+            id: frame.id.to_bytes(),
+            seq: frame.seq,
+            hash: frame.hash.as_ref().map(|hash| hash.to_string()),
+            meta: frame.meta.as_ref().map(|meta| meta.to_string()),
+            ttl: frame
+                .ttl
+                .as_ref()
+                .map(|ttl| ttl.to_query().trim_start_matches("ttl=").to_string()),
+            schema: frame.schema.clone(),
+        }
+    }
+}
+
+impl TryFrom<BincodeFrame> for Frame {
+    type Error = StoreError;
+
+    fn try_from(frame: BincodeFrame) -> Result<Self, Self::Error> {
+        let invalid = |e: String| StoreError::InvalidFrame(format!("undecodable frame: {}", e));
+        Ok(Frame {
+            topic: frame.topic,
+            context_id: Scru128Id::from_bytes(frame.context_id),
+            id: Scru128Id::from_bytes(frame.id),
+            seq: frame.seq,
+            hash: frame
+                .hash
+                .map(|hash| hash.parse())
+                .transpose()
+                .map_err(|e: ssri::Error| invalid(e.to_string()))?,
+            meta: frame
+                .meta
+                .map(|meta| serde_json::from_str(&meta))
+                .transpose()?,
+            ttl: frame
+                .ttl
+                .map(|ttl| parse_ttl(&ttl))
+                .transpose()
+                .map_err(invalid)?,
+            schema: frame.schema,
+        })
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Everything that can go wrong in the store layer.
+#[derive(Debug)]
+pub enum StoreError {
+    /// The underlying fjall keyspace failed.
+    Db(fjall::Error),
+    /// Reading or writing CAS content failed.
+    Cas(cacache::Error),
+    /// A frame couldn't be encoded or decoded.
+    Serde(serde_json::Error),
+    /// The frame was rejected, e.g. for referencing an unknown context.
+    InvalidFrame(String),
+    /// The store's background worker has stopped.
+    Closed,
+    /// Another store already has the directory open.
+    AlreadyLocked { path: PathBuf },
+    /// Setting up the store directory failed.
+    Io(std::io::Error),
+    /// A frame couldn't be encoded or decoded with bincode.
+    Bincode(bincode::Error),
+    /// The store was created with a different codec than it's being opened with.
+    CodecMismatch {
+        stored: super::Codec,
+        requested: super::Codec,
+    },
+    /// The store records a codec this version doesn't know.
+    UnknownCodec(String),
+    /// A conditional append found a different head on the topic than it expected.
+    HeadMismatch {
+        expected: Option<scru128::Scru128Id>,
+        actual: Option<scru128::Scru128Id>,
+    },
+    /// Content written to the CAS passed the store's `max_content_bytes`.
+    ContentTooLarge(super::ContentTooLarge),
+}
+
+impl StoreError {
+    /// Whether this is CAS content that doesn't exist.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            StoreError::Cas(cacache::Error::EntryNotFound(..)) => true,
+            StoreError::Cas(cacache::Error::IoError(e, _)) => {
+                e.kind() == std::io::ErrorKind::NotFound
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Db(e) => write!(f, "database error: {}", e),
+            StoreError::Cas(e) => write!(f, "CAS error: {}", e),
+            StoreError::Serde(e) => write!(f, "serialization error: {}", e),
+            StoreError::InvalidFrame(msg) => write!(f, "{}", msg),
+            StoreError::Closed => write!(f, "store is closed"),
+            StoreError::AlreadyLocked { path } => {
+                write!(f, "another xs is using this directory: {}", path.display())
+            }
+            StoreError::Io(e) => write!(f, "IO error: {}", e),
+            StoreError::Bincode(e) => write!(f, "serialization error: {}", e),
+            StoreError::CodecMismatch { stored, requested } => write!(
+                f,
+                "store uses the {} codec, but was opened with {}",
+                stored, requested
+            ),
+            StoreError::UnknownCodec(codec) => write!(f, "store uses an unknown codec: {}", codec),
+            StoreError::HeadMismatch { expected, actual } => write!(
+                f,
+                "topic head is {}, expected {}",
+                head_or_empty(actual),
+                head_or_empty(expected)
+            ),
+            StoreError::ContentTooLarge(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Db(e) => Some(e),
+            StoreError::Cas(e) => Some(e),
+            StoreError::Serde(e) => Some(e),
+            StoreError::Io(e) => Some(e),
+            StoreError::Bincode(e) => Some(e),
+            StoreError::ContentTooLarge(e) => Some(e),
+            StoreError::InvalidFrame(_)
+            | StoreError::Closed
+            | StoreError::AlreadyLocked { .. }
+            | StoreError::CodecMismatch { .. }
+            | StoreError::UnknownCodec(_)
+            | StoreError::HeadMismatch { .. } => None,
+        }
+    }
+}
+
+fn head_or_empty(head: &Option<scru128::Scru128Id>) -> String {
+    match head {
+        Some(id) => id.to_string(),
+        None => "empty".to_string(),
+    }
+}
+
+impl From<fjall::Error> for StoreError {
+    fn from(e: fjall::Error) -> Self {
+        StoreError::Db(e)
+    }
+}
+
+impl From<fjall::LsmError> for StoreError {
+    fn from(e: fjall::LsmError) -> Self {
+        StoreError::Db(e.into())
+    }
+}
+
+impl From<cacache::Error> for StoreError {
+    fn from(e: cacache::Error) -> Self {
+        StoreError::Cas(e)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(e: serde_json::Error) -> Self {
+        StoreError::Serde(e)
+    }
+}
+
+impl From<std::io::Error> for StoreError {
+    fn from(e: std::io::Error) -> Self {
+        // A CAS writer's limit surfaces as an IO error
+        match e
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<super::ContentTooLarge>())
+        {
+            Some(too_large) => StoreError::ContentTooLarge(*too_large),
+            None => StoreError::Io(e),
+        }
+    }
+}
+
+impl From<bincode::Error> for StoreError {
+    fn from(e: bincode::Error) -> Self {
+        StoreError::Bincode(e)
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+/// Counts of what a store has done since it was opened, as returned by
+/// [`Store::metrics`](crate::store::Store::metrics).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Metrics {
+    /// Frames appended, ephemeral ones included.
+    pub appends: u64,
+    /// Bytes of content written to the CAS, before any compression.
+    pub cas_bytes_written: u64,
+    /// Reads started with [`Store::read`](crate::store::Store::read).
+    pub reads: u64,
+    /// Frames handed to readers. Synthetic frames, like `xs.threshold`, aren't counted.
+    pub frames_delivered: u64,
+}
+
+// Counters are only ever summed, so relaxed ordering is enough
+#[derive(Default)]
+pub struct Counters {
+    appends: AtomicU64,
+    cas_bytes_written: AtomicU64,
+    reads: AtomicU64,
+    frames_delivered: AtomicU64,
+}
+
+impl Counters {
+    pub fn appended(&self, frames: u64) {
+        self.appends.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    pub fn cas_written(&self, bytes: u64) {
+        self.cas_bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn read_started(&self) {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn frame_delivered(&self) {
+        self.frames_delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> Metrics {
+        Metrics {
+            appends: self.appends.load(Ordering::Relaxed),
+            cas_bytes_written: self.cas_bytes_written.load(Ordering::Relaxed),
+            reads: self.reads.load(Ordering::Relaxed),
+            frames_delivered: self.frames_delivered.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,6 +1,26 @@
+mod cas;
+mod cas_lru;
+mod error;
+pub use cas::{
+    cas_open, CasEntry, CasReader, CasSyncReader, CasSyncWriter, CasWriter, ContentTooLarge,
+};
+pub use error::StoreError;
+
+use cas_lru::CasLru;
+
+mod metrics;
+use metrics::Counters;
+pub use metrics::Metrics;
+
 mod ttl;
 pub use ttl::*;
 
+mod schema;
+use schema::Schemas;
+
+mod codec;
+pub use codec::Codec;
+
 #[cfg(test)]
 mod tests;
 
@@ -10,9 +30,14 @@ use std::time::Duration;
 
 use tokio::sync::broadcast;
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tracing::Instrument;
+
+use crate::thread_pool::ThreadPool;
 
-use std::collections::HashSet;
-use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
 
 use scru128::Scru128Id;
 
@@ -23,6 +48,11 @@ use fjall::{Config, Keyspace, PartitionCreateOptions, PartitionHandle};
 // Context with all bits set to zero for system operations
 pub const ZERO_CONTEXT: Scru128Id = Scru128Id::from_bytes([0; 16]);
 
+// Key in the meta partition for the seq the next appended frame gets
+const NEXT_SEQ_KEY: &str = "next_seq";
+// Key in the meta partition for the codec frames are encoded with
+const CODEC_KEY: &str = "codec";
+
 #[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Default, bon::Builder)]
 pub struct Frame {
     #[builder(start_fn, into)]
@@ -31,9 +61,19 @@ pub struct Frame {
     pub context_id: Scru128Id,
     #[builder(default)]
     pub id: Scru128Id,
+    /// Position of the frame in the stream, assigned on append. Increases with `id`, starting
+    /// from 0, but isn't contiguous: frames can be removed or expire. Ephemeral frames carry
+    /// the seq the next stored frame gets.
+    #[serde(default)]
+    #[builder(default)]
+    pub seq: u64,
     pub hash: Option<ssri::Integrity>,
     pub meta: Option<serde_json::Value>,
     pub ttl: Option<TTL>,
+    /// Name of a registered schema the frame's meta is checked against on append.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub schema: Option<String>,
 }
 
 use std::fmt;
@@ -42,11 +82,13 @@ impl fmt::Debug for Frame {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Frame")
             .field("id", &format!("{}", self.id))
+            .field("seq", &self.seq)
             .field("context_id", &format!("{}", self.context_id))
             .field("topic", &self.topic)
             .field("hash", &self.hash.as_ref().map(|x| format!("{}", x)))
             .field("meta", &self.meta)
             .field("ttl", &self.ttl)
+            .field("schema", &self.schema)
             .finish()
     }
 }
@@ -71,6 +113,132 @@ impl<'de> Deserialize<'de> for FollowOption {
     }
 }
 
+/// Where a read starts: after a specific frame, or after whatever the last frame is at the time
+/// of the read (`last-id=head`).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum LastId {
+    Id(Scru128Id),
+    Head,
+}
+
+impl From<Scru128Id> for LastId {
+    fn from(id: Scru128Id) -> Self {
+        LastId::Id(id)
+    }
+}
+
+impl fmt::Display for LastId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LastId::Id(id) => write!(f, "{}", id),
+            LastId::Head => write!(f, "head"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LastId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        match s.as_str() {
+            "head" => Ok(LastId::Head),
+            s => s.parse().map(LastId::Id).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Where a read starts, in any of the ways a position in the stream can be named. In a query
+/// string it's `cursor=start`, `cursor=head`, `cursor=id:<id>`, `cursor=seq:<n>` or
+/// `cursor=time:<time>`, where the time is RFC 3339 or milliseconds since the Unix epoch.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Cursor {
+    /// From the first frame.
+    Start,
+    /// After whatever the last frame is at the time of the read.
+    Head,
+    /// After the frame with this id.
+    Id(Scru128Id),
+    /// From the frame with this `seq`, inclusive.
+    Seq(u64),
+    /// From the first frame appended at or after this time, as told by its id.
+    Time(chrono::DateTime<chrono::Utc>),
+}
+
+impl From<LastId> for Cursor {
+    fn from(last_id: LastId) -> Self {
+        match last_id {
+            LastId::Id(id) => Cursor::Id(id),
+            LastId::Head => Cursor::Head,
+        }
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cursor::Start => write!(f, "start"),
+            Cursor::Head => write!(f, "head"),
+            Cursor::Id(id) => write!(f, "id:{}", id),
+            Cursor::Seq(seq) => write!(f, "seq:{}", seq),
+            Cursor::Time(time) => write!(
+                f,
+                "time:{}",
+                time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+            ),
+        }
+    }
+}
+
+impl std::str::FromStr for Cursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |e: &dyn fmt::Display| format!("Invalid cursor {:?}: {}", s, e);
+        match s.split_once(':') {
+            None if s == "start" => Ok(Cursor::Start),
+            None if s == "head" => Ok(Cursor::Head),
+            Some(("id", id)) => id.parse().map(Cursor::Id).map_err(|e| invalid(&e)),
+            Some(("seq", seq)) => seq.parse().map(Cursor::Seq).map_err(|e| invalid(&e)),
+            Some(("time", time)) => match time.parse::<i64>() {
+                Ok(ms) => chrono::DateTime::from_timestamp_millis(ms)
+                    .map(Cursor::Time)
+                    .ok_or_else(|| invalid(&"out of range")),
+                Err(_) => chrono::DateTime::parse_from_rfc3339(time)
+                    .map(|time| Cursor::Time(time.to_utc()))
+                    .map_err(|e| invalid(&e)),
+            },
+            _ => Err(invalid(
+                &"expected start, head, id:<id>, seq:<n> or time:<time>",
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+fn deserialize_topics<'de, D>(deserializer: D) -> Result<Option<HashSet<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    Ok(Some(
+        s.split(',')
+            .filter(|topic| !topic.is_empty())
+            .map(String::from)
+            .collect(),
+    ))
+}
+
 fn deserialize_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: Deserializer<'de>,
@@ -91,10 +259,57 @@ pub struct ReadOptions {
     #[builder(default)]
     pub tail: bool,
     #[serde(rename = "last-id")]
-    pub last_id: Option<Scru128Id>,
+    #[builder(into)]
+    pub last_id: Option<LastId>,
+    /// Where to start reading. Takes precedence over `last_id` and `from_seq`.
+    pub cursor: Option<Cursor>,
     pub limit: Option<usize>,
+    /// Emit the last N historical frames before following. Takes precedence over `tail`.
+    #[serde(rename = "last-n")]
+    pub last_n: Option<usize>,
     #[serde(rename = "context-id")]
     pub context_id: Option<Scru128Id>,
+    #[serde(rename = "emit-eoh", default, deserialize_with = "deserialize_bool")]
+    #[builder(default)]
+    pub emit_eoh: bool,
+    /// Only read frames whose topic matches this pattern. See [`topic_matches`]. Control frames,
+    /// such as `xs.threshold` and `xs.pulse`, are always delivered.
+    #[builder(into)]
+    pub topic: Option<String>,
+    /// Only read frames whose topic is exactly one of these, given as `topics=a,b,c`. Checked
+    /// alongside `topic`, if both are set. Control frames are always delivered.
+    #[serde(default, deserialize_with = "deserialize_topics")]
+    pub topics: Option<HashSet<String>>,
+    /// Start from the frame with this `seq`, inclusive: an alternative to `last_id`.
+    #[serde(rename = "from-seq")]
+    pub from_seq: Option<u64>,
+    /// Pace historical frames to at most this many per second. Live frames aren't paced.
+    #[serde(rename = "max-rate")]
+    pub max_rate: Option<f64>,
+    /// Emit an `xs.gap` frame before a frame whose `seq` doesn't follow on from the previous
+    /// frame read, describing the missing range.
+    #[serde(rename = "report-gaps", default, deserialize_with = "deserialize_bool")]
+    #[builder(default)]
+    pub report_gaps: bool,
+    /// Skip a frame whose `hash` is the same as the last frame delivered with the same context
+    /// and topic: the key [`CompactStrategy::LatestPerTopic`] compacts by. Only changes to a
+    /// topic's content come through. Frames without content are always delivered.
+    #[serde(
+        rename = "dedupe-by-hash",
+        default,
+        deserialize_with = "deserialize_bool"
+    )]
+    #[builder(default)]
+    pub dedupe_by_hash: bool,
+    /// Deliver the `xs.remove` tombstones [`Store::remove`] leaves, inline with the other
+    /// frames. They're skipped otherwise.
+    #[serde(
+        rename = "include-tombstones",
+        default,
+        deserialize_with = "deserialize_bool"
+    )]
+    #[builder(default)]
+    pub include_tombstones: bool,
 }
 
 impl ReadOptions {
@@ -131,11 +346,63 @@ impl ReadOptions {
             params.push(("last-id", last_id.to_string()));
         }
 
+        // Add cursor if present
+        if let Some(cursor) = self.cursor {
+            params.push(("cursor", cursor.to_string()));
+        }
+
         // Add limit if present
         if let Some(limit) = self.limit {
             params.push(("limit", limit.to_string()));
         }
 
+        // Add last-n if present
+        if let Some(last_n) = self.last_n {
+            params.push(("last-n", last_n.to_string()));
+        }
+
+        // Add emit-eoh if true
+        if self.emit_eoh {
+            params.push(("emit-eoh", "true".to_string()));
+        }
+
+        // Add topic if present
+        if let Some(topic) = &self.topic {
+            params.push(("topic", topic.clone()));
+        }
+
+        // Add topics, sorted so the query is stable
+        if let Some(topics) = &self.topics {
+            let mut topics: Vec<_> = topics.iter().map(String::as_str).collect();
+            topics.sort();
+            params.push(("topics", topics.join(",")));
+        }
+
+        // Add from-seq if present
+        if let Some(from_seq) = self.from_seq {
+            params.push(("from-seq", from_seq.to_string()));
+        }
+
+        // Add max-rate if present
+        if let Some(max_rate) = self.max_rate {
+            params.push(("max-rate", max_rate.to_string()));
+        }
+
+        // Add report-gaps if true
+        if self.report_gaps {
+            params.push(("report-gaps", "true".to_string()));
+        }
+
+        // Add dedupe-by-hash if true
+        if self.dedupe_by_hash {
+            params.push(("dedupe-by-hash", "true".to_string()));
+        }
+
+        // Add include-tombstones if true
+        if self.include_tombstones {
+            params.push(("include-tombstones", "true".to_string()));
+        }
+
         // Return empty string if no params
         if params.is_empty() {
             String::new()
@@ -145,6 +412,71 @@ impl ReadOptions {
                 .finish()
         }
     }
+
+    /// The `xs.gap` frame to send ahead of `frame`, if gaps are reported and frames between the
+    /// previous frame read and this one are missing.
+    fn gap(&self, prev_seq: Option<u64>, frame: &Frame) -> Option<Frame> {
+        let prev_seq = prev_seq.filter(|_| self.report_gaps)?;
+        if frame.seq <= prev_seq + 1 {
+            return None;
+        }
+        Some(
+            Frame::builder("xs.gap", self.context_id.unwrap_or(ZERO_CONTEXT))
+                .id(scru128::new())
+                .ttl(TTL::Ephemeral)
+                .meta(serde_json::json!({
+                    "from_seq": prev_seq + 1,
+                    "to_seq": frame.seq - 1,
+                }))
+                .build(),
+        )
+    }
+
+    /// Where the read starts: `cursor` if it's set, and otherwise `last_id`.
+    fn start(&self) -> Option<Cursor> {
+        self.cursor.or(self.last_id.map(Into::into))
+    }
+
+    fn matches(&self, frame: &Frame) -> bool {
+        let from_seq = match self.cursor {
+            Some(Cursor::Seq(seq)) => Some(seq),
+            Some(_) => None,
+            None => self.from_seq,
+        };
+        self.topic
+            .as_deref()
+            .is_none_or(|pattern| topic_matches(pattern, &frame.topic))
+            && self
+                .topics
+                .as_ref()
+                .is_none_or(|topics| topics.contains(&frame.topic))
+            && from_seq.is_none_or(|seq| frame.seq >= seq)
+            && (self.include_tombstones || frame.topic != "xs.remove")
+    }
+}
+
+/// Matches a topic against a pattern where `*` matches any run of characters, including `.`.
+/// `logs.*` matches `logs.app` and `logs.app.error`, `*.error` matches `logs.app.error`, and a
+/// pattern without `*` matches only that exact topic.
+pub fn topic_matches(pattern: &str, topic: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // split always yields at least one part
+    let first = parts.next().unwrap();
+    let Some(mut rest) = topic.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // no `*` in the pattern
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
 }
 
 #[derive(Default, PartialEq, Clone, Debug)]
@@ -164,86 +496,656 @@ enum GCTask {
         keep: u32,
     },
     Drain(tokio::sync::oneshot::Sender<()>),
+    Compact {
+        strategy: CompactStrategy,
+        tx: tokio::sync::oneshot::Sender<Result<Vec<ssri::Integrity>, StoreError>>,
+    },
+    EvictCas,
+    RenameTopic {
+        from: String,
+        to: String,
+        tx: tokio::sync::oneshot::Sender<Result<usize, StoreError>>,
+    },
+    Shutdown,
+}
+
+/// Which frames [`Store::compact`] keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompactStrategy {
+    /// Keep only the latest frame of each topic, per context.
+    LatestPerTopic,
+}
+
+#[derive(Clone, Debug, bon::Builder)]
+pub struct StoreOptions {
+    /// Store CAS content deflate-compressed. Hashes still describe the original content, and
+    /// reads decompress transparently, so this is invisible to clients. CAS writers and readers
+    /// compress and decompress as content streams through, without holding all of it in memory,
+    /// so a reader only finds content doesn't match its hash once it gets to the end.
+    #[builder(default)]
+    pub compress_cas: bool,
+    /// How many appended frames are held for followers before slow followers start lagging.
+    #[builder(default = 1024)]
+    pub broadcast_buffer: usize,
+    /// Capacity of the channel each reader receives frames on.
+    #[builder(default = 100)]
+    pub subscriber_buffer: usize,
+    /// Announce followers coming and going with ephemeral `xs.connect` and `xs.disconnect`
+    /// frames, carrying a subscriber id and the follower's read options.
+    #[builder(default)]
+    pub audit_subscribers: bool,
+    /// Have [`Store::remove`] leave an `xs.remove` tombstone for each frame it removes, so
+    /// removals are part of the stream's history.
+    #[builder(default)]
+    pub tombstones: bool,
+    /// How frames are encoded on disk. Fixed when the store is created.
+    #[builder(default)]
+    pub codec: Codec,
+    /// The hash algorithm CAS content is stored under, and so the algorithm of the hashes on
+    /// frames. Content stored under other algorithms still reads back: a hash names its
+    /// algorithm.
+    #[builder(default = ssri::Algorithm::Sha256)]
+    pub cas_algorithm: ssri::Algorithm,
+    /// Caps the size of the CAS, in bytes of original content. Once a write takes it past the
+    /// cap, the least recently written or read content that no frame refers to is evicted
+    /// until it's back under. Content written while the store had no cap isn't counted.
+    pub cas_max_bytes: Option<u64>,
+    /// Size in bytes of the block cache all of the store's partitions share. Defaults to fjall's
+    /// 16 MiB.
+    pub block_cache_bytes: Option<u64>,
+    /// How many bytes of writes are held in memory, across partitions, before the largest
+    /// memtable is flushed to disk. Must be at least 1 MiB. Defaults to fjall's 64 MiB.
+    pub write_buffer_bytes: Option<u64>,
+    /// How the store's partitions compress their blocks on disk. Fixed when each partition is
+    /// created: reopening a store with another compression doesn't change it. Defaults to
+    /// fjall's LZ4.
+    pub compression: Option<fjall::CompressionType>,
+    /// The most content, in bytes, a single CAS write can hold. Larger writes fail with
+    /// [`StoreError::ContentTooLarge`] as soon as they pass the limit, and leave nothing behind.
+    pub max_content_bytes: Option<u64>,
+    /// How far ahead of the wall clock an id can be. Ids given to [`Store::append_with_id`]
+    /// further ahead than this are rejected. Fresh ids still never go backwards: if the clock
+    /// does, say after an NTP adjustment, they count up from the last id handed out, with a
+    /// warning once it's further ahead than this. Unset, any id is accepted and nothing is
+    /// logged.
+    pub max_clock_skew: Option<Duration>,
+}
+
+impl Default for StoreOptions {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// A topic and the frames it holds, as returned by [`Store::topic_summaries`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicSummary {
+    pub topic: String,
+    pub count: usize,
+    pub last_id: Scru128Id,
+}
+
+/// Diagnostics about a store, as returned by [`Store::info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreInfo {
+    pub path: PathBuf,
+    /// The oldest frame still held, if any.
+    pub first_id: Option<Scru128Id>,
+    /// The newest frame held, if any.
+    pub last_id: Option<Scru128Id>,
+    pub frame_count: usize,
+    /// When this store was opened.
+    pub started_at: std::time::SystemTime,
+}
+
+// Ids and seqs are handed out together, under one lock
+#[derive(Clone)]
+struct Sequence {
+    // The seq the next stored frame gets
+    next_seq: u64,
+    // The greatest id handed out so far: new ids always follow it
+    last_id: Scru128Id,
+    // Whether fresh ids are being counted up from `last_id` and that's been warned about
+    skew_warned: bool,
+}
+
+/// The hash of the last frame delivered for each context and topic, for reads that
+/// `dedupe_by_hash`.
+#[derive(Default)]
+struct LastHashes(HashMap<(Scru128Id, String), ssri::Integrity>);
+
+impl LastHashes {
+    fn record(&mut self, frame: &Frame) {
+        if let Some(hash) = &frame.hash {
+            self.0
+                .insert((frame.context_id, frame.topic.clone()), hash.clone());
+        }
+    }
+
+    /// Whether `frame` changes its topic's content, recording it if so.
+    fn admit(&mut self, frame: &Frame) -> bool {
+        let Some(hash) = &frame.hash else {
+            return true;
+        };
+        if self.0.get(&(frame.context_id, frame.topic.clone())) == Some(hash) {
+            return false;
+        }
+        self.record(frame);
+        true
+    }
+}
+
+/// Appends a follower's `xs.disconnect` once it's gone.
+struct DisconnectOnDrop {
+    store: Store,
+    meta: serde_json::Value,
+}
+
+impl Drop for DisconnectOnDrop {
+    fn drop(&mut self) {
+        let _ = self.store.append(
+            Frame::builder("xs.disconnect", ZERO_CONTEXT)
+                .meta(self.meta.clone())
+                .ttl(TTL::Ephemeral)
+                .build(),
+        );
+    }
 }
 
 #[derive(Clone)]
 pub struct Store {
     pub path: PathBuf,
+    options: StoreOptions,
     keyspace: Keyspace,
     frame_partition: PartitionHandle,
     idx_topic: PartitionHandle,
     idx_context: PartitionHandle,
+    meta_partition: PartitionHandle,
+    sequence: Arc<Mutex<Sequence>>,
+    // Appends hold this shared from assigning an id until the frame is written, so
+    // `append_if` can hold it exclusively to see a topic's head that no append is racing
+    append_gate: Arc<RwLock<()>>,
     contexts: Arc<RwLock<HashSet<Scru128Id>>>,
+    // How many frames each topic has, across contexts, so a topic drops out once its last frame
+    // is removed
+    topics: Arc<RwLock<HashMap<String, usize>>>,
+    schemas: Schemas,
+    // Only kept when the CAS has a cap
+    cas_lru: Option<CasLru>,
+    counters: Arc<Counters>,
     broadcast_tx: broadcast::Sender<Frame>,
     gc_tx: UnboundedSender<GCTask>,
+    // Held while any clone handed out by `Store::open` is alive, but not by the gc worker's
+    _lock: Option<Arc<StoreLock>>,
+    started_at: std::time::SystemTime,
 }
 
 impl Store {
     pub fn new(path: PathBuf) -> Store {
-        let config = Config::new(path.join("fjall"));
-        let keyspace = config
-            .flush_workers(1)
-            .compaction_workers(1)
-            .open()
-            .unwrap();
-
-        let frame_partition = keyspace
-            .open_partition("stream", PartitionCreateOptions::default())
-            .unwrap();
+        Store::with_options(path, StoreOptions::default())
+    }
 
-        let idx_topic = keyspace
-            .open_partition("idx_topic", PartitionCreateOptions::default())
-            .unwrap();
+    pub fn with_options(path: PathBuf, options: StoreOptions) -> Store {
+        Store::open(path, options).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        let idx_context = keyspace
-            .open_partition("idx_context", PartitionCreateOptions::default())
-            .unwrap();
+    /// Opens the store at `path`, failing with [`StoreError::AlreadyLocked`] if another store,
+    /// in this process or another, already has it open.
+    pub fn open(path: PathBuf, options: StoreOptions) -> Result<Store, StoreError> {
+        std::fs::create_dir_all(&path)?;
+        let lock = std::fs::File::create(path.join("lock"))?;
+        lock.try_lock().map_err(|e| match e {
+            std::fs::TryLockError::WouldBlock => StoreError::AlreadyLocked { path: path.clone() },
+            std::fs::TryLockError::Error(e) => e.into(),
+        })?;
+
+        let mut config = Config::new(path.join("fjall"))
+            .flush_workers(1)
+            .compaction_workers(1);
+        if let Some(bytes) = options.block_cache_bytes {
+            config = config.block_cache(Arc::new(fjall::BlockCache::with_capacity_bytes(bytes)));
+        }
+        if let Some(bytes) = options.write_buffer_bytes {
+            config = config.max_write_buffer_size(bytes);
+        }
+        let keyspace = config.open()?;
 
-        let (broadcast_tx, _) = broadcast::channel(1024);
+        let partition_options = || {
+            let partition_options = PartitionCreateOptions::default();
+            match options.compression {
+                Some(compression) => partition_options.compression(compression),
+                None => partition_options,
+            }
+        };
+        let frame_partition = keyspace.open_partition("stream", partition_options())?;
+        let idx_topic = keyspace.open_partition("idx_topic", partition_options())?;
+        let idx_context = keyspace.open_partition("idx_context", partition_options())?;
+        let meta_partition = keyspace.open_partition("meta", partition_options())?;
+
+        // Appends can commit out of order, so the persisted high-water mark may trail the last
+        // frame's seq
+        let persisted_seq = meta_partition
+            .get(NEXT_SEQ_KEY)?
+            .map(|v| u64::from_be_bytes(v.as_ref().try_into().unwrap()))
+            .unwrap_or(0);
+        // Stores from before the codec was recorded are JSON
+        let stored_codec = match meta_partition.get(CODEC_KEY)? {
+            Some(stored) => {
+                let stored = String::from_utf8_lossy(&stored);
+                Codec::parse(&stored).ok_or_else(|| StoreError::UnknownCodec(stored.into()))?
+            }
+            None if frame_partition.is_empty()? => options.codec,
+            None => Codec::Json,
+        };
+        if stored_codec != options.codec {
+            return Err(StoreError::CodecMismatch {
+                stored: stored_codec,
+                requested: options.codec,
+            });
+        }
+        meta_partition.insert(CODEC_KEY, stored_codec.as_str())?;
+
+        let last_seq = frame_partition
+            .last_key_value()?
+            .map(|kv| deserialize_frame(options.codec, kv).map(|frame| frame.seq + 1))
+            .transpose()?
+            .unwrap_or(0);
+        let next_seq = persisted_seq.max(last_seq);
+        let last_id = frame_partition
+            .last_key_value()?
+            .map(|(key, _)| id_from_key(&key))
+            .unwrap_or(ZERO_CONTEXT);
+
+        let (broadcast_tx, _) = broadcast::channel(options.broadcast_buffer);
         let (gc_tx, gc_rx) = mpsc::unbounded_channel();
 
+        let cas_lru = match options.cas_max_bytes {
+            Some(_) => Some(CasLru::new(
+                keyspace.open_partition("cas_lru", partition_options())?,
+                gc_tx.clone(),
+            )),
+            None => None,
+        };
+
         let mut contexts = HashSet::new();
         contexts.insert(ZERO_CONTEXT); // System context is always valid
 
+        // Rebuilt from the topic index's keys alone, without reading any frames
+        let mut topics = HashMap::new();
+        for key in idx_topic.keys() {
+            let key = key?;
+            let topic = String::from_utf8_lossy(&key[16..key.len() - 17]).into_owned();
+            *topics.entry(topic).or_insert(0) += 1;
+        }
+
         let store = Store {
             path: path.clone(),
+            options,
             keyspace: keyspace.clone(),
             frame_partition: frame_partition.clone(),
             idx_topic: idx_topic.clone(),
             idx_context: idx_context.clone(),
+            meta_partition,
+            sequence: Arc::new(Mutex::new(Sequence {
+                next_seq,
+                last_id,
+                skew_warned: false,
+            })),
+            append_gate: Arc::default(),
             contexts: Arc::new(RwLock::new(contexts)),
+            topics: Arc::new(RwLock::new(topics)),
+            schemas: Schemas::default(),
+            cas_lru,
+            counters: Arc::default(),
             broadcast_tx,
             gc_tx,
+            _lock: None,
+            started_at: std::time::SystemTime::now(),
         };
 
         // Load context registrations
-        for frame in store.read_sync(None, None, Some(ZERO_CONTEXT)) {
+        for frame in store.read_sync(ReadOptions::builder().context_id(ZERO_CONTEXT).build()) {
             if frame.topic == "xs.context" {
                 store.contexts.write().unwrap().insert(frame.id);
             }
         }
 
+        // Load schema registrations. They were checked when appended, so they compile
+        for frame in store.read_sync(ReadOptions::builder().topic("xs.schema").build()) {
+            if let Ok((name, validator)) = Schemas::compile(&frame) {
+                store.schemas.insert(name, validator);
+            }
+        }
+
+        store.sweep_ttl();
+
         // Spawn gc worker thread
-        spawn_gc_worker(gc_rx, store.clone());
+        let gc_worker = spawn_gc_worker(gc_rx, store.clone());
+
+        Ok(Store {
+            _lock: Some(Arc::new(StoreLock {
+                _file: lock,
+                gc_tx: store.gc_tx.clone(),
+                gc_worker: Some(gc_worker),
+            })),
+            ..store
+        })
+    }
+
+    /// Syncs everything written so far to disk. Appends already do this before returning: this
+    /// covers writes that don't, such as the CAS usage tracked for
+    /// [`StoreOptions::cas_max_bytes`].
+    pub fn flush(&self) -> Result<(), StoreError> {
+        Ok(self.keyspace.persist(fjall::PersistMode::SyncAll)?)
+    }
+
+    pub async fn wait_for_gc(&self) -> Result<(), StoreError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.gc_tx
+            .send(GCTask::Drain(tx))
+            .map_err(|_| StoreError::Closed)?;
+        rx.await.map_err(|_| StoreError::Closed)
+    }
+
+    /// Removes the frames `strategy` doesn't keep from disk. `xs.context` and `xs.schema` frames
+    /// are always kept. Under [`CompactStrategy::LatestPerTopic`], a topic whose latest frame was
+    /// removed, by [`Store::remove`], is removed altogether: its `xs.remove` tombstone
+    /// suppresses the frame that came before.
+    /// Returns the hashes of the removed frames that no remaining frame refers to, so their CAS
+    /// content can be collected. Runs on the gc worker, so it doesn't race with TTL removals.
+    pub async fn compact(
+        &self,
+        strategy: CompactStrategy,
+    ) -> Result<Vec<ssri::Integrity>, StoreError> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.gc_tx
+            .send(GCTask::Compact { strategy, tx })
+            .map_err(|_| StoreError::Closed)?;
+        rx.await.map_err(|_| StoreError::Closed)?
+    }
+
+    fn compact_sync(&self, strategy: CompactStrategy) -> Result<Vec<ssri::Integrity>, StoreError> {
+        let mut to_remove = Vec::new();
+        match strategy {
+            CompactStrategy::LatestPerTopic => {
+                let removed = self.removed_heads(None)?;
+                // The topic index is ordered by context and topic, then id: every key but the
+                // last of each run of the same context and topic is superseded
+                let mut previous: Option<(Vec<u8>, Scru128Id)> = None;
+                let mut latest = Vec::new();
+                for entry in self.idx_topic.iter() {
+                    let (key, _) = entry?;
+                    let prefix = key[..key.len() - 16].to_vec();
+                    let id = idx_topic_frame_id_from_key(&key);
+                    if let Some((previous_prefix, previous_id)) = previous.take() {
+                        if previous_prefix == prefix {
+                            to_remove.push(previous_id);
+                        } else {
+                            latest.push((previous_prefix, previous_id));
+                        }
+                    }
+                    previous = Some((prefix, id));
+                }
+                latest.extend(previous);
+                // A topic whose latest frame was removed compacts away entirely, rather than
+                // back to the frame before it
+                to_remove.extend(
+                    latest
+                        .into_iter()
+                        .filter(|(prefix, id)| removed.get(prefix).is_some_and(|r| r > id))
+                        .map(|(_, id)| id),
+                );
+            }
+        }
+
+        self.drop_frames(self.get_many(&to_remove)?.into_iter().flatten())
+    }
 
-        store
+    // The id of the newest frame removed from each context and topic, going by the `xs.remove`
+    // tombstones, keyed by the topic index prefix of the context and topic
+    fn removed_heads(
+        &self,
+        context_id: Option<Scru128Id>,
+    ) -> Result<HashMap<Vec<u8>, Scru128Id>, StoreError> {
+        let contexts: Vec<_> = match context_id {
+            Some(context_id) => vec![context_id],
+            None => self.contexts.read().unwrap().iter().copied().collect(),
+        };
+        let mut removed = HashMap::new();
+        for context_id in contexts {
+            for kv in self
+                .idx_topic
+                .prefix(idx_topic_key_prefix(context_id, "xs.remove"))
+            {
+                let (key, _) = kv?;
+                let Some(tombstone) = self.get(&idx_topic_frame_id_from_key(&key))? else {
+                    continue;
+                };
+                let Some(meta) = tombstone.meta.as_ref() else {
+                    continue;
+                };
+                let (Some(id), Some(topic)) = (
+                    meta.get("id")
+                        .and_then(|id| id.as_str())
+                        .and_then(|id| id.parse::<Scru128Id>().ok()),
+                    meta.get("topic").and_then(|topic| topic.as_str()),
+                ) else {
+                    continue;
+                };
+                let head = removed
+                    .entry(idx_topic_key_prefix(context_id, topic))
+                    .or_insert(id);
+                *head = (*head).max(id);
+            }
+        }
+        Ok(removed)
     }
 
-    pub async fn wait_for_gc(&self) {
+    /// Moves every frame on topic `from` to topic `to`, across all contexts, rewriting each in
+    /// place: ids, seqs and everything else about the frames stay as they were. Followers are
+    /// sent an ephemeral `xs.rename` frame, with `from`, `to` and the number of frames moved in
+    /// its meta. Returns that number. Runs on the gc worker, so it doesn't race with TTL
+    /// removals, and appends wait for it to finish. `xs.context` and `xs.schema` can't be
+    /// renamed to or from.
+    pub async fn rename_topic(&self, from: &str, to: &str) -> Result<usize, StoreError> {
         let (tx, rx) = tokio::sync::oneshot::channel();
-        let _ = self.gc_tx.send(GCTask::Drain(tx));
-        let _ = rx.await;
+        self.gc_tx
+            .send(GCTask::RenameTopic {
+                from: from.to_string(),
+                to: to.to_string(),
+                tx,
+            })
+            .map_err(|_| StoreError::Closed)?;
+        rx.await.map_err(|_| StoreError::Closed)?
+    }
+
+    fn rename_topic_sync(&self, from: &str, to: &str) -> Result<usize, StoreError> {
+        for topic in [from, to] {
+            if topic == "xs.context" || topic == "xs.schema" {
+                return Err(StoreError::InvalidFrame(format!(
+                    "{} frames can't be renamed",
+                    topic
+                )));
+            }
+        }
+        if from == to {
+            return Ok(0);
+        }
+
+        let renamed = {
+            let _gate = self.append_gate.write().unwrap();
+            let mut batch = self.keyspace.batch();
+            let mut renamed = 0;
+            for key in self.idx_topic.keys() {
+                let key = key?;
+                if &key[16..key.len() - 17] != from.as_bytes() {
+                    continue;
+                }
+                let Some(mut frame) = self.get(&idx_topic_frame_id_from_key(&key))? else {
+                    continue;
+                };
+                batch.remove(&self.idx_topic, idx_topic_key_from_frame(&frame));
+                frame.topic = to.to_string();
+                batch.insert(
+                    &self.frame_partition,
+                    frame.id.as_bytes(),
+                    self.options.codec.encode(&frame)?,
+                );
+                batch.insert(&self.idx_topic, idx_topic_key_from_frame(&frame), b"");
+                renamed += 1;
+            }
+            batch.commit()?;
+            self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+            for _ in 0..renamed {
+                self.topic_removed(from);
+                self.topic_added(to);
+            }
+            renamed
+        };
+
+        self.append(
+            Frame::builder("xs.rename", ZERO_CONTEXT)
+                .meta(serde_json::json!({"from": from, "to": to, "count": renamed}))
+                .ttl(TTL::Ephemeral)
+                .build(),
+        )?;
+        Ok(renamed)
+    }
+
+    /// Removes every frame, or only those on `topic`, across all contexts. `xs.context` and
+    /// `xs.schema` frames are kept. Followers are sent an ephemeral `xs.clear` frame, with the
+    /// topic, if any, in its meta. Returns the hashes no remaining frame refers to, as
+    /// [`Store::compact`] does.
+    pub fn clear(&self, topic: Option<&str>) -> Result<Vec<ssri::Integrity>, StoreError> {
+        let frames: Vec<_> = self
+            .iter_frames(None, None)
+            .filter(|frame| topic.is_none_or(|topic| frame.topic == topic))
+            .collect();
+        let dropped_hashes = self.drop_frames(frames)?;
+        self.append(
+            Frame::builder("xs.clear", ZERO_CONTEXT)
+                .maybe_meta(topic.map(|topic| serde_json::json!({"topic": topic})))
+                .ttl(TTL::Ephemeral)
+                .build(),
+        )?;
+        Ok(dropped_hashes)
+    }
+
+    /// Archives the stream up to and including the frame `upto`, or all of it: writes each frame
+    /// to `w` as a line of JSON, oldest first, then removes them in a single atomic write, so
+    /// readers see either all of them or none. `xs.context` and `xs.schema` frames are neither
+    /// written nor removed. Returns the hashes no remaining frame refers to, as
+    /// [`Store::compact`] does. Nothing is removed if writing fails.
+    pub fn drain_to_writer<W: std::io::Write>(
+        &self,
+        mut w: W,
+        upto: Option<Scru128Id>,
+    ) -> Result<Vec<ssri::Integrity>, StoreError> {
+        let mut drained = Vec::new();
+        for frame in self
+            .iter_frames(None, None)
+            .take_while(|frame| upto.is_none_or(|upto| frame.id <= upto))
+            .filter(|frame| frame.topic != "xs.context" && frame.topic != "xs.schema")
+        {
+            serde_json::to_writer(&mut w, &frame)?;
+            w.write_all(b"\n")?;
+            drained.push(frame);
+        }
+        w.flush()?;
+        self.drop_frames(drained)
+    }
+
+    /// Removes `frames` from disk, other than registrations, and returns the hashes no remaining
+    /// frame refers to.
+    fn drop_frames(
+        &self,
+        frames: impl IntoIterator<Item = Frame>,
+    ) -> Result<Vec<ssri::Integrity>, StoreError> {
+        let mut dropped_hashes = HashSet::new();
+        {
+            // As in `delete`
+            let _gate = self.append_gate.write().unwrap();
+            let mut batch = self.keyspace.batch();
+            let mut dropped_topics = Vec::new();
+            for frame in frames {
+                if frame.topic == "xs.context" || frame.topic == "xs.schema" {
+                    continue;
+                }
+                batch.remove(&self.frame_partition, frame.id.as_bytes());
+                batch.remove(&self.idx_topic, idx_topic_key_from_frame(&frame));
+                batch.remove(&self.idx_context, idx_context_key_from_frame(&frame));
+                dropped_hashes.extend(frame.hash);
+                dropped_topics.push(frame.topic);
+            }
+            batch.commit()?;
+            for topic in &dropped_topics {
+                self.topic_removed(topic);
+            }
+            self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        }
+
+        // Content is shared between frames with the same hash
+        for frame in self.read_sync(ReadOptions::default()) {
+            if let Some(hash) = &frame.hash {
+                dropped_hashes.remove(hash);
+            }
+        }
+
+        Ok(dropped_hashes.into_iter().collect())
     }
 
     #[tracing::instrument(skip(self))]
     pub async fn read(&self, options: ReadOptions) -> tokio::sync::mpsc::Receiver<Frame> {
-        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        self.read_inner(options, None).await
+    }
+
+    /// Like [`Store::read`], but calls `on_caught_up` at the boundary between history and live
+    /// frames, in place of delivering `xs.threshold`: after the last historical frame has been
+    /// queued for the reader, and before the first live one is. A read with no history to catch
+    /// up on, such as a `tail`, is caught up straight away, and one that doesn't follow is caught
+    /// up once its history runs out. The callback runs at most once, and not at all if the
+    /// reader goes away first, or if the read reaches its `limit` within its history.
+    #[tracing::instrument(skip(self, on_caught_up))]
+    pub async fn read_with_boundary(
+        &self,
+        options: ReadOptions,
+        on_caught_up: impl FnOnce() + Send + 'static,
+    ) -> tokio::sync::mpsc::Receiver<Frame> {
+        self.read_inner(options, Some(Box::new(on_caught_up))).await
+    }
+
+    async fn read_inner(
+        &self,
+        options: ReadOptions,
+        on_caught_up: Option<Box<dyn FnOnce() + Send>>,
+    ) -> tokio::sync::mpsc::Receiver<Frame> {
+        self.counters.read_started();
+        let (tx, rx) = tokio::sync::mpsc::channel(self.options.subscriber_buffer);
 
         let should_follow = matches!(
             options.follow,
             FollowOption::On | FollowOption::WithHeartbeat(_)
         );
 
+        // Announce the follower, if enabled. This happens before subscribing, so followers
+        // don't see their own `xs.connect`.
+        let disconnect = (should_follow && self.options.audit_subscribers).then(|| {
+            let meta = serde_json::json!({
+                "subscriber_id": scru128::new().to_string(),
+                "options": options.to_query_string(),
+            });
+            let _ = self.append(
+                Frame::builder("xs.connect", ZERO_CONTEXT)
+                    .meta(meta.clone())
+                    .ttl(TTL::Ephemeral)
+                    .build(),
+            );
+            DisconnectOnDrop {
+                store: self.clone(),
+                meta,
+            }
+        });
+
         // Only take broadcast subscription if following. We initate the subscription here to
         // ensure we don't miss any messages between historical processing and starting the
         // broadcast subscription.
@@ -253,44 +1155,84 @@ impl Store {
             None
         };
 
-        // Only create done channel if we're doing historical processing
-        let done_rx = if !options.tail {
+        // Resolve `last-id=head` only once subscribed: anything appended from here on is either
+        // picked up by the historical scan or the subscription. A cursor on a seq is left in
+        // place, as that's applied as frames are read.
+        let options = ReadOptions {
+            last_id: self.resolve_last_id(&options).map(LastId::Id),
+            cursor: options
+                .cursor
+                .filter(|cursor| matches!(cursor, Cursor::Seq(_))),
+            ..options
+        };
+
+        // Delivery outlives this call, so it gets a span of its own, with an event per frame
+        let delivery = tracing::trace_span!(
+            parent: None,
+            "deliver",
+            options = %options.to_query_string()
+        );
+
+        // Only create a done channel if we're doing historical processing
+        let done_rx = if !options.tail || options.last_n.is_some() {
             let (done_tx, done_rx) = tokio::sync::oneshot::channel();
             let tx_clone = tx.clone();
             let store = self.clone();
             let options = options.clone();
             let should_follow_clone = should_follow;
-            let gc_tx = self.gc_tx.clone();
+            let delivery = delivery.clone();
 
             // Spawn OS thread to handle historical events
             std::thread::spawn(move || {
-                let mut last_id = None;
+                let _delivery = delivery.enter();
+                let mut last_id = store.resolve_last_id(&options);
+                let mut last_seq = None;
+                let mut last_hashes = LastHashes::default();
                 let mut count = 0;
 
-                for frame in store.iter_frames(options.context_id, options.last_id.as_ref()) {
-                    if let Some(TTL::Time(ttl)) = frame.ttl.as_ref() {
-                        if is_expired(&frame.id, ttl) {
-                            let _ = gc_tx.send(GCTask::Remove(frame.id));
-                            continue;
+                let interval = options
+                    .max_rate
+                    .filter(|rate| *rate > 0.0)
+                    .map(|rate| Duration::from_secs_f64(1.0 / rate));
+                let start = std::time::Instant::now();
+
+                for frame in store.read_sync(options.clone()) {
+                    // Pace against the start of the read rather than the previous frame, so
+                    // time spent reading and sending doesn't accumulate
+                    if let Some(interval) = interval {
+                        let due = start + interval * count as u32;
+                        std::thread::sleep(
+                            due.saturating_duration_since(std::time::Instant::now()),
+                        );
+                    }
+                    if let Some(gap) = options.gap(last_seq, &frame) {
+                        if tx_clone.blocking_send(gap).is_err() {
+                            return;
                         }
                     }
-
                     last_id = Some(frame.id);
-
-                    if let Some(limit) = options.limit {
-                        if count >= limit {
-                            return; // Exit early if limit reached
-                        }
+                    last_seq = Some(frame.seq);
+                    // Already deduped by `read_sync`: this picks up where it leaves off
+                    if options.dedupe_by_hash {
+                        last_hashes.record(&frame);
                     }
-
+                    store.counters.frame_delivered();
+                    tracing::trace!(id = %frame.id, topic = %frame.topic, "historical frame");
                     if tx_clone.blocking_send(frame).is_err() {
                         return;
                     }
                     count += 1;
                 }
 
-                // Send threshold message if following and no limit
-                if should_follow_clone && options.limit.is_none() {
+                let limit_reached = options.limit.is_some_and(|limit| count >= limit);
+
+                // Mark the switch to live frames: with the callback, if there is one, otherwise
+                // with a threshold message if following and no limit
+                if let Some(on_caught_up) = on_caught_up {
+                    if !limit_reached {
+                        on_caught_up();
+                    }
+                } else if should_follow_clone && options.limit.is_none() {
                     let threshold =
                         Frame::builder("xs.threshold", options.context_id.unwrap_or(ZERO_CONTEXT))
                             .id(scru128::new())
@@ -301,33 +1243,106 @@ impl Store {
                     }
                 }
 
-                // Signal completion with the last seen ID and count
-                let _ = done_tx.send((last_id, count));
+                // Send end-of-history marker for non-follow reads, if requested
+                if !should_follow_clone && options.emit_eoh {
+                    let eoh = Frame::builder("xs.eoh", options.context_id.unwrap_or(ZERO_CONTEXT))
+                        .id(scru128::new())
+                        .ttl(TTL::Ephemeral)
+                        .build();
+                    if tx_clone.blocking_send(eoh).is_err() {
+                        return;
+                    }
+                }
+
+                // A follow that has already hit its limit has nothing left to deliver
+                if limit_reached {
+                    return;
+                }
+
+                // Signal completion with the last seen ID, seq, hashes and count. The threshold
+                // is already queued, so heartbeats can start
+                let _ = done_tx.send((last_id, last_seq, last_hashes, count));
             });
 
             Some(done_rx)
         } else {
+            // Nothing to catch up on
+            if let Some(on_caught_up) = on_caught_up {
+                on_caught_up();
+            }
             None
         };
 
-        // Handle broadcast subscription and heartbeat
+        // Handle broadcast subscription and heartbeat. They share a task, so a heartbeat that
+        // can't be delivered drops the subscription, and a subscription that ends, say on
+        // reaching its limit, stops the heartbeat and so closes the stream.
         if let Some(broadcast_rx) = broadcast_rx {
-            {
-                let tx = tx.clone();
-                let limit = options.limit;
+            let limit = options.limit;
+            let options = options.clone();
+            let counters = self.counters.clone();
+
+            tokio::spawn(
+                async move {
+                    let _disconnect = disconnect;
 
-                tokio::spawn(async move {
                     // If we have a done_rx, wait for historical processing
-                    let (last_id, mut count) = match done_rx {
-                        Some(done_rx) => match done_rx.await {
-                            Ok((id, count)) => (id, count),
-                            Err(_) => return, // Historical processing failed/cancelled
+                    let (last_id, mut last_seq, mut last_hashes, mut count) = match done_rx {
+                        Some(done_rx) => tokio::select! {
+                            _ = tx.closed() => return,
+                            done = done_rx => match done {
+                                Ok(done) => done,
+                                Err(_) => return, // Historical processing failed/cancelled
+                            },
                         },
-                        None => (None, 0),
+                        None => (None, None, LastHashes::default(), 0),
+                    };
+
+                    // Pulses start once history has been delivered, so they don't interleave
+                    // with it
+                    let mut heartbeat = match options.follow {
+                        FollowOption::WithHeartbeat(duration) => {
+                            let mut heartbeat = tokio::time::interval_at(
+                                tokio::time::Instant::now() + duration,
+                                duration,
+                            );
+                            // A reader that's slow to take pulses gets them spaced out, not in a burst
+                            heartbeat
+                                .set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                            Some(heartbeat)
+                        }
+                        _ => None,
                     };
 
                     let mut broadcast_rx = broadcast_rx;
-                    while let Ok(frame) = broadcast_rx.recv().await {
+                    loop {
+                        let frame = tokio::select! {
+                            // Drop the subscription as soon as the reader goes away, rather than
+                            // on the next failed send, which may be a long way off
+                            _ = tx.closed() => break,
+                            _ = async {
+                                match heartbeat.as_mut() {
+                                    Some(heartbeat) => heartbeat.tick().await,
+                                    None => std::future::pending().await,
+                                }
+                            } => {
+                                let pulse = Frame::builder(
+                                    "xs.pulse",
+                                    options.context_id.unwrap_or(ZERO_CONTEXT),
+                                )
+                                .id(scru128::new())
+                                .ttl(TTL::Ephemeral)
+                                .build();
+                                if tx.send(pulse).await.is_err() {
+                                    break;
+                                }
+                                continue;
+                            }
+                            frame = broadcast_rx.recv() => match frame {
+                                Ok(frame) => frame,
+                                Err(_) => break,
+                            },
+                        };
+
                         // Skip frames that do not match the context_id
                         if let Some(context_id) = options.context_id {
                             if frame.context_id != context_id {
@@ -335,6 +1350,10 @@ impl Store {
                             }
                         }
 
+                        if !options.matches(&frame) {
+                            continue;
+                        }
+
                         // Skip if we've already seen this frame during historical scan
                         if let Some(last_scanned_id) = last_id {
                             if frame.id <= last_scanned_id {
@@ -342,8 +1361,21 @@ impl Store {
                             }
                         }
 
-                        if tx.send(frame).await.is_err() {
-                            break;
+                        if options.dedupe_by_hash && !last_hashes.admit(&frame) {
+                            continue;
+                        }
+
+                        if let Some(gap) = options.gap(last_seq, &frame) {
+                            if tx.send(gap).await.is_err() {
+                                break;
+                            }
+                        }
+                        last_seq = Some(frame.seq);
+
+                        counters.frame_delivered();
+                        tracing::trace!(id = %frame.id, topic = %frame.topic, "live frame");
+                        if tx.send(frame).await.is_err() {
+                            break;
                         }
 
                         if let Some(limit) = limit {
@@ -353,150 +1385,1050 @@ impl Store {
                             }
                         }
                     }
-                });
+                }
+                .instrument(delivery),
+            );
+        }
+
+        rx
+    }
+
+    /// Like [`Store::read`], but as a [`Stream`]. Dropping the stream unsubscribes straight away.
+    pub async fn subscribe(&self, options: ReadOptions) -> impl Stream<Item = Frame> + Unpin {
+        ReceiverStream::new(self.read(options).await)
+    }
+
+    /// Follows only frames appended after this call: no history, and no `xs.threshold`. For
+    /// heartbeats, [`Store::subscribe`] with `tail` and [`FollowOption::WithHeartbeat`].
+    pub async fn tail_stream(&self) -> impl Stream<Item = Frame> + Unpin {
+        self.subscribe(
+            ReadOptions::builder()
+                .follow(FollowOption::On)
+                .tail(true)
+                .last_id(LastId::Head)
+                .build(),
+        )
+        .await
+    }
+
+    /// Catches up on frames after `last_id`, then follows. An `xs.threshold` frame always marks
+    /// the switch from history to live frames. With a `heartbeat`, `xs.pulse` frames are sent at
+    /// that interval.
+    pub async fn since_stream(
+        &self,
+        last_id: Scru128Id,
+        heartbeat: Option<Duration>,
+    ) -> impl Stream<Item = Frame> + Unpin {
+        let follow = heartbeat
+            .map(FollowOption::WithHeartbeat)
+            .unwrap_or(FollowOption::On);
+        self.subscribe(
+            ReadOptions::builder()
+                .follow(follow)
+                .last_id(last_id)
+                .build(),
+        )
+        .await
+    }
+
+    /// The current `head` of `topic`, along with a stream of frames appended to it from then on.
+    /// The stream is subscribed before `head` is read, so no update falls between the two.
+    pub async fn watch_topic(
+        &self,
+        topic: &str,
+        context_id: Scru128Id,
+    ) -> Result<(Option<Frame>, impl Stream<Item = Frame> + Unpin), StoreError> {
+        let stream = self
+            .subscribe(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .tail(true)
+                    .topic(topic.to_string())
+                    .context_id(context_id)
+                    .build(),
+            )
+            .await;
+        let head = self.head(topic, context_id)?;
+        let after = head.as_ref().map(|frame| frame.id);
+        let stream = stream.filter(move |frame| after.is_none_or(|id| frame.id > id));
+        Ok((head, stream))
+    }
+
+    /// The latest frame of each topic, per context, oldest first: what
+    /// [`CompactStrategy::LatestPerTopic`] would keep, so a topic whose latest frame was removed
+    /// is left out. Along with it, a stream of every frame
+    /// appended from then on, ephemeral ones included. Appends wait while the snapshot is taken,
+    /// so each frame is either in the snapshot or on the stream, and never both. With a
+    /// `context_id`, only that context's frames are included. A stream that falls too far behind
+    /// ends.
+    pub async fn subscribe_snapshot(
+        &self,
+        context_id: Option<Scru128Id>,
+    ) -> Result<(Vec<Frame>, impl Stream<Item = Frame> + Unpin), StoreError> {
+        let (mut broadcast_rx, mut ids) = {
+            let _gate = self.append_gate.write().unwrap();
+            let broadcast_rx = self.broadcast_tx.subscribe();
+
+            // The topic index is ordered by context and topic, then id: the last key of each
+            // run of the same context and topic is its latest frame
+            let keys: Box<dyn Iterator<Item = _>> = match context_id {
+                Some(context_id) => Box::new(
+                    self.idx_topic
+                        .prefix(context_id.as_bytes())
+                        .map(|kv| kv.map(|(key, _)| key)),
+                ),
+                None => Box::new(self.idx_topic.keys()),
+            };
+            let mut latest: Vec<(Vec<u8>, Scru128Id)> = Vec::new();
+            for key in keys {
+                let key = key?;
+                let prefix = key[..key.len() - 16].to_vec();
+                if latest
+                    .last()
+                    .is_some_and(|(previous, _)| *previous == prefix)
+                {
+                    latest.pop();
+                }
+                latest.push((prefix, idx_topic_frame_id_from_key(&key)));
             }
+            // Topics whose latest frame was removed are left out, as compaction would
+            let removed = self.removed_heads(context_id)?;
+            let ids = latest
+                .into_iter()
+                .filter(|(prefix, id)| removed.get(prefix).is_none_or(|r| r <= id))
+                .map(|(_, id)| id)
+                .collect::<Vec<_>>();
+            (broadcast_rx, ids)
+        };
 
-            // Handle heartbeat if requested
-            if let FollowOption::WithHeartbeat(duration) = options.follow {
-                let heartbeat_tx = tx;
-                tokio::spawn(async move {
-                    loop {
-                        tokio::time::sleep(duration).await;
-                        let frame =
-                            Frame::builder("xs.pulse", options.context_id.unwrap_or(ZERO_CONTEXT))
-                                .id(scru128::new())
-                                .ttl(TTL::Ephemeral)
-                                .build();
-                        if heartbeat_tx.send(frame).await.is_err() {
-                            break;
+        ids.sort();
+        let snapshot = self.get_many(&ids)?.into_iter().flatten().collect();
+
+        let (tx, rx) = mpsc::channel(self.options.subscriber_buffer);
+        let counters = self.counters.clone();
+        tokio::spawn(async move {
+            loop {
+                let frame = tokio::select! {
+                    _ = tx.closed() => break,
+                    frame = broadcast_rx.recv() => match frame {
+                        Ok(frame) => frame,
+                        Err(_) => break,
+                    },
+                };
+                if context_id.is_some_and(|context_id| frame.context_id != context_id) {
+                    continue;
+                }
+                counters.frame_delivered();
+                if tx.send(frame).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok((snapshot, ReceiverStream::new(rx)))
+    }
+
+    /// Reads historical frames without requiring an async runtime. `follow` is ignored: use
+    /// [`Store::read`] to follow the stream.
+    #[tracing::instrument(skip(self))]
+    pub fn read_sync(&self, options: ReadOptions) -> impl Iterator<Item = Frame> + '_ {
+        let last_id = self.resolve_last_id(&options);
+        let limit = options.limit.unwrap_or(usize::MAX);
+        let dedupe_by_hash = options.dedupe_by_hash;
+        let frames: Box<dyn Iterator<Item = Frame>> = match options.last_n {
+            Some(n) => Box::new(
+                self.last_n_frames(n, &options, last_id.as_ref())
+                    .into_iter(),
+            ),
+            None if options.tail => Box::new(std::iter::empty()),
+            None => Box::new(
+                self.iter_frames(options.context_id, last_id.as_ref())
+                    .filter(move |frame| options.matches(frame))
+                    .filter(move |frame| {
+                        if let Some(TTL::Time(ttl)) = frame.ttl.as_ref() {
+                            if is_expired(&frame.id, ttl) {
+                                let _ = self.gc_tx.send(GCTask::Remove(frame.id));
+                                return false;
+                            }
                         }
-                    }
-                });
+                        true
+                    }),
+            ),
+        };
+        let mut last_hashes = LastHashes::default();
+        frames
+            .filter(move |frame| !dedupe_by_hash || last_hashes.admit(frame))
+            .take(limit)
+    }
+
+    pub fn get(&self, id: &Scru128Id) -> Result<Option<Frame>, StoreError> {
+        match self.frame_partition.get(id.to_bytes())? {
+            Some(value) => Ok(Some(self.options.codec.decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Patches a frame's meta by appending an `xs.meta` frame, in the frame's context, with meta
+    /// `{id, patch}`. The frame itself is left as it is: [`Store::get_patched`] reads it back
+    /// with its patches applied. `patch` has to be an object.
+    pub fn patch_meta(
+        &self,
+        id: &Scru128Id,
+        patch: serde_json::Value,
+    ) -> Result<Frame, StoreError> {
+        if !patch.is_object() {
+            return Err(StoreError::InvalidFrame(
+                "xs.meta: patch must be an object".into(),
+            ));
+        }
+        let frame = self
+            .get(id)?
+            .ok_or_else(|| StoreError::InvalidFrame(format!("xs.meta: no frame {}", id)))?;
+        self.append(
+            Frame::builder("xs.meta", frame.context_id)
+                .meta(serde_json::json!({"id": id.to_string(), "patch": patch}))
+                .build(),
+        )
+    }
+
+    /// The frame with `id`, with every patch appended for it by [`Store::patch_meta`] merged
+    /// into its meta, oldest first. Merges are shallow: each key of a patch replaces the key in
+    /// the meta, and a key patched to `null` is removed.
+    pub fn get_patched(&self, id: &Scru128Id) -> Result<Option<Frame>, StoreError> {
+        let Some(mut frame) = self.get(id)? else {
+            return Ok(None);
+        };
+        let id = id.to_string();
+        let patches = self.read_sync(
+            ReadOptions::builder()
+                .context_id(frame.context_id)
+                .topic("xs.meta")
+                .last_id(frame.id)
+                .build(),
+        );
+        for patch in patches {
+            let Some(meta) = &patch.meta else { continue };
+            if meta.get("id").and_then(|v| v.as_str()) != Some(&id) {
+                continue;
+            }
+            if let Some(serde_json::Value::Object(patch)) = meta.get("patch") {
+                merge_meta(&mut frame.meta, patch);
             }
         }
+        Ok(Some(frame))
+    }
 
-        rx
+    /// Whether a frame with this id is held, without decoding it.
+    pub fn exists(&self, id: &Scru128Id) -> Result<bool, StoreError> {
+        Ok(self.frame_partition.contains_key(id.to_bytes())?)
+    }
+
+    /// Looks up several frames at once, returning them in the order asked for. Lookups are made
+    /// in key order, so neighbouring frames share the blocks they're read from.
+    pub fn get_many(&self, ids: &[Scru128Id]) -> Result<Vec<Option<Frame>>, StoreError> {
+        let mut order: Vec<usize> = (0..ids.len()).collect();
+        order.sort_by_key(|&i| ids[i]);
+
+        let mut frames = vec![None; ids.len()];
+        for i in order {
+            frames[i] = self.get(&ids[i])?;
+        }
+        Ok(frames)
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn read_sync(
+    pub fn head(&self, topic: &str, context_id: Scru128Id) -> Result<Option<Frame>, StoreError> {
+        for kv in self
+            .idx_topic
+            .prefix(idx_topic_key_prefix(context_id, topic))
+            .rev()
+        {
+            let (key, _) = kv?;
+            if let Some(frame) = self.get(&idx_topic_frame_id_from_key(&key))? {
+                return Ok(Some(frame));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The latest frame for each distinct `key` in the meta of frames on `topic`, oldest first.
+    /// Frames without a string `key` are left out. This is how `.set` frames read as a key-value
+    /// store: each key's earlier frames are superseded.
+    pub fn latest_by_key(
         &self,
-        last_id: Option<&Scru128Id>,
-        limit: Option<usize>,
-        context_id: Option<Scru128Id>,
-    ) -> impl Iterator<Item = Frame> + '_ {
-        self.iter_frames(context_id, last_id)
-            .filter(move |frame| {
-                if let Some(TTL::Time(ttl)) = frame.ttl.as_ref() {
-                    if is_expired(&frame.id, ttl) {
-                        let _ = self.gc_tx.send(GCTask::Remove(frame.id));
-                        return false;
-                    }
+        topic: &str,
+        context_id: Scru128Id,
+    ) -> Result<Vec<Frame>, StoreError> {
+        let mut seen = HashSet::new();
+        let mut latest = Vec::new();
+        for kv in self
+            .idx_topic
+            .prefix(idx_topic_key_prefix(context_id, topic))
+            .rev()
+        {
+            let (key, _) = kv?;
+            let Some(frame) = self.get(&idx_topic_frame_id_from_key(&key))? else {
+                continue;
+            };
+            let Some(frame_key) = frame_key(&frame) else {
+                continue;
+            };
+            if seen.insert(frame_key.to_string()) {
+                latest.push(frame);
+            }
+        }
+        latest.reverse();
+        Ok(latest)
+    }
+
+    /// Removes the frames on `topic` that `latest_by_key` leaves out for `key`: all but the
+    /// latest with that key.
+    pub fn prune_key(
+        &self,
+        topic: &str,
+        context_id: Scru128Id,
+        key: &str,
+    ) -> Result<(), StoreError> {
+        let mut superseded = Vec::new();
+        let mut found_latest = false;
+        for kv in self
+            .idx_topic
+            .prefix(idx_topic_key_prefix(context_id, topic))
+            .rev()
+        {
+            let (idx_key, _) = kv?;
+            let id = idx_topic_frame_id_from_key(&idx_key);
+            let Some(frame) = self.get(&id)? else {
+                continue;
+            };
+            if frame_key(&frame) == Some(key) {
+                if found_latest {
+                    superseded.push(id);
                 }
-                true
-            })
-            .take(limit.unwrap_or(usize::MAX))
+                found_latest = true;
+            }
+        }
+        for id in superseded {
+            self.delete(&id)?;
+        }
+        Ok(())
     }
 
-    pub fn get(&self, id: &Scru128Id) -> Option<Frame> {
-        self.frame_partition
-            .get(id.to_bytes())
+    /// The oldest frame in a context, or on a topic within it: the counterpart to [`Store::head`].
+    #[tracing::instrument(skip(self))]
+    pub fn first(
+        &self,
+        topic: Option<&str>,
+        context_id: Scru128Id,
+    ) -> Result<Option<Frame>, StoreError> {
+        // both indexes' keys end with the frame id
+        let ids: Box<dyn Iterator<Item = _>> = match topic {
+            Some(topic) => Box::new(
+                self.idx_topic
+                    .prefix(idx_topic_key_prefix(context_id, topic))
+                    .map(|kv| kv.map(|(key, _)| idx_topic_frame_id_from_key(&key))),
+            ),
+            None => Box::new(
+                self.idx_context
+                    .prefix(context_id.as_bytes())
+                    .map(|kv| kv.map(|(key, _)| idx_topic_frame_id_from_key(&key))),
+            ),
+        };
+        for id in ids {
+            if let Some(frame) = self.get(&id?)? {
+                return Ok(Some(frame));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The frame with the given id. An alias for [`Store::get`].
+    pub fn at(&self, id: &Scru128Id) -> Result<Option<Frame>, StoreError> {
+        self.get(id)
+    }
+
+    pub fn info(&self) -> Result<StoreInfo, StoreError> {
+        let first_id = self
+            .frame_partition
+            .first_key_value()?
+            .map(|(key, _)| id_from_key(&key));
+        let last_id = self
+            .frame_partition
+            .last_key_value()?
+            .map(|(key, _)| id_from_key(&key));
+        Ok(StoreInfo {
+            path: self.path.clone(),
+            first_id,
+            last_id,
+            frame_count: self.frame_partition.len()?,
+            started_at: self.started_at,
+        })
+    }
+
+    /// Every topic with at least one frame stored, in any context, sorted.
+    pub fn topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = self.topics.read().unwrap().keys().cloned().collect();
+        topics.sort();
+        topics
+    }
+
+    fn topic_added(&self, topic: &str) {
+        *self
+            .topics
+            .write()
             .unwrap()
-            .map(|value| deserialize_frame((id.as_bytes(), value)))
+            .entry(topic.to_string())
+            .or_insert(0) += 1;
     }
 
+    fn topic_removed(&self, topic: &str) {
+        let mut topics = self.topics.write().unwrap();
+        if let Some(count) = topics.get_mut(topic) {
+            *count -= 1;
+            if *count == 0 {
+                topics.remove(topic);
+            }
+        }
+    }
+
+    /// Counts of appends, CAS writes, reads and deliveries since the store was opened.
+    pub fn metrics(&self) -> Metrics {
+        self.counters.snapshot()
+    }
+
+    /// How many followers are currently subscribed to appended frames. Followers are counted
+    /// from when they subscribe, before their history is delivered, until they're dropped.
+    pub fn subscriber_count(&self) -> usize {
+        self.broadcast_tx.receiver_count()
+    }
+
+    /// Lists the distinct topics in a context, in topic order, from a single scan of the topic
+    /// index.
     #[tracing::instrument(skip(self))]
-    pub fn head(&self, topic: &str, context_id: Scru128Id) -> Option<Frame> {
-        self.idx_topic
-            .prefix(idx_topic_key_prefix(context_id, topic))
-            .rev()
-            .find_map(|kv| self.get(&idx_topic_frame_id_from_key(&kv.unwrap().0)))
+    pub fn topic_summaries(&self, context_id: Scru128Id) -> Result<Vec<TopicSummary>, StoreError> {
+        let mut summaries: Vec<TopicSummary> = Vec::new();
+        for kv in self.idx_topic.prefix(context_id.as_bytes()) {
+            let (key, _) = kv?;
+            // <context_id><topic><0xFF><frame_id>
+            let topic = String::from_utf8_lossy(&key[16..key.len() - 17]);
+            let last_id = idx_topic_frame_id_from_key(&key);
+            match summaries.last_mut() {
+                Some(summary) if summary.topic == topic => {
+                    summary.count += 1;
+                    summary.last_id = last_id;
+                }
+                _ => summaries.push(TopicSummary {
+                    topic: topic.into_owned(),
+                    count: 1,
+                    last_id,
+                }),
+            }
+        }
+        Ok(summaries)
     }
 
+    /// Deletes the frame with `id`. With [`StoreOptions::tombstones`], this leaves an `xs.remove`
+    /// tombstone in its context, with the frame's `id` and `topic` in its meta, so the deletion
+    /// is part of the stream's history. The deletion and the tombstone are written together, so
+    /// there's never one without the other. Reads skip tombstones unless they ask for them with
+    /// `include_tombstones`. No tombstone is left for a frame that's already gone, whose context
+    /// has been removed, or that's a tombstone itself, so tombstones can be cleaned up.
     #[tracing::instrument(skip(self), fields(id = %id.to_string()))]
-    pub fn remove(&self, id: &Scru128Id) -> Result<(), fjall::Error> {
-        let Some(frame) = self.get(id) else {
-            // Already deleted
+    pub fn remove(&self, id: &Scru128Id) -> Result<(), StoreError> {
+        if !self.options.tombstones {
+            self.delete(id)?;
             return Ok(());
+        }
+
+        // As in `delete`
+        let _gate = self.append_gate.write().unwrap();
+        let Some(frame) = self.get(id)? else {
+            return Ok(());
+        };
+        let mut batch = self.keyspace.batch();
+        self.batch_delete_frame(&mut batch, &frame);
+        let tombstone = (frame.topic != "xs.remove"
+            && self.contexts.read().unwrap().contains(&frame.context_id))
+        .then(|| {
+            let mut tombstone = Frame::builder("xs.remove", frame.context_id)
+                .meta(serde_json::json!({
+                    "id": frame.id.to_string(),
+                    "topic": frame.topic,
+                }))
+                .build();
+            self.assign_id_and_seq(&mut self.sequence.lock().unwrap(), &mut tombstone);
+            self.batch_insert_frame(&mut batch, &tombstone)
+                .map(|_| tombstone)
+        })
+        .transpose()?;
+        batch.commit()?;
+        self.frame_deleted(&frame);
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+
+        if let Some(tombstone) = tombstone {
+            self.topic_added(&tombstone.topic);
+            self.counters.appended(1);
+            let _ = self.broadcast_tx.send(tombstone);
+        }
+        Ok(())
+    }
+
+    // Deletes a frame outright, as expiry and trimming do, returning it if it was there. This
+    // holds the append gate exclusively, so it can't interleave with a rewrite of the frame,
+    // such as filling in its hash, which would bring it back without its index entries
+    fn delete(&self, id: &Scru128Id) -> Result<Option<Frame>, StoreError> {
+        let _gate = self.append_gate.write().unwrap();
+        self.delete_locked(id)
+    }
+
+    // `delete`, for callers already holding the append gate exclusively
+    fn delete_locked(&self, id: &Scru128Id) -> Result<Option<Frame>, StoreError> {
+        let Some(frame) = self.get(id)? else {
+            // Already deleted
+            return Ok(None);
         };
 
         let mut batch = self.keyspace.batch();
-        batch.remove(&self.frame_partition, id.as_bytes());
-        batch.remove(&self.idx_topic, idx_topic_key_from_frame(&frame));
-        batch.remove(&self.idx_context, idx_context_key_from_frame(&frame));
+        self.batch_delete_frame(&mut batch, &frame);
+        batch.commit()?;
+        self.frame_deleted(&frame);
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(Some(frame))
+    }
 
+    fn batch_delete_frame(&self, batch: &mut fjall::Batch, frame: &Frame) {
+        batch.remove(&self.frame_partition, frame.id.as_bytes());
+        batch.remove(&self.idx_topic, idx_topic_key_from_frame(frame));
+        batch.remove(&self.idx_context, idx_context_key_from_frame(frame));
+    }
+
+    // Bookkeeping once a frame's deletion is committed
+    fn frame_deleted(&self, frame: &Frame) {
         // If this is a context frame, remove it from the contexts set
         if frame.topic == "xs.context" {
             self.contexts.write().unwrap().remove(&frame.id);
         }
+        self.topic_removed(&frame.topic);
+    }
 
-        batch.commit()?;
-        self.keyspace.persist(fjall::PersistMode::SyncAll)
+    pub async fn cas_reader(&self, hash: ssri::Integrity) -> Result<CasReader, StoreError> {
+        self.cas_touched(&hash, None)?;
+        Ok(cas::cas_open(&self.path.join("cacache"), hash).await?)
+    }
+
+    pub fn cas_reader_sync(&self, hash: ssri::Integrity) -> Result<CasSyncReader, StoreError> {
+        self.cas_touched(&hash, None)?;
+        Ok(cas::cas_open_sync(&self.path.join("cacache"), hash)?)
     }
 
-    pub async fn cas_reader(&self, hash: ssri::Integrity) -> cacache::Result<cacache::Reader> {
-        cacache::Reader::open_hash(&self.path.join("cacache"), hash).await
+    pub async fn cas_writer(&self) -> Result<CasWriter, StoreError> {
+        Ok(CasWriter::open(
+            &self.path.join("cacache"),
+            self.options.compress_cas,
+            self.options.cas_algorithm,
+            self.options.max_content_bytes,
+            self.on_cas_commit(),
+        )
+        .await?)
     }
 
-    pub fn cas_reader_sync(&self, hash: ssri::Integrity) -> cacache::Result<cacache::SyncReader> {
-        cacache::SyncReader::open_hash(self.path.join("cacache"), hash)
+    pub fn cas_writer_sync(&self) -> Result<CasSyncWriter, StoreError> {
+        Ok(CasSyncWriter::open(
+            &self.path.join("cacache"),
+            self.options.compress_cas,
+            self.options.cas_algorithm,
+            self.options.max_content_bytes,
+            self.on_cas_commit(),
+        )?)
     }
 
-    pub async fn cas_writer(&self) -> cacache::Result<cacache::Writer> {
-        cacache::WriteOpts::new()
-            .open_hash(&self.path.join("cacache"))
-            .await
+    fn check_content_size(&self, content: &[u8]) -> Result<(), StoreError> {
+        match self.options.max_content_bytes {
+            Some(limit) if content.len() as u64 > limit => {
+                Err(StoreError::ContentTooLarge(ContentTooLarge { limit }))
+            }
+            _ => Ok(()),
+        }
     }
 
-    pub fn cas_writer_sync(&self) -> cacache::Result<cacache::SyncWriter> {
-        cacache::WriteOpts::new().open_hash_sync(self.path.join("cacache"))
+    fn on_cas_commit(&self) -> cas::OnCommit {
+        let store = self.clone();
+        Box::new(move |hash, size| {
+            store.cas_inserted(hash, size as usize).map_err(|e| {
+                let e = std::io::Error::other(e.to_string());
+                let context = e.to_string();
+                cacache::Error::IoError(e, context)
+            })
+        })
     }
 
-    pub async fn cas_insert(&self, content: impl AsRef<[u8]>) -> cacache::Result<ssri::Integrity> {
-        cacache::write_hash(&self.path.join("cacache"), content).await
+    pub async fn cas_insert(
+        &self,
+        content: impl AsRef<[u8]>,
+    ) -> Result<ssri::Integrity, StoreError> {
+        let content = content.as_ref();
+        self.check_content_size(content)?;
+        let hash = cas::cas_insert(
+            &self.path.join("cacache"),
+            self.options.compress_cas,
+            self.options.cas_algorithm,
+            content,
+        )
+        .await?;
+        self.cas_inserted(&hash, content.len())?;
+        Ok(hash)
     }
 
-    pub fn cas_insert_sync(&self, content: impl AsRef<[u8]>) -> cacache::Result<ssri::Integrity> {
-        cacache::write_hash_sync(self.path.join("cacache"), content)
+    pub fn cas_insert_sync(
+        &self,
+        content: impl AsRef<[u8]>,
+    ) -> Result<ssri::Integrity, StoreError> {
+        let content = content.as_ref();
+        self.check_content_size(content)?;
+        let hash = cas::cas_insert_sync(
+            &self.path.join("cacache"),
+            self.options.compress_cas,
+            self.options.cas_algorithm,
+            content,
+        )?;
+        self.cas_inserted(&hash, content.len())?;
+        Ok(hash)
     }
 
-    pub async fn cas_read(&self, hash: &ssri::Integrity) -> cacache::Result<Vec<u8>> {
-        cacache::read_hash(&self.path.join("cacache"), hash).await
+    pub async fn cas_read(&self, hash: &ssri::Integrity) -> Result<Vec<u8>, StoreError> {
+        let content = cas::cas_read(&self.path.join("cacache"), hash).await?;
+        self.cas_touched(hash, Some(content.len()))?;
+        Ok(content)
     }
 
-    pub fn cas_read_sync(&self, hash: &ssri::Integrity) -> cacache::Result<Vec<u8>> {
-        cacache::read_hash_sync(self.path.join("cacache"), hash)
+    pub fn cas_read_sync(&self, hash: &ssri::Integrity) -> Result<Vec<u8>, StoreError> {
+        let content = cas::cas_read_sync(&self.path.join("cacache"), hash)?;
+        self.cas_touched(hash, Some(content.len()))?;
+        Ok(content)
+    }
+
+    fn cas_inserted(&self, hash: &ssri::Integrity, size: usize) -> Result<(), StoreError> {
+        self.counters.cas_written(size as u64);
+        if let Some(lru) = &self.cas_lru {
+            lru.inserted(hash, size as u64)?;
+        }
+        Ok(())
+    }
+
+    fn cas_touched(&self, hash: &ssri::Integrity, size: Option<usize>) -> Result<(), StoreError> {
+        if let Some(lru) = &self.cas_lru {
+            lru.touched(hash, size.map(|size| size as u64))?;
+        }
+        Ok(())
+    }
+
+    /// Evicts the least recently used content no frame refers to until the CAS is back under
+    /// `cas_max_bytes`.
+    fn evict_cas_sync(&self) -> Result<(), StoreError> {
+        let (Some(lru), Some(max_bytes)) = (&self.cas_lru, self.options.cas_max_bytes) else {
+            return Ok(());
+        };
+        let entries = lru.entries()?;
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        if total <= max_bytes {
+            return Ok(());
+        }
+
+        let referenced: HashSet<ssri::Integrity> = self
+            .read_sync(ReadOptions::default())
+            .filter_map(|frame| frame.hash)
+            .collect();
+        let cache = self.path.join("cacache");
+        for entry in entries {
+            if total <= max_bytes {
+                break;
+            }
+            if referenced.contains(&entry.hash) {
+                continue;
+            }
+            cas::cas_remove_sync(&cache, &entry.hash)?;
+            lru.remove(&entry.hash)?;
+            total -= entry.size;
+        }
+        Ok(())
+    }
+
+    /// Whether content for `hash` is in the CAS, without reading it.
+    pub async fn cas_exists(&self, hash: &ssri::Integrity) -> Result<bool, StoreError> {
+        Ok(cas::cas_exists(&self.path.join("cacache"), hash).await?)
+    }
+
+    pub fn cas_exists_sync(&self, hash: &ssri::Integrity) -> Result<bool, StoreError> {
+        Ok(cas::cas_exists_sync(&self.path.join("cacache"), hash)?)
+    }
+
+    /// The size of the content for `hash`, or `None` if it isn't in the CAS, without reading it.
+    pub async fn cas_size(&self, hash: &ssri::Integrity) -> Result<Option<u64>, StoreError> {
+        Ok(cas::cas_size(&self.path.join("cacache"), hash).await?)
+    }
+
+    /// Every blob in the CAS, whether or not a frame still refers to it, ordered by hash.
+    pub fn cas_list(&self) -> Result<Vec<CasEntry>, StoreError> {
+        let mut entries = cas::cas_list_sync(&self.path.join("cacache"))?;
+        entries.sort_by_key(|entry| entry.hash.to_string());
+        Ok(entries)
     }
 
     #[tracing::instrument(skip(self))]
-    pub fn insert_frame(&self, frame: &Frame) -> Result<(), fjall::Error> {
-        let encoded: Vec<u8> = serde_json::to_vec(&frame).unwrap();
+    pub fn insert_frame(&self, frame: &Frame) -> Result<(), StoreError> {
+        // Imports can overwrite a frame that's already there, which isn't a new one for its topic
+        let is_new = !self.exists(&frame.id)?;
         let mut batch = self.keyspace.batch();
+        self.batch_insert_frame(&mut batch, frame)?;
+        batch.commit()?;
+        if is_new {
+            self.topic_added(&frame.topic);
+        }
+        Ok(self.keyspace.persist(fjall::PersistMode::SyncAll)?)
+    }
+
+    /// Writes every frame in the store to `w` as newline-delimited JSON, oldest first, returning
+    /// how many were written. CAS content isn't included: frames keep their hashes only.
+    pub fn export<W: std::io::Write>(&self, mut w: W) -> Result<usize, StoreError> {
+        let mut count = 0;
+        for frame in self.read_sync(ReadOptions::default()) {
+            serde_json::to_writer(&mut w, &frame)?;
+            w.write_all(b"\n")?;
+            count += 1;
+        }
+        w.flush()?;
+        Ok(count)
+    }
+
+    /// Reads newline-delimited JSON frames, as written by [`Store::export`], and inserts them
+    /// with their ids and seqs intact, returning how many were inserted. A frame whose id is
+    /// already in the store is an error, unless `overwrite` is set, in which case it replaces the
+    /// existing frame. Imported frames aren't broadcast to followers.
+    pub fn import<R: std::io::BufRead>(&self, r: R, overwrite: bool) -> Result<usize, StoreError> {
+        let _gate = self.append_gate.write().unwrap();
+        let mut count = 0;
+        for line in r.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let frame: Frame = serde_json::from_str(&line)?;
+
+            if frame.topic != "xs.context"
+                && !self.contexts.read().unwrap().contains(&frame.context_id)
+            {
+                return Err(StoreError::InvalidFrame(format!(
+                    "Invalid context: {}",
+                    frame.context_id
+                )));
+            }
+            let schema = if frame.topic == "xs.schema" {
+                Some(Schemas::compile(&frame)?)
+            } else {
+                None
+            };
+
+            if self.exists(&frame.id)? {
+                if !overwrite {
+                    return Err(StoreError::InvalidFrame(format!(
+                        "id {} is taken",
+                        frame.id
+                    )));
+                }
+                self.delete_locked(&frame.id)?;
+            }
+            self.insert_frame(&frame)?;
+
+            if frame.topic == "xs.context" {
+                self.contexts.write().unwrap().insert(frame.id);
+            }
+            if let Some((name, validator)) = schema {
+                self.schemas.insert(name, validator);
+            }
+
+            let mut sequence = self.sequence.lock().unwrap();
+            sequence.last_id = sequence.last_id.max(frame.id);
+            sequence.next_seq = sequence.next_seq.max(frame.seq + 1);
+            count += 1;
+        }
+
+        // `insert_frame` records the seq after each frame, which needn't be the greatest
+        let next_seq = self.sequence.lock().unwrap().next_seq;
+        self.meta_partition
+            .insert(NEXT_SEQ_KEY, next_seq.to_be_bytes())?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+        Ok(count)
+    }
+
+    fn batch_insert_frame(
+        &self,
+        batch: &mut fjall::Batch,
+        frame: &Frame,
+    ) -> Result<(), StoreError> {
+        let encoded: Vec<u8> = self.options.codec.encode(frame)?;
         batch.insert(&self.frame_partition, frame.id.as_bytes(), encoded);
         batch.insert(&self.idx_topic, idx_topic_key_from_frame(frame), b"");
         batch.insert(&self.idx_context, idx_context_key_from_frame(frame), b"");
-        batch.commit()?;
-        self.keyspace.persist(fjall::PersistMode::SyncAll)
+        batch.insert(
+            &self.meta_partition,
+            NEXT_SEQ_KEY,
+            (frame.seq + 1).to_be_bytes(),
+        );
+        Ok(())
+    }
+
+    // Assigns the id and seq together, so that seqs increase in id order
+    fn assign_id_and_seq(&self, sequence: &mut Sequence, frame: &mut Frame) {
+        let id = scru128::new();
+        // Ids given to `append_with_id` can be ahead of the clock, and the clock can go back
+        frame.id = if id > sequence.last_id {
+            sequence.skew_warned = false;
+            id
+        } else {
+            if let Some(max_skew) = self.options.max_clock_skew {
+                let behind_ms = sequence.last_id.timestamp() - id.timestamp();
+                if !sequence.skew_warned && behind_ms > max_skew.as_millis() as u64 {
+                    tracing::warn!(
+                        last_id = %sequence.last_id,
+                        behind_ms,
+                        "clock is behind the last id: counting ids up from it"
+                    );
+                    sequence.skew_warned = true;
+                }
+            }
+            Scru128Id::from_u128(sequence.last_id.to_u128() + 1)
+        };
+        sequence.last_id = frame.id;
+        take_seq(sequence, frame);
+    }
+
+    fn assign_seq_to_id(
+        &self,
+        sequence: &mut Sequence,
+        frame: &mut Frame,
+        id: Scru128Id,
+        allow_out_of_order: bool,
+    ) -> Result<(), StoreError> {
+        if let Some(max_skew) = self.options.max_clock_skew {
+            let now_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64;
+            if id.timestamp() > now_ms.saturating_add(max_skew.as_millis() as u64) {
+                return Err(StoreError::InvalidFrame(format!(
+                    "id {} is dated {}ms ahead of the clock",
+                    id,
+                    id.timestamp() - now_ms
+                )));
+            }
+        }
+        if id <= sequence.last_id {
+            if !allow_out_of_order {
+                return Err(StoreError::InvalidFrame(format!(
+                    "id {} isn't after the last id {}",
+                    id, sequence.last_id
+                )));
+            }
+            if self.frame_partition.contains_key(id.to_bytes())? {
+                return Err(StoreError::InvalidFrame(format!("id {} is taken", id)));
+            }
+        }
+        frame.id = id;
+        sequence.last_id = sequence.last_id.max(id);
+        take_seq(sequence, frame);
+        Ok(())
+    }
+
+    /// Writes `content`, if any, to the CAS and appends `frame` referencing it, without needing
+    /// an async runtime. The frame is on disk, and has been broadcast to followers, by the time
+    /// this returns.
+    pub fn append_blocking(
+        &self,
+        mut frame: Frame,
+        content: Option<&[u8]>,
+    ) -> Result<Frame, StoreError> {
+        if let Some(content) = content {
+            frame.hash = Some(self.cas_insert_sync(content)?);
+        }
+        self.append(frame)
     }
 
-    pub fn append(&self, mut frame: Frame) -> Result<Frame, crate::error::Error> {
-        frame.id = scru128::new();
+    /// Appends `frame` straight away, without a hash, and writes `content` to the CAS in the
+    /// background, on `pool`. Once the content is committed, the stored frame gets its hash and
+    /// is broadcast again. This only blocks while every worker in `pool` is busy.
+    ///
+    /// This trades consistency for ingest throughput. Followers see the frame twice, with the
+    /// same id: first without a hash, then with it. A follower that picked the frame up from
+    /// history before the hash was filled in doesn't see the update. Until then, reads return
+    /// the frame without a hash. If the content can't be written, the frame is left without one.
+    pub fn append_async_content(
+        &self,
+        pool: &ThreadPool,
+        frame: Frame,
+        content: Vec<u8>,
+    ) -> Result<Frame, StoreError> {
+        let provisional = self.append(Frame {
+            hash: None,
+            ..frame
+        })?;
+
+        let store = self.clone();
+        let frame = provisional.clone();
+        pool.execute(move || {
+            let result = store
+                .cas_insert_sync(&content)
+                .and_then(|hash| store.fill_in_hash(frame.clone(), hash));
+            if let Err(e) = result {
+                tracing::error!("Failed to write content for frame {}: {}", frame.id, e);
+            }
+        });
+
+        Ok(provisional)
+    }
+
+    // Rewrites a frame appended by `append_async_content` with its hash, unless it's gone in the
+    // meantime, and broadcasts it again. This holds the append gate, as an append would, so it
+    // doesn't race with anything that rewrites or deletes frames, such as renaming a topic or
+    // removing the frame
+    fn fill_in_hash(&self, frame: Frame, hash: ssri::Integrity) -> Result<(), StoreError> {
+        let _gate = self.append_gate.read().unwrap();
+        let frame = match frame.ttl {
+            Some(TTL::Ephemeral) => Frame {
+                hash: Some(hash),
+                ..frame
+            },
+            _ => {
+                // Picks up anything else that changed about the frame since it was appended
+                let Some(stored) = self.get(&frame.id)? else {
+                    return Ok(());
+                };
+                let frame = Frame {
+                    hash: Some(hash),
+                    ..stored
+                };
+                self.frame_partition
+                    .insert(frame.id.as_bytes(), self.options.codec.encode(&frame)?)?;
+                self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+                frame
+            }
+        };
+        let _ = self.broadcast_tx.send(frame);
+        Ok(())
+    }
+
+    pub fn append(&self, frame: Frame) -> Result<Frame, StoreError> {
+        let _gate = self.append_gate.read().unwrap();
+        Ok(self.append_inner(frame, None)?.0)
+    }
+
+    /// Appends `frame` like [`Store::append`], and also returns how many followers it was
+    /// broadcast to, much as Redis' `PUBLISH` returns its receiver count. That's every current
+    /// subscriber, as counted by [`Store::subscriber_count`], including those whose topic or
+    /// context filter will then skip the frame. It's most telling for ephemeral frames, which
+    /// are lost if no one is listening.
+    pub fn publish(&self, frame: Frame) -> Result<(Frame, usize), StoreError> {
+        let _gate = self.append_gate.read().unwrap();
+        self.append_inner(frame, None)
+    }
+
+    /// Appends `frame` only if the latest frame on its topic, in its context, is
+    /// `expected_head`, or if the topic is empty when that's `None`. Otherwise fails with
+    /// [`StoreError::HeadMismatch`]. Checking and appending is atomic with respect to other
+    /// appends, which makes read-modify-write over a topic safe.
+    pub fn append_if(
+        &self,
+        frame: Frame,
+        expected_head: Option<Scru128Id>,
+    ) -> Result<Frame, StoreError> {
+        let _gate = self.append_gate.write().unwrap();
+        let head = self
+            .head(&frame.topic, frame.context_id)?
+            .map(|frame| frame.id);
+        if head != expected_head {
+            return Err(StoreError::HeadMismatch {
+                expected: expected_head,
+                actual: head,
+            });
+        }
+        Ok(self.append_inner(frame, None)?.0)
+    }
+
+    /// Appends `frame` with the given `id` rather than a fresh one, for building deterministic
+    /// streams in tests or preserving ids on import. The id has to be greater than every id
+    /// handed out so far, unless `allow_out_of_order` is set, in which case it only has to be
+    /// unused. Either way the frame gets the next seq, so out of order frames break the usual
+    /// agreement between id and seq order.
+    pub fn append_with_id(
+        &self,
+        frame: Frame,
+        id: Scru128Id,
+        allow_out_of_order: bool,
+    ) -> Result<Frame, StoreError> {
+        let _gate = self.append_gate.read().unwrap();
+        Ok(self.append_inner(frame, Some((id, allow_out_of_order)))?.0)
+    }
+
+    /// Appends `frame`, returning it along with how many followers it was broadcast to.
+    #[tracing::instrument(
+        name = "append",
+        level = "trace",
+        skip_all,
+        fields(topic = %frame.topic, context_id = %frame.context_id, id = tracing::field::Empty)
+    )]
+    fn append_inner(
+        &self,
+        mut frame: Frame,
+        id: Option<(Scru128Id, bool)>,
+    ) -> Result<(Frame, usize), StoreError> {
+        // The frame is checked before it takes an id and seq, so a rejected frame doesn't leave
+        // a gap in seqs
 
         // Special handling for xs.context registration
         if frame.topic == "xs.context" {
             if frame.context_id != ZERO_CONTEXT {
-                return Err("xs.context frames must be in zero context".into());
+                return Err(StoreError::InvalidFrame(
+                    "xs.context frames must be in zero context".into(),
+                ));
             }
             frame.ttl = Some(TTL::Forever);
-            self.contexts.write().unwrap().insert(frame.id);
         } else {
             // Validate context exists
             let contexts = self.contexts.read().unwrap();
             if !contexts.contains(&frame.context_id) {
-                return Err(format!("Invalid context: {}", frame.context_id).into());
+                return Err(StoreError::InvalidFrame(format!(
+                    "Invalid context: {}",
+                    frame.context_id
+                )));
             }
         }
 
+        // Special handling for xs.schema registration
+        let new_schema = if frame.topic == "xs.schema" {
+            frame.ttl = Some(TTL::Forever);
+            Some(Schemas::compile(&frame)?)
+        } else {
+            None
+        };
+        let validator = frame
+            .schema
+            .as_deref()
+            .and_then(|name| self.schemas.get(name));
+        schema::validate(validator.as_deref(), &frame)?;
+
+        {
+            let mut sequence = self.sequence.lock().unwrap();
+            match id {
+                Some((id, allow_out_of_order)) => {
+                    self.assign_seq_to_id(&mut sequence, &mut frame, id, allow_out_of_order)?
+                }
+                None => self.assign_id_and_seq(&mut sequence, &mut frame),
+            }
+        }
+        tracing::Span::current().record("id", tracing::field::display(frame.id));
+        if frame.topic == "xs.context" {
+            self.contexts.write().unwrap().insert(frame.id);
+        }
+
         // only store the frame if it's not ephemeral
         if frame.ttl != Some(TTL::Ephemeral) {
             self.insert_frame(&frame)?;
@@ -511,8 +2443,292 @@ impl Store {
             }
         }
 
-        let _ = self.broadcast_tx.send(frame.clone());
-        Ok(frame)
+        if let Some((name, validator)) = new_schema {
+            self.schemas.insert(name, validator);
+        }
+
+        self.counters.appended(1);
+        // Sending only fails when there are no subscribers
+        let receivers = self.broadcast_tx.send(frame.clone()).unwrap_or(0);
+        Ok((frame, receivers))
+    }
+
+    /// Registers a JSON Schema under `name`, by appending an `xs.schema` frame. Frames tagged
+    /// with `name` must then have meta that matches it. Registering a name again replaces it.
+    pub fn register_schema(
+        &self,
+        name: &str,
+        schema: serde_json::Value,
+    ) -> Result<Frame, StoreError> {
+        self.append(
+            Frame::builder("xs.schema", ZERO_CONTEXT)
+                .meta(serde_json::json!({"name": name, "schema": schema}))
+                .build(),
+        )
+    }
+
+    /// Appends a set of frames in a single atomic write. Ids are assigned in order, so they're
+    /// strictly increasing within the batch, and frames are broadcast to subscribers in that same
+    /// order once the write has been committed. If any frame is invalid, nothing is written.
+    pub fn append_batch(&self, frames: Vec<Frame>) -> Result<Vec<Frame>, StoreError> {
+        let _gate = self.append_gate.read().unwrap();
+        let mut batch = self.keyspace.batch();
+        let mut new_contexts = Vec::new();
+        let mut new_schemas: Vec<(String, Arc<jsonschema::Validator>)> = Vec::new();
+        let mut head_ttls = Vec::new();
+
+        let frames = {
+            let contexts = self.contexts.read().unwrap();
+            let mut sequence = self.sequence.lock().unwrap();
+            // Handed back if the batch is rejected, so it doesn't leave a gap in seqs
+            let unused = sequence.clone();
+            let frames = frames
+                .into_iter()
+                .map(|mut frame| {
+                    if frame.topic == "xs.context" {
+                        if frame.context_id != ZERO_CONTEXT {
+                            return Err(StoreError::InvalidFrame(
+                                "xs.context frames must be in zero context".into(),
+                            ));
+                        }
+                        frame.ttl = Some(TTL::Forever);
+                    } else if !contexts.contains(&frame.context_id)
+                        && !new_contexts.contains(&frame.context_id)
+                    {
+                        return Err(StoreError::InvalidFrame(format!(
+                            "Invalid context: {}",
+                            frame.context_id
+                        )));
+                    }
+
+                    if frame.topic == "xs.schema" {
+                        frame.ttl = Some(TTL::Forever);
+                        new_schemas.push(Schemas::compile(&frame)?);
+                    }
+                    // Schemas registered earlier in the batch apply to the frames after them
+                    let validator = frame.schema.as_deref().and_then(|name| {
+                        new_schemas
+                            .iter()
+                            .rev()
+                            .find(|(new_name, _)| new_name == name)
+                            .map(|(_, validator)| validator.clone())
+                            .or_else(|| self.schemas.get(name))
+                    });
+                    schema::validate(validator.as_deref(), &frame)?;
+
+                    self.assign_id_and_seq(&mut sequence, &mut frame);
+                    if frame.topic == "xs.context" {
+                        new_contexts.push(frame.id);
+                    }
+
+                    if frame.ttl != Some(TTL::Ephemeral) {
+                        self.batch_insert_frame(&mut batch, &frame)?;
+
+                        if let Some(TTL::Head(n)) = frame.ttl {
+                            head_ttls.push(GCTask::CheckHeadTTL {
+                                context_id: frame.context_id,
+                                topic: frame.topic.clone(),
+                                keep: n,
+                            });
+                        }
+                    }
+
+                    Ok(frame)
+                })
+                .collect::<Result<Vec<_>, StoreError>>()
+                .inspect_err(|_| *sequence = unused)?;
+            frames
+        };
+
+        batch.commit()?;
+        self.keyspace.persist(fjall::PersistMode::SyncAll)?;
+
+        self.contexts.write().unwrap().extend(new_contexts);
+        for (name, validator) in new_schemas {
+            self.schemas.insert(name, validator);
+        }
+        for frame in frames
+            .iter()
+            .filter(|frame| frame.ttl != Some(TTL::Ephemeral))
+        {
+            self.topic_added(&frame.topic);
+        }
+
+        for task in head_ttls {
+            let _ = self.gc_tx.send(task);
+        }
+
+        self.counters.appended(frames.len() as u64);
+        for frame in &frames {
+            let _ = self.broadcast_tx.send(frame.clone());
+        }
+
+        Ok(frames)
+    }
+
+    /// Applies TTLs to what's on disk, so frames that expired, or fell out of their topic's head,
+    /// while the store was down are gone before anything reads them.
+    fn sweep_ttl(&self) {
+        let mut expired = Vec::new();
+        // The latest frame's TTL decides how much of a topic to keep, as it would on append
+        let mut heads = std::collections::HashMap::new();
+        for frame in self.iter_frames(None, None) {
+            match frame.ttl {
+                Some(TTL::Time(ttl)) if is_expired(&frame.id, &ttl) => expired.push(frame.id),
+                Some(TTL::Head(keep)) => {
+                    heads.insert((frame.context_id, frame.topic), keep);
+                }
+                _ => {}
+            }
+        }
+
+        for id in expired {
+            let _ = self.delete(&id);
+        }
+        for ((context_id, topic), keep) in heads {
+            self.trim_topic_head(context_id, &topic, keep);
+        }
+    }
+
+    /// Removes all but the newest `keep` frames of a topic.
+    fn trim_topic_head(&self, context_id: Scru128Id, topic: &str, keep: u32) {
+        let prefix = idx_topic_key_prefix(context_id, topic);
+        let frames_to_remove: Vec<_> = self
+            .idx_topic
+            .prefix(&prefix)
+            .rev() // Scan from newest to oldest
+            .skip(keep as usize)
+            .map(|r| idx_topic_frame_id_from_key(&r.unwrap().0))
+            .collect();
+
+        for frame_id in frames_to_remove {
+            let _ = self.delete(&frame_id);
+        }
+    }
+
+    /// Resolves where a read starts to the id it reads after, if any. Reads from a `seq` scan
+    /// from the start, as seqs aren't indexed, and leave out earlier frames as they go.
+    fn resolve_last_id(&self, options: &ReadOptions) -> Option<Scru128Id> {
+        match options.start()? {
+            Cursor::Start | Cursor::Seq(_) => None,
+            Cursor::Id(id) => Some(id),
+            Cursor::Head => self
+                .iter_frames_rev(options.context_id)
+                .next()
+                .map(|f| f.id),
+            // Just before the first id that could have been minted at `time`
+            Cursor::Time(time) => {
+                let ms = u128::try_from(time.timestamp_millis()).unwrap_or(0);
+                (ms << 80).checked_sub(1).map(Scru128Id::from_u128)
+            }
+        }
+    }
+
+    /// Returns the last `n` unexpired frames after `last_id` matching `options`, in forward order.
+    fn last_n_frames(
+        &self,
+        n: usize,
+        options: &ReadOptions,
+        last_id: Option<&Scru128Id>,
+    ) -> Vec<Frame> {
+        let mut frames: Vec<Frame> = self
+            .iter_frames_rev(options.context_id)
+            .take_while(|frame| last_id.is_none_or(|last_id| frame.id > *last_id))
+            .filter(|frame| options.matches(frame))
+            .filter(|frame| match frame.ttl.as_ref() {
+                Some(TTL::Time(ttl)) if is_expired(&frame.id, ttl) => {
+                    let _ = self.gc_tx.send(GCTask::Remove(frame.id));
+                    false
+                }
+                _ => true,
+            })
+            .take(n)
+            .collect();
+        frames.reverse();
+        frames
+    }
+
+    fn iter_frames_rev(
+        &self,
+        context_id: Option<Scru128Id>,
+    ) -> Box<dyn Iterator<Item = Frame> + '_> {
+        match context_id {
+            Some(ctx_id) => Box::new(self.idx_context.prefix(ctx_id.as_bytes()).rev().filter_map(
+                move |r| {
+                    let (key, _) = r.ok()?;
+                    let frame_id_bytes = &key[16..];
+                    let frame_id = Scru128Id::from_bytes(frame_id_bytes.try_into().ok()?);
+                    skip_unreadable(self.get(&frame_id).transpose()?)
+                },
+            )),
+            None => Box::new(self.frame_partition.iter().rev().filter_map(|r| {
+                skip_unreadable(
+                    r.map_err(StoreError::from)
+                        .and_then(|record| deserialize_frame(self.options.codec, record)),
+                )
+            })),
+        }
+    }
+
+    /// Frames strictly between `after` and `before`, oldest first, across all contexts. Either
+    /// bound can be left open. `xs.remove` tombstones, and frames that fail to decode, are
+    /// skipped.
+    pub fn range(
+        &self,
+        after: Option<Scru128Id>,
+        before: Option<Scru128Id>,
+        limit: Option<usize>,
+    ) -> Vec<Frame> {
+        let bound = |id: Option<Scru128Id>| match id {
+            Some(id) => Bound::Excluded(id.as_bytes().to_vec()),
+            None => Bound::Unbounded,
+        };
+        self.frame_partition
+            .range((bound(after), bound(before)))
+            .filter_map(|r| {
+                let (_, value) = r.ok()?;
+                self.options.codec.decode(&value).ok()
+            })
+            .filter(|frame| frame.topic != "xs.remove")
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// The most recent `n` frames, oldest first, across all contexts, scanning back from the end
+    /// of the stream rather than reading it from the start. With a `topic`, only frames matching
+    /// it count, with the same `*` wildcards as [`ReadOptions::topic`]. `xs.remove` tombstones,
+    /// and frames that fail to decode or have expired, are skipped.
+    pub fn latest_n(&self, n: usize, topic: Option<&str>) -> Vec<Frame> {
+        let mut frames: Vec<Frame> = self
+            .frame_partition
+            .iter()
+            .rev()
+            .filter_map(|r| {
+                let (_, value) = r.ok()?;
+                self.options.codec.decode(&value).ok()
+            })
+            .filter(|frame| frame.topic != "xs.remove")
+            .filter(|frame| topic.is_none_or(|topic| topic_matches(topic, &frame.topic)))
+            .filter(
+                |frame| !matches!(&frame.ttl, Some(TTL::Time(ttl)) if is_expired(&frame.id, ttl)),
+            )
+            .take(n)
+            .collect();
+        frames.reverse();
+        frames
+    }
+
+    /// Folds every frame, oldest first and across all contexts, into an accumulator, without
+    /// collecting the stream. `xs.remove` tombstones, and frames that fail to decode, are skipped.
+    pub fn fold<T>(&self, init: T, mut f: impl FnMut(T, &Frame) -> T) -> T {
+        self.frame_partition
+            .iter()
+            .filter_map(|r| {
+                let (_, value) = r.ok()?;
+                self.options.codec.decode(&value).ok()
+            })
+            .filter(|frame| frame.topic != "xs.remove")
+            .fold(init, |acc, frame| f(acc, &frame))
     }
 
     fn iter_frames(
@@ -541,7 +2757,7 @@ impl Store {
                             let (key, _) = r.ok()?;
                             let frame_id_bytes = &key[16..];
                             let frame_id = Scru128Id::from_bytes(frame_id_bytes.try_into().ok()?);
-                            self.get(&frame_id)
+                            skip_unreadable(self.get(&frame_id).transpose()?)
                         }),
                 )
             }
@@ -551,51 +2767,109 @@ impl Store {
                     None => (Bound::Unbounded, Bound::Unbounded),
                 };
 
-                Box::new(
-                    self.frame_partition
-                        .range(range)
-                        .map(|r| deserialize_frame(r.unwrap())),
-                )
+                Box::new(self.frame_partition.range(range).filter_map(|r| {
+                    skip_unreadable(
+                        r.map_err(StoreError::from)
+                            .and_then(|record| deserialize_frame(self.options.codec, record)),
+                    )
+                }))
             }
         }
     }
 }
 
-fn spawn_gc_worker(mut gc_rx: UnboundedReceiver<GCTask>, store: Store) {
+// The store's lock file. Once the last handle lets go of it, it shuts the gc worker down and
+// waits for it, and with it the keyspace the worker holds, before releasing the lock, so the
+// next store to take it doesn't open the keyspace while it's still open here
+struct StoreLock {
+    _file: std::fs::File,
+    gc_tx: UnboundedSender<GCTask>,
+    gc_worker: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let _ = self.gc_tx.send(GCTask::Shutdown);
+        if let Some(gc_worker) = self.gc_worker.take() {
+            let _ = gc_worker.join();
+        }
+    }
+}
+
+fn spawn_gc_worker(
+    mut gc_rx: UnboundedReceiver<GCTask>,
+    store: Store,
+) -> std::thread::JoinHandle<()> {
     std::thread::spawn(move || {
         while let Some(task) = gc_rx.blocking_recv() {
             match task {
                 GCTask::Remove(id) => {
-                    let _ = store.remove(&id);
+                    let _ = store.delete(&id);
                 }
 
                 GCTask::CheckHeadTTL {
                     context_id,
                     topic,
                     keep,
-                } => {
-                    let prefix = idx_topic_key_prefix(context_id, &topic);
-                    let frames_to_remove: Vec<_> = store
-                        .idx_topic
-                        .prefix(&prefix)
-                        .rev() // Scan from newest to oldest
-                        .skip(keep as usize)
-                        .map(|r| {
-                            Scru128Id::from_bytes(idx_topic_frame_id_from_key(&r.unwrap().0).into())
-                        })
-                        .collect();
-
-                    for frame_id in frames_to_remove {
-                        let _ = store.remove(&frame_id);
-                    }
-                }
+                } => store.trim_topic_head(context_id, &topic, keep),
 
                 GCTask::Drain(tx) => {
                     let _ = tx.send(());
                 }
+
+                GCTask::Compact { strategy, tx } => {
+                    let _ = tx.send(store.compact_sync(strategy));
+                }
+
+                GCTask::EvictCas => {
+                    let _ = store.evict_cas_sync();
+                }
+
+                GCTask::RenameTopic { from, to, tx } => {
+                    let _ = tx.send(store.rename_topic_sync(&from, &to));
+                }
+
+                GCTask::Shutdown => break,
             }
         }
-    });
+    })
+}
+
+// Gives `frame` the next seq. Only stored frames take it: an ephemeral frame carries the seq the
+// next stored frame gets, so seqs only skip over frames that were stored and then removed
+fn take_seq(sequence: &mut Sequence, frame: &mut Frame) {
+    frame.seq = sequence.next_seq;
+    if frame.ttl != Some(TTL::Ephemeral) {
+        sequence.next_seq += 1;
+    }
+}
+
+fn merge_meta(
+    meta: &mut Option<serde_json::Value>,
+    patch: &serde_json::Map<String, serde_json::Value>,
+) {
+    // Meta that isn't an object has no keys to keep
+    if !meta.as_ref().is_some_and(|meta| meta.is_object()) {
+        *meta = Some(serde_json::Value::Object(Default::default()));
+    }
+    let Some(serde_json::Value::Object(meta)) = meta else {
+        unreachable!()
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            meta.remove(key);
+        } else {
+            meta.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+fn id_from_key(key: &[u8]) -> Scru128Id {
+    Scru128Id::from_bytes(key.try_into().unwrap())
+}
+
+fn frame_key(frame: &Frame) -> Option<&str> {
+    frame.meta.as_ref()?.get("key")?.as_str()
 }
 
 fn is_expired(id: &Scru128Id, ttl: &Duration) -> bool {
@@ -651,10 +2925,22 @@ fn idx_context_key_range_end(context_id: Scru128Id) -> Vec<u8> {
     bytes
 }
 
-fn deserialize_frame<B1: AsRef<[u8]>, B2: AsRef<[u8]>>(record: (B1, B2)) -> Frame {
-    serde_json::from_slice(record.1.as_ref()).unwrap_or_else(|e| {
-        let key = std::str::from_utf8(record.0.as_ref()).unwrap();
-        let value = std::str::from_utf8(record.1.as_ref()).unwrap();
-        panic!("Failed to deserialize frame: {} {} {}", e, key, value)
+fn deserialize_frame<B1: AsRef<[u8]>, B2: AsRef<[u8]>>(
+    codec: Codec,
+    record: (B1, B2),
+) -> Result<Frame, StoreError> {
+    codec.decode(record.1.as_ref()).map_err(|e| {
+        let key = match <[u8; 16]>::try_from(record.0.as_ref()) {
+            Ok(bytes) => Scru128Id::from_bytes(bytes).to_string(),
+            Err(_) => String::from_utf8_lossy(record.0.as_ref()).into_owned(),
+        };
+        StoreError::InvalidFrame(format!("failed to decode frame {}: {}", key, e))
     })
 }
+
+// Logs and drops a frame that couldn't be read, so one bad record doesn't end a scan
+fn skip_unreadable(frame: Result<Frame, StoreError>) -> Option<Frame> {
+    frame
+        .inspect_err(|e| tracing::warn!("Skipping unreadable frame: {}", e))
+        .ok()
+}
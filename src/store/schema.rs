@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::store::{Frame, StoreError};
+
+/// Compiled JSON Schemas, by name, that frames tagged with a `schema` have their meta checked
+/// against. Schemas are registered by appending an `xs.schema` frame with meta
+/// `{name, schema}`.
+#[derive(Clone, Default)]
+pub struct Schemas {
+    validators: Arc<RwLock<HashMap<String, Arc<jsonschema::Validator>>>>,
+}
+
+impl Schemas {
+    /// Compiles the schema carried by an `xs.schema` frame.
+    pub fn compile(frame: &Frame) -> Result<(String, Arc<jsonschema::Validator>), StoreError> {
+        let invalid = |msg: &str| StoreError::InvalidFrame(format!("xs.schema: {}", msg));
+        let meta = frame
+            .meta
+            .as_ref()
+            .ok_or_else(|| invalid("meta must hold a name and a schema"))?;
+        let name = meta
+            .get("name")
+            .and_then(|name| name.as_str())
+            .ok_or_else(|| invalid("name must be a string"))?;
+        let schema = meta
+            .get("schema")
+            .ok_or_else(|| invalid("schema is missing"))?;
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| invalid(&format!("invalid schema {}: {}", name, e)))?;
+        Ok((name.to_string(), Arc::new(validator)))
+    }
+
+    pub fn insert(&self, name: String, validator: Arc<jsonschema::Validator>) {
+        self.validators.write().unwrap().insert(name, validator);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<jsonschema::Validator>> {
+        self.validators.read().unwrap().get(name).cloned()
+    }
+}
+
+/// Checks a tagged frame's meta against its schema. A frame without meta is checked as `null`.
+pub fn validate(
+    validator: Option<&jsonschema::Validator>,
+    frame: &Frame,
+) -> Result<(), StoreError> {
+    let Some(name) = &frame.schema else {
+        return Ok(());
+    };
+    let validator =
+        validator.ok_or_else(|| StoreError::InvalidFrame(format!("unknown schema: {}", name)))?;
+    let meta = frame.meta.clone().unwrap_or(serde_json::Value::Null);
+    let errors: Vec<String> = validator
+        .iter_errors(&meta)
+        .map(|e| e.to_string())
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidFrame(format!(
+            "meta doesn't match schema {}: {}",
+            name,
+            errors.join("; ")
+        )))
+    }
+}
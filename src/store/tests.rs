@@ -1,5 +1,7 @@
 use crate::store::*;
 
+use scru128::Scru128Id;
+
 use std::time::Duration;
 
 mod tests_ensure {
@@ -45,11 +47,11 @@ mod tests_read_options {
 
         assert_eq!(
             Some(frame1),
-            store.head("hello", crate::store::ZERO_CONTEXT)
+            store.head("hello", crate::store::ZERO_CONTEXT).unwrap()
         );
         assert_eq!(
             Some(frame2),
-            store.head("hallo", crate::store::ZERO_CONTEXT)
+            store.head("hallo", crate::store::ZERO_CONTEXT).unwrap()
         );
     }
 
@@ -91,7 +93,7 @@ mod tests_read_options {
             TestCase {
                 input: Some("last-id=03bidzvknotgjpvuew3k23g45"),
                 expected: ReadOptions::builder()
-                    .last_id("03bidzvknotgjpvuew3k23g45".parse().unwrap())
+                    .last_id("03bidzvknotgjpvuew3k23g45".parse::<Scru128Id>().unwrap())
                     .build(),
                 reencoded: None,
             },
@@ -99,10 +101,18 @@ mod tests_read_options {
                 input: Some("follow&last-id=03bidzvknotgjpvuew3k23g45"),
                 expected: ReadOptions::builder()
                     .follow(FollowOption::On)
-                    .last_id("03bidzvknotgjpvuew3k23g45".parse().unwrap())
+                    .last_id("03bidzvknotgjpvuew3k23g45".parse::<Scru128Id>().unwrap())
                     .build(),
                 reencoded: Some("follow=true&last-id=03bidzvknotgjpvuew3k23g45"),
             },
+            TestCase {
+                input: Some("follow&last-id=head"),
+                expected: ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .last_id(LastId::Head)
+                    .build(),
+                reencoded: Some("follow=true&last-id=head"),
+            },
             TestCase {
                 input: Some("context-id=03d8tlkt4iw1gqqp703hlyfzl"),
                 expected: ReadOptions::builder()
@@ -110,6 +120,82 @@ mod tests_read_options {
                     .build(),
                 reencoded: None,
             },
+            TestCase {
+                input: Some("emit-eoh"),
+                expected: ReadOptions::builder().emit_eoh(true).build(),
+                reencoded: Some("emit-eoh=true"),
+            },
+            TestCase {
+                input: Some("follow&last-n=3"),
+                expected: ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .last_n(3)
+                    .build(),
+                reencoded: Some("follow=true&last-n=3"),
+            },
+            TestCase {
+                input: Some("from-seq=42"),
+                expected: ReadOptions::builder().from_seq(42).build(),
+                reencoded: None,
+            },
+            TestCase {
+                input: Some("cursor=seq:42"),
+                expected: ReadOptions::builder().cursor(Cursor::Seq(42)).build(),
+                reencoded: Some("cursor=seq%3A42"),
+            },
+            TestCase {
+                input: Some("cursor=id:03bidzvknotgjpvuew3k23g45"),
+                expected: ReadOptions::builder()
+                    .cursor(Cursor::Id("03bidzvknotgjpvuew3k23g45".parse().unwrap()))
+                    .build(),
+                reencoded: Some("cursor=id%3A03bidzvknotgjpvuew3k23g45"),
+            },
+            TestCase {
+                input: Some("cursor=head"),
+                expected: ReadOptions::builder().cursor(Cursor::Head).build(),
+                reencoded: None,
+            },
+            TestCase {
+                input: Some("cursor=start"),
+                expected: ReadOptions::builder().cursor(Cursor::Start).build(),
+                reencoded: None,
+            },
+            TestCase {
+                input: Some("cursor=time:1700000000000"),
+                expected: ReadOptions::builder()
+                    .cursor(Cursor::Time(
+                        chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap(),
+                    ))
+                    .build(),
+                reencoded: Some("cursor=time%3A2023-11-14T22%3A13%3A20.000Z"),
+            },
+            TestCase {
+                input: Some("max-rate=2.5"),
+                expected: ReadOptions::builder().max_rate(2.5).build(),
+                reencoded: None,
+            },
+            TestCase {
+                input: Some("report-gaps=true"),
+                expected: ReadOptions::builder().report_gaps(true).build(),
+                reencoded: None,
+            },
+            TestCase {
+                input: Some("dedupe-by-hash=true"),
+                expected: ReadOptions::builder().dedupe_by_hash(true).build(),
+                reencoded: None,
+            },
+            TestCase {
+                input: Some("topic=logs.*"),
+                expected: ReadOptions::builder().topic("logs.*").build(),
+                reencoded: Some("topic=logs.*"),
+            },
+            TestCase {
+                input: Some("topics=b,a,c"),
+                expected: ReadOptions::builder()
+                    .topics(["a", "b", "c"].map(String::from).into())
+                    .build(),
+                reencoded: Some("topics=a%2Cb%2Cc"),
+            },
         ];
 
         for case in &test_cases {
@@ -133,6 +219,28 @@ mod tests_read_options {
         }
 
         assert!(ReadOptions::from_query(Some("last-id=123")).is_err());
+        for cursor in ["seq:x", "id:123", "time:yesterday", "tail", "seq"] {
+            let query = format!("cursor={}", cursor);
+            assert!(ReadOptions::from_query(Some(&query)).is_err(), "{}", cursor);
+        }
+    }
+
+    #[test]
+    fn test_topic_matches() {
+        // exact
+        assert!(topic_matches("logs", "logs"));
+        assert!(!topic_matches("logs", "logs.app"));
+        // prefix
+        assert!(topic_matches("logs.*", "logs.app"));
+        assert!(topic_matches("logs.*", "logs.app.error"));
+        assert!(!topic_matches("logs.*", "logs"));
+        assert!(!topic_matches("logs.*", "metrics.app"));
+        // wildcards elsewhere
+        assert!(topic_matches("*.error", "logs.app.error"));
+        assert!(topic_matches("logs.*.error", "logs.app.error"));
+        assert!(!topic_matches("logs.*.error", "logs.app.warn"));
+        assert!(topic_matches("*", "anything"));
+        assert!(!topic_matches("a*a", "a"));
     }
 }
 
@@ -152,7 +260,7 @@ mod tests_store {
         let frame = store
             .append(Frame::builder("stream", ZERO_CONTEXT).meta(meta).build())
             .unwrap();
-        let got = store.get(&frame.id);
+        let got = store.get(&frame.id).unwrap();
         assert_eq!(Some(frame.clone()), got);
     }
 
@@ -203,6 +311,182 @@ mod tests_store {
         assert_eq!("xs.pulse".to_string(), recver.recv().await.unwrap().topic);
     }
 
+    #[tokio::test]
+    async fn test_control_frames_bypass_topic_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let wanted = store
+            .append(Frame::builder("wanted", ZERO_CONTEXT).build())
+            .unwrap();
+        store
+            .append(Frame::builder("unwanted", ZERO_CONTEXT).build())
+            .unwrap();
+
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::WithHeartbeat(Duration::from_millis(5)))
+                    .topic("wanted")
+                    .build(),
+            )
+            .await;
+
+        assert_eq!(recver.recv().await.unwrap(), wanted);
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.pulse");
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.pulse");
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_waits_for_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        for _ in 0..500 {
+            store
+                .append(Frame::builder("stream", ZERO_CONTEXT).build())
+                .unwrap();
+        }
+
+        // pace the backlog so it takes several heartbeat intervals to deliver
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::WithHeartbeat(Duration::from_millis(1)))
+                    .max_rate(10_000.0)
+                    .build(),
+            )
+            .await;
+
+        let mut count = 0;
+        loop {
+            let frame = recver.recv().await.unwrap();
+            match frame.topic.as_str() {
+                "xs.threshold" => break,
+                "stream" => count += 1,
+                topic => panic!("unexpected {} before xs.threshold", topic),
+            }
+        }
+        assert_eq!(count, 500);
+        assert_eq!("xs.pulse".to_string(), recver.recv().await.unwrap().topic);
+    }
+
+    #[tokio::test]
+    async fn test_cursor() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        // A millisecond apart, so each can be picked out by its time
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            frames.push(
+                store
+                    .append(Frame::builder("stream", ZERO_CONTEXT).build())
+                    .unwrap(),
+            );
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        let time_of = |frame: &Frame| {
+            chrono::DateTime::from_timestamp_millis(frame.id.timestamp() as i64).unwrap()
+        };
+        let first = |cursor: Cursor| {
+            store
+                .read_sync(ReadOptions::builder().cursor(cursor).build())
+                .next()
+        };
+
+        assert_eq!(first(Cursor::Start).as_ref(), Some(&frames[0]));
+        assert_eq!(first(Cursor::Id(frames[1].id)).as_ref(), Some(&frames[2]));
+        assert_eq!(first(Cursor::Seq(frames[1].seq)).as_ref(), Some(&frames[1]));
+        assert_eq!(
+            first(Cursor::Time(time_of(&frames[2]))).as_ref(),
+            Some(&frames[2])
+        );
+        assert_eq!(
+            first(Cursor::Time(chrono::DateTime::UNIX_EPOCH)).as_ref(),
+            Some(&frames[0])
+        );
+        assert_eq!(first(Cursor::Head), None);
+
+        // The cursor wins over last-id and from-seq
+        let options = ReadOptions::builder()
+            .cursor(Cursor::Seq(frames[3].seq))
+            .last_id(frames[0].id)
+            .from_seq(frames[1].seq)
+            .build();
+        assert_eq!(store.read_sync(options).collect::<Vec<_>>(), frames[3..]);
+
+        // Following from head picks up with the next frame appended
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .cursor(Cursor::Head)
+                    .build(),
+            )
+            .await;
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+        let next = store
+            .append(Frame::builder("stream", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(recver.recv().await.unwrap(), next);
+
+        // And following from a seq leaves out earlier live frames too
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .cursor(Cursor::Seq(next.seq))
+                    .build(),
+            )
+            .await;
+        assert_eq!(recver.recv().await.unwrap(), next);
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_ends_with_subscription() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+        let options = || {
+            ReadOptions::builder()
+                .follow(FollowOption::WithHeartbeat(Duration::from_millis(5)))
+                .tail(true)
+        };
+
+        // A reader that goes away is unsubscribed on its next pulse at the latest, with nothing
+        // appended in the meantime
+        let mut recver = store.read(options().build()).await;
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.pulse");
+        drop(recver);
+        timeout(Duration::from_secs(1), async {
+            while store.subscriber_count() > 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("follower wasn't unsubscribed");
+
+        // And a subscription that's done stops the heartbeat, closing the stream
+        let mut recver = store.read(options().limit(1).build()).await;
+        let frame = store
+            .append(Frame::builder("stream", ZERO_CONTEXT).build())
+            .unwrap();
+        let received = timeout(Duration::from_secs(1), async {
+            let mut received = Vec::new();
+            while let Some(frame) = recver.recv().await {
+                if frame.topic != "xs.pulse" {
+                    received.push(frame);
+                }
+            }
+            received
+        })
+        .await
+        .expect("stream wasn't closed");
+        assert_eq!(received, vec![frame]);
+    }
+
     #[tokio::test]
     async fn test_stream_basics() {
         let temp_dir = TempDir::new().unwrap();
@@ -216,7 +500,7 @@ mod tests_store {
             .unwrap();
 
         assert_eq!(
-            store.head("/stream", crate::store::ZERO_CONTEXT),
+            store.head("/stream", crate::store::ZERO_CONTEXT).unwrap(),
             Some(f2.clone())
         );
 
@@ -329,61 +613,2002 @@ mod tests_store {
             .append(Frame::builder("test", ZERO_CONTEXT).build())
             .unwrap();
 
-        // Start read with limit 3 and follow enabled
-        let options = ReadOptions::builder()
-            .limit(3)
-            .follow(FollowOption::On)
-            .context_id(crate::store::ZERO_CONTEXT)
-            .build();
-        let mut rx = store.read(options).await;
+        // Start read with limit 3 and follow enabled
+        let options = ReadOptions::builder()
+            .limit(3)
+            .follow(FollowOption::On)
+            .context_id(crate::store::ZERO_CONTEXT)
+            .build();
+        let mut rx = store.read(options).await;
+
+        // We should only get exactly 3 frames, even though follow is enabled
+        // and there are 5 frames available
+        assert_eq!(Some(frame1), rx.recv().await);
+        assert_eq!(Some(frame2), rx.recv().await);
+        assert_eq!(Some(frame3), rx.recv().await);
+
+        // This should complete quickly if the channel is actually closed
+        assert_eq!(
+            Ok(None),
+            timeout(Duration::from_millis(100), rx.recv()).await,
+            "Channel should be closed after limit"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_emit_eoh() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let f1 = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        let f2 = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+
+        let recver = store
+            .read(ReadOptions::builder().emit_eoh(true).build())
+            .await;
+        let frames = tokio_stream::wrappers::ReceiverStream::new(recver)
+            .collect::<Vec<Frame>>()
+            .await;
+
+        // the marker appears exactly once, after the last historical frame
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[..2], [f1.clone(), f2]);
+        assert_eq!(frames[2].topic, "xs.eoh");
+
+        // the marker is also sent when the read is cut short by a limit
+        let recver = store
+            .read(ReadOptions::builder().emit_eoh(true).limit(1).build())
+            .await;
+        let frames = tokio_stream::wrappers::ReceiverStream::new(recver)
+            .collect::<Vec<Frame>>()
+            .await;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], f1);
+        assert_eq!(frames[1].topic, "xs.eoh");
+    }
+
+    #[tokio::test]
+    async fn test_read_follow_last_n() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let frames: Vec<Frame> = (0..10)
+            .map(|_| {
+                store
+                    .append(Frame::builder("test", ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .last_n(3)
+                    .build(),
+            )
+            .await;
+
+        // the last 3 historical frames, in order, then the threshold
+        for expected in &frames[7..] {
+            assert_eq!(&recver.recv().await.unwrap(), expected);
+        }
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+
+        // then live frames
+        let live = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(recver.recv().await.unwrap(), live);
+        assert_no_more_frames(&mut recver).await;
+    }
+
+    #[tokio::test]
+    async fn test_seq() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+
+        {
+            let store = Store::new(path.clone());
+            for i in 0..3 {
+                let frame = store
+                    .append(Frame::builder("test", ZERO_CONTEXT).build())
+                    .unwrap();
+                assert_eq!(frame.seq, i);
+            }
+            let frames = store
+                .append_batch(vec![
+                    Frame::builder("test", ZERO_CONTEXT).build(),
+                    Frame::builder("test", ZERO_CONTEXT)
+                        .ttl(TTL::Ephemeral)
+                        .build(),
+                ])
+                .unwrap();
+            assert_eq!(frames.iter().map(|f| f.seq).collect::<Vec<_>>(), [3, 4]);
+        }
+
+        // the counter survives a restart. The ephemeral frame didn't take its seq
+        let store = Store::new(path);
+        let frame = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(frame.seq, 4);
+
+        let frames: Vec<_> = store
+            .read_sync(ReadOptions::builder().from_seq(3).build())
+            .map(|f| f.seq)
+            .collect();
+        assert_eq!(frames, [3, 4]);
+
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .from_seq(4)
+                    .build(),
+            )
+            .await;
+        assert_eq!(recver.recv().await.unwrap().seq, 4);
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+        let frame = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(frame.seq, 5);
+        assert_eq!(recver.recv().await.unwrap(), frame);
+    }
+
+    #[tokio::test]
+    async fn test_read_report_gaps() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let frames: Vec<_> = (0..5)
+            .map(|_| {
+                store
+                    .append(Frame::builder("test", ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+        // Deleted outright, as expiry does, leaving no tombstone behind
+        store.delete(&frames[2].id).unwrap();
+
+        let options = ReadOptions::builder()
+            .follow(FollowOption::On)
+            .report_gaps(true)
+            .build();
+        let mut recver = store.read(options).await;
+
+        let mut topics = Vec::new();
+        for _ in 0..5 {
+            let frame = recver.recv().await.unwrap();
+            if frame.topic == "xs.gap" {
+                assert_eq!(
+                    frame.meta,
+                    Some(serde_json::json!({"from_seq": 2, "to_seq": 2}))
+                );
+            }
+            topics.push(frame.topic);
+        }
+        assert_eq!(topics, ["test", "test", "xs.gap", "test", "test"]);
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+
+        // live frames follow on from history, ephemeral ones included
+        let live = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        let ephemeral = store
+            .append(
+                Frame::builder("test", ZERO_CONTEXT)
+                    .ttl(TTL::Ephemeral)
+                    .build(),
+            )
+            .unwrap();
+        assert_eq!(recver.recv().await.unwrap(), live);
+        assert_eq!(recver.recv().await.unwrap(), ephemeral);
+
+        // the ephemeral frame isn't in history, and didn't take a seq, so nothing is missing
+        let options = ReadOptions::builder()
+            .last_id(frames[4].id)
+            .report_gaps(true)
+            .build();
+        let mut recver = store.read(options).await;
+        assert_eq!(recver.recv().await.unwrap(), live);
+        assert!(recver.recv().await.is_none());
+
+        // without the option, nothing extra is sent
+        let mut recver = store.read(ReadOptions::default()).await;
+        let mut count = 0;
+        while let Some(frame) = recver.recv().await {
+            assert_ne!(frame.topic, "xs.gap");
+            count += 1;
+        }
+        assert_eq!(count, 5);
+    }
+
+    #[tokio::test]
+    async fn test_read_report_gaps_skips_unstored_frames() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let first = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        // rejected appends and ephemeral frames don't use up a seq
+        assert!(store
+            .append(Frame::builder("test", Scru128Id::from_u128(1)).build())
+            .is_err());
+        assert!(store
+            .append_batch(vec![
+                Frame::builder("test", ZERO_CONTEXT).build(),
+                Frame::builder("test", Scru128Id::from_u128(1)).build(),
+            ])
+            .is_err());
+        store
+            .append(
+                Frame::builder("test", ZERO_CONTEXT)
+                    .ttl(TTL::Ephemeral)
+                    .build(),
+            )
+            .unwrap();
+        let second = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(second.seq, first.seq + 1);
+
+        let mut recver = store
+            .read(ReadOptions::builder().report_gaps(true).build())
+            .await;
+        assert_eq!(recver.recv().await, Some(first));
+        assert_eq!(recver.recv().await, Some(second));
+        assert_eq!(recver.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_read_dedupe_by_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let append = |topic: &str, content: &str| {
+            store
+                .append(
+                    Frame::builder(topic, ZERO_CONTEXT)
+                        .hash(store.cas_insert_sync(content).unwrap())
+                        .build(),
+                )
+                .unwrap()
+        };
+
+        let first = append("key", "a");
+        append("key", "a");
+        let other = append("other", "a");
+        let changed = append("key", "b");
+        let back = append("key", "a");
+
+        let options = ReadOptions::builder()
+            .follow(FollowOption::On)
+            .dedupe_by_hash(true)
+            .build();
+        assert_eq!(
+            store.read_sync(options.clone()).collect::<Vec<_>>(),
+            [first.clone(), other.clone(), changed.clone(), back.clone()]
+        );
+
+        // live frames are deduped against the last historical frame for their topic
+        let mut recver = store.read(options).await;
+        for expected in [&first, &other, &changed, &back] {
+            assert_eq!(&recver.recv().await.unwrap(), expected);
+        }
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+        append("key", "a");
+        let live = append("key", "c");
+        assert_eq!(recver.recv().await.unwrap(), live);
+        append("key", "c");
+        let empty = store
+            .append(Frame::builder("key", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(recver.recv().await.unwrap(), empty);
+
+        // without the option, every frame comes through
+        assert_eq!(store.read_sync(Default::default()).count(), 9);
+    }
+
+    #[test]
+    fn test_append_span() {
+        use tracing::span::{Attributes, Id, Record};
+        use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+        type Span = (String, HashMap<String, String>);
+
+        // Collects each span's name and fields, as they're recorded
+        #[derive(Clone, Default)]
+        struct Spans(Arc<Mutex<HashMap<u64, Span>>>);
+
+        struct Fields<'a>(&'a mut HashMap<String, String>);
+
+        impl tracing::field::Visit for Fields<'_> {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .insert(field.name().to_string(), format!("{:?}", value));
+            }
+        }
+
+        impl<S: tracing::Subscriber> Layer<S> for Spans {
+            fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+                let mut fields = HashMap::new();
+                attrs.record(&mut Fields(&mut fields));
+                let name = attrs.metadata().name().to_string();
+                self.0.lock().unwrap().insert(id.into_u64(), (name, fields));
+            }
+
+            fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+                if let Some((_, fields)) = self.0.lock().unwrap().get_mut(&id.into_u64()) {
+                    values.record(&mut Fields(fields));
+                }
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let spans = Spans::default();
+        let subscriber = tracing_subscriber::registry().with(spans.clone());
+        let frame = tracing::subscriber::with_default(subscriber, || {
+            store
+                .append(Frame::builder("topic", ZERO_CONTEXT).build())
+                .unwrap()
+        });
+
+        let spans = spans.0.lock().unwrap();
+        let appends: Vec<_> = spans
+            .values()
+            .filter(|(name, _)| name == "append")
+            .map(|(_, fields)| fields)
+            .collect();
+        assert_eq!(appends.len(), 1);
+        assert_eq!(appends[0]["id"], frame.id.to_string());
+        assert_eq!(appends[0]["topic"], "topic");
+        assert_eq!(appends[0]["context_id"], ZERO_CONTEXT.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_read_max_rate() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        for _ in 0..5 {
+            store
+                .append(Frame::builder("test", ZERO_CONTEXT).build())
+                .unwrap();
+        }
+
+        let start = std::time::Instant::now();
+        let mut recver = store
+            .read(ReadOptions::builder().max_rate(20.0).build())
+            .await;
+        let mut count = 0;
+        while recver.recv().await.is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 5);
+        // the first frame goes straight out, then 4 intervals of 50ms
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_get_many() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let frames: Vec<_> = (0..3)
+            .map(|_| {
+                store
+                    .append(Frame::builder("test", ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+        let missing = scru128::new();
+
+        let got = store
+            .get_many(&[frames[2].id, missing, frames[0].id, frames[2].id])
+            .unwrap();
+        assert_eq!(
+            got,
+            vec![
+                Some(frames[2].clone()),
+                None,
+                Some(frames[0].clone()),
+                Some(frames[2].clone()),
+            ]
+        );
+        assert!(store.get_many(&[]).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_first_and_at() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        assert_eq!(store.first(None, ZERO_CONTEXT).unwrap(), None);
+
+        let a1 = store
+            .append(Frame::builder("a", ZERO_CONTEXT).build())
+            .unwrap();
+        let b1 = store
+            .append(Frame::builder("b", ZERO_CONTEXT).build())
+            .unwrap();
+        store
+            .append(Frame::builder("a", ZERO_CONTEXT).build())
+            .unwrap();
+        store
+            .append(Frame::builder("b", ZERO_CONTEXT).build())
+            .unwrap();
+
+        assert_eq!(store.first(None, ZERO_CONTEXT).unwrap(), Some(a1.clone()));
+        assert_eq!(
+            store.first(Some("a"), ZERO_CONTEXT).unwrap(),
+            Some(a1.clone())
+        );
+        assert_eq!(
+            store.first(Some("b"), ZERO_CONTEXT).unwrap(),
+            Some(b1.clone())
+        );
+        assert_eq!(store.first(Some("c"), ZERO_CONTEXT).unwrap(), None);
+
+        // once the oldest frame is gone, the next one is first
+        store.remove(&a1.id).unwrap();
+        assert_eq!(store.first(None, ZERO_CONTEXT).unwrap(), Some(b1.clone()));
+
+        assert_eq!(store.at(&b1.id).unwrap(), Some(b1));
+        assert_eq!(store.at(&a1.id).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_swept_on_startup() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+
+        let (expiring, kept, head) = {
+            let store = Store::new(path.clone());
+            // insert_frame writes as-is, without scheduling any gc, as if the store went down
+            // before it got around to it
+            let frame = |topic: &str, ttl: TTL| {
+                let frame = Frame::builder(topic, ZERO_CONTEXT)
+                    .id(scru128::new())
+                    .ttl(ttl)
+                    .build();
+                store.insert_frame(&frame).unwrap();
+                frame
+            };
+            let expiring = frame("expiring", TTL::Time(Duration::from_millis(1)));
+            let kept = frame("kept", TTL::Time(Duration::from_secs(3600)));
+            let head: Vec<_> = (0..3).map(|_| frame("head", TTL::Head(2))).collect();
+            (expiring, kept, head)
+        };
+
+        std::thread::sleep(Duration::from_millis(10));
+
+        let store = Store::new(path);
+        assert_eq!(store.get(&expiring.id).unwrap(), None);
+        assert_eq!(store.get(&kept.id).unwrap(), Some(kept));
+        assert_eq!(store.get(&head[0].id).unwrap(), None);
+        let remaining: Vec<_> = store
+            .read_sync(ReadOptions::builder().topic("head").build())
+            .collect();
+        assert_eq!(remaining, head[1..]);
+    }
+
+    #[tokio::test]
+    async fn test_audit_subscribers() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::with_options(
+            temp_dir.into_path(),
+            StoreOptions::builder().audit_subscribers(true).build(),
+        );
+
+        let options = ReadOptions::builder().follow(FollowOption::On).build();
+        let mut observer = store.read(options.clone()).await;
+        assert_eq!(observer.recv().await.unwrap().topic, "xs.threshold");
+
+        let follower = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .topic("test")
+                    .build(),
+            )
+            .await;
+
+        let connect = observer.recv().await.unwrap();
+        assert_eq!(connect.topic, "xs.connect");
+        let meta = connect.meta.unwrap();
+        assert_eq!(meta["options"], "follow=true&topic=test");
+
+        drop(follower);
+        let disconnect = timeout(Duration::from_secs(1), observer.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(disconnect.topic, "xs.disconnect");
+        assert_eq!(
+            disconnect.meta.unwrap()["subscriber_id"],
+            meta["subscriber_id"]
+        );
+
+        // audit frames are ephemeral
+        assert_eq!(store.read_sync(ReadOptions::default()).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_topic() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let append = |topic: &str| {
+            store
+                .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                .unwrap()
+        };
+
+        let error = append("logs.app.error");
+        append("metrics.cpu");
+        let warn = append("logs.app.warn");
+
+        let options = ReadOptions::builder()
+            .follow(FollowOption::On)
+            .topic("logs.*")
+            .build();
+
+        let history: Vec<_> = store.read_sync(options.clone()).collect();
+        assert_eq!(history, vec![error.clone(), warn.clone()]);
+
+        let last_n: Vec<_> = store
+            .read_sync(ReadOptions::builder().topic("logs.*").last_n(1).build())
+            .collect();
+        assert_eq!(last_n, vec![warn.clone()]);
+
+        let mut recver = store.read(options).await;
+        assert_eq!(recver.recv().await.unwrap(), error);
+        assert_eq!(recver.recv().await.unwrap(), warn);
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+
+        append("metrics.mem");
+        let info = append("logs.db.info");
+        assert_eq!(recver.recv().await.unwrap(), info);
+        assert_no_more_frames(&mut recver).await;
+    }
+
+    #[tokio::test]
+    async fn test_read_topics() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let append = |topic: &str| {
+            store
+                .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                .unwrap()
+        };
+
+        let a = append("a");
+        append("d");
+        let b = append("b");
+
+        let options = ReadOptions::builder()
+            .follow(FollowOption::On)
+            .topics(["a", "b", "c"].map(String::from).into())
+            .build();
+
+        let mut recver = store.read(options).await;
+        assert_eq!(recver.recv().await.unwrap(), a);
+        assert_eq!(recver.recv().await.unwrap(), b);
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+
+        let live: Vec<_> = ["d", "c", "a.b", "a", "ab", "b"]
+            .into_iter()
+            .map(append)
+            .collect();
+        for expected in [&live[1], &live[3], &live[5]] {
+            assert_eq!(&recver.recv().await.unwrap(), expected);
+        }
+        assert_no_more_frames(&mut recver).await;
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        use tokio_stream::StreamExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let frames: Vec<_> = (0..6)
+            .map(|i| {
+                store
+                    .append(Frame::builder(format!("topic{}", i % 2), ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+
+        let stream = store
+            .subscribe(ReadOptions::builder().follow(FollowOption::On).build())
+            .await;
+        assert_eq!(store.broadcast_tx.receiver_count(), 1);
+
+        let received: Vec<_> = stream
+            .filter(|frame| frame.topic == "topic1")
+            .take(3)
+            .collect()
+            .await;
+        assert_eq!(
+            received,
+            vec![frames[1].clone(), frames[3].clone(), frames[5].clone()]
+        );
+
+        // dropping the stream unsubscribes without waiting for another frame to be appended
+        timeout(Duration::from_secs(1), async {
+            while store.broadcast_tx.receiver_count() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_open_already_locked() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+
+        let store = Store::open(path.clone(), StoreOptions::default()).unwrap();
+        let clone = store.clone();
+
+        let Err(err) = Store::open(path.clone(), StoreOptions::default()) else {
+            panic!("opened a store that's already open");
+        };
+        assert!(
+            matches!(&err, StoreError::AlreadyLocked { path: locked } if *locked == path),
+            "unexpected error: {:?}",
+            err
+        );
+
+        // the lock is held until every clone is gone
+        drop(store);
+        assert!(Store::open(path.clone(), StoreOptions::default()).is_err());
+        drop(clone);
+        Store::open(path, StoreOptions::default()).unwrap();
+    }
+
+    #[test]
+    fn test_reopen_after_drop() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+
+        let store = Store::open(path.clone(), StoreOptions::default()).unwrap();
+        store
+            .append(
+                Frame::builder("test", ZERO_CONTEXT)
+                    .ttl(TTL::Head(1))
+                    .build(),
+            )
+            .unwrap();
+        // leaves a trim queued on the gc worker
+        let kept = store
+            .append(
+                Frame::builder("test", ZERO_CONTEXT)
+                    .ttl(TTL::Head(1))
+                    .build(),
+            )
+            .unwrap();
+
+        let mut old_rx = store.broadcast_tx.subscribe();
+
+        // the old gc worker finishes, and lets go of its handles, before the lock is released
+        drop(store);
+        assert!(matches!(
+            old_rx.try_recv(),
+            Err(tokio::sync::broadcast::error::TryRecvError::Closed)
+        ));
+        let store = Store::open(path, StoreOptions::default()).unwrap();
+        let frames: Vec<_> = store.read_sync(ReadOptions::default()).collect();
+        assert_eq!(frames, vec![kept]);
+
+        let frame = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(store.get(&frame.id).unwrap(), Some(frame));
+    }
+
+    #[test]
+    fn test_open_with_fjall_tuning() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+        let options = StoreOptions::builder()
+            .block_cache_bytes(1024 * 1024)
+            .write_buffer_bytes(2 * 1024 * 1024)
+            .compression(fjall::CompressionType::None)
+            .build();
+
+        let store = Store::open(path.clone(), options.clone()).unwrap();
+        assert_eq!(store.keyspace.config.block_cache.capacity(), 1024 * 1024);
+
+        let frame = store
+            .append(
+                Frame::builder("topic", ZERO_CONTEXT)
+                    .hash(store.cas_insert_sync("content").unwrap())
+                    .build(),
+            )
+            .unwrap();
+        assert_eq!(store.get(&frame.id).unwrap(), Some(frame.clone()));
+        assert_eq!(
+            store.head("topic", ZERO_CONTEXT).unwrap(),
+            Some(frame.clone())
+        );
+
+        // the frames are still there when reopened
+        drop(store);
+        let store = Store::open(path, options).unwrap();
+        assert_eq!(
+            store.read_sync(Default::default()).collect::<Vec<_>>(),
+            [frame]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_since_stream() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let append = |topic: &str| {
+            store
+                .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                .unwrap()
+        };
+
+        let last_seen = append("seen");
+        let history: Vec<_> = (0..3).map(|_| append("history")).collect();
+
+        let mut stream = store.since_stream(last_seen.id, None).await;
+        for expected in &history {
+            assert_eq!(&stream.next().await.unwrap(), expected);
+        }
+        assert_eq!(stream.next().await.unwrap().topic, "xs.threshold");
+
+        let live: Vec<_> = (0..3).map(|_| append("live")).collect();
+        for expected in &live {
+            assert_eq!(&stream.next().await.unwrap(), expected);
+        }
+
+        // caught up already: the threshold still marks the switch
+        let mut stream = store
+            .since_stream(live[2].id, Some(Duration::from_millis(200)))
+            .await;
+        assert_eq!(stream.next().await.unwrap().topic, "xs.threshold");
+        let frame = append("live");
+        let next = stream.next().await.unwrap();
+        assert_eq!(next, frame);
+        assert_eq!(stream.next().await.unwrap().topic, "xs.pulse");
+    }
+
+    #[tokio::test]
+    async fn test_read_with_boundary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let history: Vec<_> = ["a", "b"]
+            .into_iter()
+            .map(|topic| {
+                store
+                    .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+
+        // The callback appends a frame of its own, which has to be the first live frame
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut recver = {
+            let appender = store.clone();
+            let calls = calls.clone();
+            store
+                .read_with_boundary(
+                    ReadOptions::builder().follow(FollowOption::On).build(),
+                    move || {
+                        calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        appender
+                            .append(Frame::builder("caught-up", ZERO_CONTEXT).build())
+                            .unwrap();
+                    },
+                )
+                .await
+        };
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(recver.recv().await.unwrap());
+        }
+        assert_eq!(received[..2], history);
+        assert_eq!(received[2].topic, "caught-up");
+
+        let live = store
+            .append(Frame::builder("c", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(recver.recv().await.unwrap(), live);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_no_more_frames(&mut recver).await;
+
+        // Without following, the reader is caught up once history runs out
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut recver = store
+            .read_with_boundary(ReadOptions::default(), move || tx.send(()).unwrap())
+            .await;
+        let mut count = 0;
+        while recver.recv().await.is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 4);
+        rx.await.unwrap();
+
+        // Frames are delivered on whatever topic they're on, xs.threshold included
+        let user_threshold = store
+            .append(Frame::builder("xs.threshold", ZERO_CONTEXT).build())
+            .unwrap();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut recver = store
+            .read_with_boundary(
+                ReadOptions::builder().last_id(LastId::Id(live.id)).build(),
+                move || tx.send(()).unwrap(),
+            )
+            .await;
+        assert_eq!(recver.recv().await.unwrap(), user_threshold);
+        assert!(recver.recv().await.is_none());
+        rx.await.unwrap();
+
+        // A follow with a limit is caught up once its history is, if that's within the limit
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut recver = store
+            .read_with_boundary(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .last_id(LastId::Id(live.id))
+                    .limit(2)
+                    .build(),
+                move || tx.send(()).unwrap(),
+            )
+            .await;
+        assert_eq!(recver.recv().await.unwrap(), user_threshold);
+        rx.recv().await.unwrap();
+        let live = store
+            .append(Frame::builder("d", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(recver.recv().await.unwrap(), live);
+        assert!(recver.recv().await.is_none());
+        assert!(rx.recv().await.is_none());
+
+        // but not if the limit is reached first
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut recver = store
+            .read_with_boundary(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .limit(1)
+                    .build(),
+                move || tx.send(()).unwrap(),
+            )
+            .await;
+        assert_eq!(recver.recv().await.unwrap(), history[0]);
+        assert!(recver.recv().await.is_none());
+        assert!(rx.recv().await.is_none());
+
+        // A tail has no history, so it's caught up straight away
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut recver = store
+            .read_with_boundary(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .tail(true)
+                    .build(),
+                move || tx.send(()).unwrap(),
+            )
+            .await;
+        rx.await.unwrap();
+        let live = store
+            .append(Frame::builder("e", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(recver.recv().await.unwrap(), live);
+        assert_no_more_frames(&mut recver).await;
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        use tokio::io::AsyncWriteExt;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        assert_eq!(store.metrics(), Metrics::default());
+
+        store
+            .append_blocking(Frame::builder("a", ZERO_CONTEXT).build(), Some(b"12345"))
+            .unwrap();
+        store
+            .append_batch(vec![
+                Frame::builder("b", ZERO_CONTEXT).build(),
+                Frame::builder("c", ZERO_CONTEXT)
+                    .ttl(TTL::Ephemeral)
+                    .build(),
+            ])
+            .unwrap();
+        let mut writer = store.cas_writer().await.unwrap();
+        writer.write_all(b"123").await.unwrap();
+        writer.commit().await.unwrap();
+
+        let mut recver = store.read(ReadOptions::default()).await;
+        while recver.recv().await.is_some() {}
+        let mut follower = store
+            .read(ReadOptions::builder().follow(FollowOption::On).build())
+            .await;
+        for _ in 0..3 {
+            follower.recv().await.unwrap(); // a, b and xs.threshold
+        }
+        store
+            .append(Frame::builder("d", ZERO_CONTEXT).build())
+            .unwrap();
+        follower.recv().await.unwrap();
+
+        assert_eq!(
+            store.metrics(),
+            Metrics {
+                appends: 4,
+                cas_bytes_written: 8,
+                reads: 2,
+                frames_delivered: 5,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_append_async_content() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let mut follower = store
+            .read(ReadOptions::builder().follow(FollowOption::On).build())
+            .await;
+        assert_eq!(follower.recv().await.unwrap().topic, "xs.threshold");
+
+        let provisional = store
+            .append_async_content(
+                &ThreadPool::new(1),
+                Frame::builder("a", ZERO_CONTEXT).build(),
+                b"content".to_vec(),
+            )
+            .unwrap();
+        assert_eq!(provisional.hash, None);
+
+        // Followers see the frame again once its content is committed
+        assert_eq!(follower.recv().await.unwrap(), provisional);
+        let updated = timeout(Duration::from_secs(5), follower.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.id, provisional.id);
+        assert_eq!(updated.seq, provisional.seq);
+        let hash = updated.hash.clone().unwrap();
+        assert_eq!(hash, ssri::Integrity::from(b"content"));
+        assert_eq!(store.cas_read(&hash).await.unwrap(), b"content");
+
+        // and so do reads
+        assert_eq!(store.get(&provisional.id).unwrap(), Some(updated.clone()));
+        let frames: Vec<_> = store.read_sync(ReadOptions::default()).collect();
+        assert_eq!(frames, vec![updated]);
+
+        // A frame removed while its content is being committed stays removed, however the
+        // removal and filling in its hash interleave
+        let pool = ThreadPool::new(1);
+        for i in 0..100 {
+            let removed = store
+                .append_async_content(
+                    &pool,
+                    Frame::builder("b", ZERO_CONTEXT).build(),
+                    format!("removed {}", i).into_bytes(),
+                )
+                .unwrap();
+            let remover = {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(Duration::from_micros(i * 10));
+                    store.remove(&removed.id).unwrap();
+                })
+            };
+            remover.join().unwrap();
+            // The pool's only worker takes this once it's done filling in the hash
+            pool.execute_with_result(|| ()).recv().unwrap().unwrap();
+            assert_eq!(store.get(&removed.id).unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_count() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        assert_eq!(store.subscriber_count(), 0);
+
+        let recver = store
+            .read(ReadOptions::builder().follow(FollowOption::On).build())
+            .await;
+        assert_eq!(store.subscriber_count(), 1);
+        // Reads that don't follow don't subscribe
+        let _history = store.read(ReadOptions::default()).await;
+        assert_eq!(store.subscriber_count(), 1);
+
+        // Unsubscribes without waiting for another frame to be appended
+        drop(recver);
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while store.subscriber_count() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("follower wasn't unsubscribed");
+    }
+
+    #[tokio::test]
+    async fn test_publish() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let ping = || {
+            Frame::builder("ping", ZERO_CONTEXT)
+                .ttl(TTL::Ephemeral)
+                .build()
+        };
+
+        // No one was listening
+        let (_, receivers) = store.publish(ping()).unwrap();
+        assert_eq!(receivers, 0);
+
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .tail(true)
+                    .build(),
+            )
+            .await;
+        let (frame, receivers) = store.publish(ping()).unwrap();
+        assert_eq!(receivers, 1);
+        assert_eq!(recver.recv().await.unwrap(), frame);
+    }
+
+    #[tokio::test]
+    async fn test_watch_topic() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let append = |topic: &str| {
+            store
+                .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                .unwrap()
+        };
+
+        let (head, _) = store.watch_topic("status", ZERO_CONTEXT).await.unwrap();
+        assert_eq!(head, None);
+
+        let _ = append("status");
+        let current = append("status");
+        let (head, mut stream) = store.watch_topic("status", ZERO_CONTEXT).await.unwrap();
+        assert_eq!(head, Some(current));
+        assert_eq!(head, store.head("status", ZERO_CONTEXT).unwrap());
+
+        let _ = append("other");
+        let update = append("status");
+        assert_eq!(stream.next().await.unwrap(), update);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_subscribe_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let append = |store: &Store, n: usize| {
+            store
+                .append(
+                    Frame::builder(format!("topic{}", n % 5), ZERO_CONTEXT)
+                        .meta(serde_json::json!({"n": n}))
+                        .build(),
+                )
+                .unwrap()
+        };
+        for n in 0..50 {
+            append(&store, n);
+        }
+
+        // keep appending while subscribing
+        let appender = std::thread::spawn({
+            let store = store.clone();
+            move || {
+                for n in 50..500 {
+                    append(&store, n);
+                }
+            }
+        });
+        let (snapshot, mut stream) = store.subscribe_snapshot(None).await.unwrap();
+        appender.join().unwrap();
+        let done = store
+            .append(Frame::builder("done", ZERO_CONTEXT).build())
+            .unwrap();
+
+        let mut state: HashMap<String, Frame> = HashMap::new();
+        let mut seen = HashSet::new();
+        for frame in snapshot {
+            assert!(seen.insert(frame.id));
+            state.insert(frame.topic.clone(), frame);
+        }
+        loop {
+            let frame = stream.next().await.unwrap();
+            assert!(seen.insert(frame.id), "delivered twice: {:?}", frame);
+            let is_done = frame.id == done.id;
+            state.insert(frame.topic.clone(), frame);
+            if is_done {
+                break;
+            }
+        }
+
+        // the same as compacting the whole stream
+        let mut compacted: HashMap<String, Frame> = HashMap::new();
+        for frame in store.read_sync(ReadOptions::default()) {
+            compacted.insert(frame.topic.clone(), frame);
+        }
+        assert_eq!(state, compacted);
+
+        // with a context, only its frames
+        let context = store
+            .append(Frame::builder("xs.context", ZERO_CONTEXT).build())
+            .unwrap();
+        store
+            .append(Frame::builder("topic0", context.id).build())
+            .unwrap();
+        let latest = store
+            .append(Frame::builder("topic0", context.id).build())
+            .unwrap();
+        let (snapshot, mut stream) = store.subscribe_snapshot(Some(context.id)).await.unwrap();
+        assert_eq!(snapshot, [latest]);
+        append(&store, 0);
+        let live = store
+            .append(Frame::builder("topic1", context.id).build())
+            .unwrap();
+        assert_eq!(stream.next().await.unwrap(), live);
+    }
+
+    #[tokio::test]
+    async fn test_rename_topic() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let frames: Vec<_> = (0..2)
+            .map(|_| {
+                store
+                    .append(Frame::builder("old", ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+        let other = store
+            .append(Frame::builder("other", ZERO_CONTEXT).build())
+            .unwrap();
+
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .tail(true)
+                    .build(),
+            )
+            .await;
+
+        assert_eq!(store.rename_topic("old", "new").await.unwrap(), 2);
+        assert_eq!(store.head("old", ZERO_CONTEXT).unwrap(), None);
+        let head = store.head("new", ZERO_CONTEXT).unwrap().unwrap();
+        assert_eq!(head.id, frames[1].id);
+        assert_eq!(head.seq, frames[1].seq);
+        assert_eq!(head.topic, "new");
+        assert_eq!(store.get(&frames[0].id).unwrap().unwrap().topic, "new");
+        assert_eq!(store.topics(), ["new", "other"]);
+        assert_eq!(store.head("other", ZERO_CONTEXT).unwrap(), Some(other));
+
+        let notice = recver.recv().await.unwrap();
+        assert_eq!(notice.topic, "xs.rename");
+        assert_eq!(
+            notice.meta,
+            Some(serde_json::json!({"from": "old", "to": "new", "count": 2}))
+        );
+
+        // topic filters pick up the new name
+        let renamed: Vec<_> = store
+            .read_sync(ReadOptions::builder().topic("new").build())
+            .map(|frame| frame.id)
+            .collect();
+        assert_eq!(renamed, [frames[0].id, frames[1].id]);
+
+        assert_eq!(store.rename_topic("missing", "new").await.unwrap(), 0);
+        assert!(store.rename_topic("xs.context", "new").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_clear() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let append = |topic: &str, content: &str| {
+            let hash = store.cas_insert_sync(content).unwrap();
+            store
+                .append(Frame::builder(topic, ZERO_CONTEXT).hash(hash).build())
+                .unwrap()
+        };
+
+        let shared = append("a", "shared");
+        append("a", "only-a");
+        let kept = append("b", "shared");
+
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .tail(true)
+                    .build(),
+            )
+            .await;
+
+        // only topic a: its shared content is still used by b
+        let dropped = store.clear(Some("a")).unwrap();
+        assert_eq!(dropped, vec![store.cas_insert_sync("only-a").unwrap()]);
+        assert_eq!(store.head("a", ZERO_CONTEXT).unwrap(), None);
+        assert_eq!(
+            store.read_sync(ReadOptions::default()).collect::<Vec<_>>(),
+            vec![kept.clone()]
+        );
+        let notice = recver.recv().await.unwrap();
+        assert_eq!(notice.topic, "xs.clear");
+        assert_eq!(notice.meta, Some(serde_json::json!({"topic": "a"})));
+
+        // everything, other than contexts
+        let context = store
+            .append(Frame::builder("xs.context", ZERO_CONTEXT).build())
+            .unwrap();
+        append("c", "c");
+        let dropped: HashSet<_> = store.clear(None).unwrap().into_iter().collect();
+        assert_eq!(
+            dropped,
+            HashSet::from([shared.hash.unwrap(), store.cas_insert_sync("c").unwrap()])
+        );
+        assert_eq!(store.head("b", ZERO_CONTEXT).unwrap(), None);
+        assert_eq!(
+            store.read_sync(ReadOptions::default()).collect::<Vec<_>>(),
+            vec![context]
+        );
+    }
+
+    #[test]
+    fn test_drain_to_writer() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let context = store
+            .append(Frame::builder("xs.context", ZERO_CONTEXT).build())
+            .unwrap();
+        let frames: Vec<_> = (0..6)
+            .map(|i| {
+                let hash = store.cas_insert_sync(format!("content {}", i % 4)).unwrap();
+                store
+                    .append(Frame::builder("stream", ZERO_CONTEXT).hash(hash).build())
+                    .unwrap()
+            })
+            .collect();
+
+        let mut archive = Vec::new();
+        let dropped = store
+            .drain_to_writer(&mut archive, Some(frames[2].id))
+            .unwrap();
+
+        let archived: Vec<Frame> = archive
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(archived, frames[..3]);
+        // content 0 and 1 are still used by the frames left behind
+        assert_eq!(dropped, vec![frames[2].hash.clone().unwrap()]);
+
+        let mut remaining = vec![context];
+        remaining.extend_from_slice(&frames[3..]);
+        assert_eq!(
+            store.read_sync(ReadOptions::default()).collect::<Vec<_>>(),
+            remaining
+        );
+        for frame in &frames[..3] {
+            assert_eq!(store.get(&frame.id).unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compact_latest_per_topic() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+
+        let (kept, expected_dropped) = {
+            let store = Store::new(path.clone());
+            let context = store
+                .append(Frame::builder("xs.context", ZERO_CONTEXT).build())
+                .unwrap();
+
+            let shared = store.cas_insert("shared").await.unwrap();
+            let append = |topic: &str, context_id, hash: ssri::Integrity| {
+                store
+                    .append(Frame::builder(topic, context_id).hash(hash).build())
+                    .unwrap()
+            };
+
+            let a1 = append("a", ZERO_CONTEXT, store.cas_insert("a1").await.unwrap());
+            let b1 = append("b", ZERO_CONTEXT, shared.clone());
+            let a2 = append("a", ZERO_CONTEXT, store.cas_insert("a2").await.unwrap());
+            let a_other = append("a", context.id, store.cas_insert("a3").await.unwrap());
+            let b2 = append("b", ZERO_CONTEXT, shared.clone());
+            let a3 = append("a", ZERO_CONTEXT, shared);
+
+            let mut dropped = store
+                .compact(CompactStrategy::LatestPerTopic)
+                .await
+                .unwrap();
+            dropped.sort_by_key(|hash| hash.to_string());
+            let mut expected = vec![a1.hash.unwrap(), a2.hash.unwrap()];
+            expected.sort_by_key(|hash| hash.to_string());
+            assert_eq!(dropped, expected);
+            assert_eq!(b1.hash, b2.hash);
+
+            (vec![context, a_other, b2, a3], expected)
+        };
+
+        // what's left is on disk, not just hidden from readers
+        let store = Store::new(path);
+        let mut remaining: Vec<_> = store.read_sync(ReadOptions::default()).collect();
+        remaining.sort_by_key(|frame| frame.id);
+        assert_eq!(remaining, kept);
+        assert_eq!(store.frame_partition.len().unwrap(), kept.len());
+        assert_eq!(store.idx_topic.len().unwrap(), kept.len());
+        assert_eq!(store.idx_context.len().unwrap(), kept.len());
+
+        // the dropped content is still there, for the caller to collect
+        for hash in expected_dropped {
+            assert!(store.cas_exists(&hash).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_tombstones() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::with_options(
+            temp_dir.into_path(),
+            StoreOptions::builder().tombstones(true).build(),
+        );
+        let append = |topic: &str| {
+            store
+                .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                .unwrap()
+        };
+
+        let a1 = append("a");
+        let a2 = append("a");
+        let b1 = append("b");
+        let b2 = append("b");
+        store.remove(&a2.id).unwrap();
+        store.remove(&b1.id).unwrap();
+
+        // Tombstones are skipped unless asked for
+        let frames: Vec<_> = store.read_sync(ReadOptions::default()).collect();
+        assert_eq!(frames, [a1.clone(), b2.clone()]);
+        assert_eq!(store.latest_n(1, None), vec![b2.clone()]);
+        assert_eq!(store.latest_n(10, Some("xs.*")), []);
+        assert_eq!(store.range(None, None, None), [a1.clone(), b2.clone()]);
+        assert_eq!(store.fold(0, |count, _| count + 1), 2);
+
+        let options = ReadOptions::builder().include_tombstones(true).build();
+        let frames: Vec<_> = store.read_sync(options.clone()).collect();
+        let tombstones: Vec<_> = frames[2..]
+            .iter()
+            .map(|frame| (frame.topic.as_str(), frame.meta.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            tombstones,
+            [
+                (
+                    "xs.remove",
+                    serde_json::json!({"id": a2.id.to_string(), "topic": "a"})
+                ),
+                (
+                    "xs.remove",
+                    serde_json::json!({"id": b1.id.to_string(), "topic": "b"})
+                ),
+            ]
+        );
+        assert_eq!(
+            ReadOptions::from_query(Some("include-tombstones=true")).unwrap(),
+            options
+        );
+
+        // and delivered live along with the rest
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .tail(true)
+                    .include_tombstones(true)
+                    .build(),
+            )
+            .await;
+        store.remove(&a1.id).unwrap();
+        let tombstone = recver.recv().await.unwrap();
+        assert_eq!(tombstone.topic, "xs.remove");
+        assert_eq!(
+            tombstone.meta,
+            Some(serde_json::json!({"id": a1.id.to_string(), "topic": "a"}))
+        );
+
+        // A topic whose latest frame was removed compacts away, rather than back to the frame
+        // before it. Removing an older frame doesn't suppress a newer one.
+        let a3 = append("a");
+        let a4 = append("a");
+        store.remove(&a4.id).unwrap();
+        let (snapshot, _) = store.subscribe_snapshot(None).await.unwrap();
+        let topics: Vec<_> = snapshot.iter().map(|frame| frame.topic.as_str()).collect();
+        assert_eq!(topics, ["b", "xs.remove"]);
+
+        store
+            .compact(CompactStrategy::LatestPerTopic)
+            .await
+            .unwrap();
+        assert_eq!(store.get(&a3.id).unwrap(), None);
+        let frames: Vec<_> = store.read_sync(ReadOptions::default()).collect();
+        assert_eq!(frames, vec![b2.clone()]);
+
+        // Removing a tombstone doesn't leave another
+        let tombstones: Vec<_> = store.read_sync(options.clone()).skip(1).collect();
+        for tombstone in &tombstones {
+            store.remove(&tombstone.id).unwrap();
+        }
+        let frames: Vec<_> = store.read_sync(options).collect();
+        assert_eq!(frames, [b2]);
+
+        // Without the option, removals leave nothing behind
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+        let frame = store
+            .append(Frame::builder("a", ZERO_CONTEXT).build())
+            .unwrap();
+        store.remove(&frame.id).unwrap();
+        let options = ReadOptions::builder().include_tombstones(true).build();
+        assert_eq!(store.read_sync(options).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_tail_stream() {
+        use tokio_stream::StreamExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        for _ in 0..3 {
+            store
+                .append(Frame::builder("before", ZERO_CONTEXT).build())
+                .unwrap();
+        }
+
+        let mut stream = store.tail_stream().await;
+
+        let after: Vec<_> = (0..3)
+            .map(|_| {
+                store
+                    .append(Frame::builder("after", ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+
+        let received: Vec<_> = (&mut stream).take(3).collect().await;
+        assert_eq!(received, after);
+
+        // nothing else is waiting: no history and no threshold
+        assert!(timeout(Duration::from_millis(50), stream.next())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_reads_unsubscribe() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        store
+            .append(Frame::builder("history", ZERO_CONTEXT).build())
+            .unwrap();
+
+        for i in 0..50 {
+            let follow = if i % 2 == 0 {
+                FollowOption::On
+            } else {
+                FollowOption::WithHeartbeat(Duration::from_millis(5))
+            };
+            let options = ReadOptions::builder()
+                .follow(follow)
+                .tail(i % 3 == 0)
+                .build();
+            let recver = store.read(options).await;
+            drop(recver);
+        }
+
+        // no appends: each reader's subscription should be dropped as soon as it goes away
+        timeout(Duration::from_secs(1), async {
+            while store.broadcast_tx.receiver_count() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_last_id_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        for _ in 0..10 {
+            store
+                .append(Frame::builder("before", ZERO_CONTEXT).build())
+                .unwrap();
+        }
+
+        // keep appending while the read starts
+        let appender = {
+            let store = store.clone();
+            std::thread::spawn(move || {
+                (0..200)
+                    .map(|_| {
+                        store
+                            .append(Frame::builder("during", ZERO_CONTEXT).build())
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let mut recver = store
+            .read(
+                ReadOptions::builder()
+                    .follow(FollowOption::On)
+                    .last_id(LastId::Head)
+                    .build(),
+            )
+            .await;
+
+        let appended = appender.join().unwrap();
+        let mut received = Vec::new();
+        loop {
+            let frame = timeout(Duration::from_secs(1), recver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if frame.topic == "xs.threshold" {
+                continue;
+            }
+            let done = frame.id == appended.last().unwrap().id;
+            received.push(frame);
+            if done {
+                break;
+            }
+        }
+
+        // what's received is exactly the tail of what was appended: no gaps, no repeats, and
+        // nothing from before the read
+        assert!(!received.is_empty());
+        assert_eq!(received[..], appended[appended.len() - received.len()..]);
+        assert_no_more_frames(&mut recver).await;
+    }
+
+    #[tokio::test]
+    async fn test_append_batch() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let mut recver = store
+            .read(ReadOptions::builder().follow(FollowOption::On).build())
+            .await;
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+
+        let frames = store
+            .append_batch(
+                (0..100)
+                    .map(|i| {
+                        Frame::builder("batch", ZERO_CONTEXT)
+                            .meta(serde_json::json!({"i": i}))
+                            .build()
+                    })
+                    .collect(),
+            )
+            .unwrap();
+
+        // ids are strictly increasing, in submission order
+        assert_eq!(frames.len(), 100);
+        assert!(frames.windows(2).all(|w| w[0].id < w[1].id));
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.meta, Some(serde_json::json!({"i": i})));
+        }
+
+        // everything was persisted
+        let stored: Vec<Frame> = store.read_sync(ReadOptions::default()).collect();
+        assert_eq!(stored, frames);
+
+        // followers see the batch contiguously
+        for frame in &frames {
+            assert_eq!(Some(frame), recver.recv().await.as_ref());
+        }
+        assert_no_more_frames(&mut recver).await;
+
+        // an invalid frame rejects the entire batch
+        let result = store.append_batch(vec![
+            Frame::builder("batch", ZERO_CONTEXT).build(),
+            Frame::builder("batch", scru128::new()).build(),
+        ]);
+        assert!(result.is_err());
+        assert_eq!(store.read_sync(ReadOptions::default()).count(), 100);
+    }
+
+    #[test]
+    fn test_read_sync() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        // Append three frames
+        let frame1 = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        let frame2 = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        let frame3 = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+
+        // Test reading all frames
+        let frames: Vec<Frame> = store
+            .read_sync(
+                ReadOptions::builder()
+                    .context_id(crate::store::ZERO_CONTEXT)
+                    .build(),
+            )
+            .collect();
+        assert_eq!(vec![frame1.clone(), frame2.clone(), frame3.clone()], frames);
+
+        // Test with last_id (passing Scru128Id directly)
+        let frames: Vec<Frame> = store
+            .read_sync(
+                ReadOptions::builder()
+                    .last_id(frame1.id)
+                    .context_id(crate::store::ZERO_CONTEXT)
+                    .build(),
+            )
+            .collect();
+        assert_eq!(vec![frame2.clone(), frame3.clone()], frames);
+
+        // Test with limit
+        let frames: Vec<Frame> = store
+            .read_sync(
+                ReadOptions::builder()
+                    .limit(2)
+                    .context_id(crate::store::ZERO_CONTEXT)
+                    .build(),
+            )
+            .collect();
+        assert_eq!(vec![frame1, frame2], frames);
+    }
+
+    #[tokio::test]
+    async fn test_read_sync_matches_read() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let frames: Vec<Frame> = (0..10)
+            .map(|_| {
+                store
+                    .append(Frame::builder("test", ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+
+        let cases = [
+            ReadOptions::default(),
+            ReadOptions::builder().last_id(frames[3].id).build(),
+            ReadOptions::builder().limit(4).build(),
+            ReadOptions::builder().last_n(3).build(),
+            ReadOptions::builder().tail(true).build(),
+        ];
+
+        for options in cases {
+            let recver = store.read(options.clone()).await;
+            let expected = tokio_stream::wrappers::ReceiverStream::new(recver)
+                .collect::<Vec<Frame>>()
+                .await;
+            let got: Vec<Frame> = store.read_sync(options.clone()).collect();
+            assert_eq!(got, expected, "options: {:?}", options);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_buffers() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::with_options(
+            temp_dir.into_path(),
+            StoreOptions::builder()
+                .broadcast_buffer(512)
+                .subscriber_buffer(1)
+                .build(),
+        );
+
+        let mut recver = store
+            .read(ReadOptions::builder().follow(FollowOption::On).build())
+            .await;
+        assert_eq!(recver.recv().await.unwrap().topic, "xs.threshold");
+
+        // a burst of appends, faster than the follower drains them
+        let appender = {
+            let store = store.clone();
+            tokio::task::spawn_blocking(move || {
+                (0..500)
+                    .map(|_| {
+                        store
+                            .append(Frame::builder("burst", ZERO_CONTEXT).build())
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let received = timeout(Duration::from_secs(10), async {
+            let mut received = Vec::new();
+            while received.len() < 500 {
+                received.push(recver.recv().await.unwrap());
+            }
+            received
+        })
+        .await
+        .expect("burst of appends deadlocked");
+
+        assert_eq!(received, appender.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cas_compression() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::with_options(
+            temp_dir.into_path(),
+            StoreOptions::builder().compress_cas(true).build(),
+        );
+
+        let content = "the quick brown fox jumps over the lazy dog\n".repeat(1000);
+        let expected = ssri::Integrity::from(&content);
+
+        // The hash describes the original content, not the compressed bytes
+        let hash = store.cas_insert(&content).await.unwrap();
+        assert_eq!(hash, expected);
+        assert_eq!(store.cas_read(&hash).await.unwrap(), content.as_bytes());
+        assert_eq!(store.cas_read_sync(&hash).unwrap(), content.as_bytes());
+
+        let mut got = String::new();
+        let mut reader = store.cas_reader(hash.clone()).await.unwrap();
+        reader.read_to_string(&mut got).await.unwrap();
+        assert_eq!(got, content);
+
+        // Streaming writes produce the same hash, however the content is split up
+        let mut writer = store.cas_writer().await.unwrap();
+        for chunk in content.as_bytes().chunks(1000) {
+            writer.write_all(chunk).await.unwrap();
+        }
+        assert_eq!(writer.commit().await.unwrap(), expected);
+        let mut writer = store.cas_writer_sync().unwrap();
+        for chunk in content.as_bytes().chunks(1000) {
+            std::io::Write::write_all(&mut writer, chunk).unwrap();
+        }
+        assert_eq!(writer.commit().unwrap(), expected);
+
+        let mut got = String::new();
+        let mut reader = store.cas_reader_sync(hash.clone()).unwrap();
+        std::io::Read::read_to_string(&mut reader, &mut got).unwrap();
+        assert_eq!(got, content);
+
+        // The stored blob is actually smaller than the original
+        let stored: u64 = walk_size(&store.path.join("cacache").join("content-v2"));
+        assert!(stored < content.len() as u64 / 10);
+
+        // Content written before compression was enabled is still readable
+        let hash = cacache::write_hash(store.path.join("cacache"), "uncompressed")
+            .await
+            .unwrap();
+        assert_eq!(store.cas_read(&hash).await.unwrap(), b"uncompressed");
+
+        // Listed once each, under the hash of the original content
+        let mut listed: Vec<_> = store
+            .cas_list()
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.hash, entry.compressed, entry.size))
+            .collect();
+        listed.sort_by_key(|(_, compressed, _)| *compressed);
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0], (hash, false, 12));
+        assert_eq!(listed[1].0, expected);
+        assert!(listed[1].1);
+        assert!(listed[1].2 < content.len() as u64 / 10);
+    }
+
+    #[tokio::test]
+    async fn test_cas_algorithm() {
+        use tokio::io::AsyncWriteExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+        let algorithm_of = |hash: &ssri::Integrity| hash.hashes[0].algorithm;
+
+        let store = Store::with_options(
+            path.clone(),
+            StoreOptions::builder()
+                .cas_algorithm(ssri::Algorithm::Sha512)
+                .build(),
+        );
+        let sha512 = store
+            .append_blocking(Frame::builder("a", ZERO_CONTEXT).build(), Some(b"sha512"))
+            .unwrap()
+            .hash
+            .unwrap();
+        assert_eq!(algorithm_of(&sha512), ssri::Algorithm::Sha512);
+        let mut writer = store.cas_writer().await.unwrap();
+        writer.write_all(b"streamed").await.unwrap();
+        assert_eq!(
+            algorithm_of(&writer.commit().await.unwrap()),
+            ssri::Algorithm::Sha512
+        );
+        drop(store);
+
+        // Compressed content is hashed with the chosen algorithm too, and content stored under
+        // another algorithm still reads back
+        let store = Store::with_options(
+            path,
+            StoreOptions::builder()
+                .cas_algorithm(ssri::Algorithm::Sha256)
+                .compress_cas(true)
+                .build(),
+        );
+        let frame = store
+            .append_blocking(Frame::builder("b", ZERO_CONTEXT).build(), Some(b"sha256"))
+            .unwrap();
+        let sha256 = frame.hash.unwrap();
+        assert_eq!(algorithm_of(&sha256), ssri::Algorithm::Sha256);
+        assert_eq!(sha256, ssri::Integrity::from(b"sha256"));
+        assert_eq!(store.cas_read(&sha256).await.unwrap(), b"sha256");
+        assert_eq!(store.cas_read(&sha512).await.unwrap(), b"sha512");
+    }
+
+    #[tokio::test]
+    async fn test_cas_read_integrity() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
 
-        // We should only get exactly 3 frames, even though follow is enabled
-        // and there are 5 frames available
-        assert_eq!(Some(frame1), rx.recv().await);
-        assert_eq!(Some(frame2), rx.recv().await);
-        assert_eq!(Some(frame3), rx.recv().await);
+        let hash = store.cas_insert("original content").await.unwrap();
+
+        // corrupt the blob on disk, keeping its length
+        let blobs = walk_files(&store.path.join("cacache").join("content-v2"));
+        assert_eq!(blobs.len(), 1);
+        let mut perms = std::fs::metadata(&blobs[0]).unwrap().permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        perms.set_readonly(false);
+        std::fs::set_permissions(&blobs[0], perms).unwrap();
+        std::fs::write(&blobs[0], "corrupt content!").unwrap();
+
+        assert!(matches!(
+            store.cas_read(&hash).await,
+            Err(StoreError::Cas(cacache::Error::IntegrityError(_)))
+        ));
+        assert!(matches!(
+            store.cas_read_sync(&hash),
+            Err(StoreError::Cas(cacache::Error::IntegrityError(_)))
+        ));
+
+        // and so do readers, once they get to the end
+        let mut reader = store.cas_reader(hash.clone()).await.unwrap();
+        let err = tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut Vec::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let mut reader = store.cas_reader_sync(hash).unwrap();
+        let err = std::io::Read::read_to_end(&mut reader, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
 
-        // This should complete quickly if the channel is actually closed
-        assert_eq!(
-            Ok(None),
-            timeout(Duration::from_millis(100), rx.recv()).await,
-            "Channel should be closed after limit"
+    #[tokio::test]
+    async fn test_cas_reader_integrity_compressed() {
+        use tokio::io::AsyncReadExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::with_options(
+            temp_dir.into_path(),
+            StoreOptions::builder().compress_cas(true).build(),
         );
+        let hash = store.cas_insert("original content").await.unwrap();
+
+        // Swap the compressed blob for valid compressed content that isn't what was hashed
+        let blobs = walk_files(&store.path.join("cacache").join("content-v2"));
+        assert_eq!(blobs.len(), 1);
+        let mut perms = std::fs::metadata(&blobs[0]).unwrap().permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        perms.set_readonly(false);
+        std::fs::set_permissions(&blobs[0], perms).unwrap();
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b"tampered content").unwrap();
+        std::fs::write(&blobs[0], encoder.finish().unwrap()).unwrap();
+
+        // Readers only find out once they get to the end
+        let mut reader = store.cas_reader(hash.clone()).await.unwrap();
+        let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let mut reader = store.cas_reader_sync(hash).unwrap();
+        let err = std::io::Read::read_to_end(&mut reader, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
     }
 
-    #[test]
-    fn test_read_sync() {
+    #[tokio::test]
+    async fn test_store_errors() {
         let temp_dir = TempDir::new().unwrap();
         let store = Store::new(temp_dir.into_path());
 
-        // Append three frames
-        let frame1 = store
-            .append(Frame::builder("test", ZERO_CONTEXT).build())
-            .unwrap();
-        let frame2 = store
-            .append(Frame::builder("test", ZERO_CONTEXT).build())
-            .unwrap();
-        let frame3 = store
-            .append(Frame::builder("test", ZERO_CONTEXT).build())
+        // a frame that's been corrupted on disk
+        let id = scru128::new();
+        store
+            .frame_partition
+            .insert(id.as_bytes(), b"{not json")
             .unwrap();
+        assert!(matches!(store.get(&id), Err(StoreError::Serde(_))));
+        assert!(matches!(store.remove(&id), Err(StoreError::Serde(_))));
 
-        // Test reading all frames
-        let frames: Vec<Frame> = store
-            .read_sync(None, None, Some(crate::store::ZERO_CONTEXT))
-            .collect();
-        assert_eq!(vec![frame1.clone(), frame2.clone(), frame3.clone()], frames);
+        assert!(matches!(
+            store.append(Frame::builder("test", scru128::new()).build()),
+            Err(StoreError::InvalidFrame(_))
+        ));
 
-        // Test with last_id (passing Scru128Id directly)
-        let frames: Vec<Frame> = store
-            .read_sync(Some(&frame1.id), None, Some(crate::store::ZERO_CONTEXT))
-            .collect();
-        assert_eq!(vec![frame2.clone(), frame3.clone()], frames);
+        let missing = store.cas_read(&ssri::Integrity::from("missing")).await;
+        assert!(missing.unwrap_err().is_not_found());
+    }
 
-        // Test with limit
-        let frames: Vec<Frame> = store
-            .read_sync(None, Some(2), Some(crate::store::ZERO_CONTEXT))
-            .collect();
-        assert_eq!(vec![frame1, frame2], frames);
+    fn walk_files(path: &std::path::Path) -> Vec<std::path::PathBuf> {
+        std::fs::read_dir(path)
+            .unwrap()
+            .flat_map(|entry| {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    walk_files(&entry.path())
+                } else {
+                    vec![entry.path()]
+                }
+            })
+            .collect()
+    }
+
+    fn walk_size(path: &std::path::Path) -> u64 {
+        std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                if entry.file_type().unwrap().is_dir() {
+                    walk_size(&entry.path())
+                } else {
+                    entry.metadata().unwrap().len()
+                }
+            })
+            .sum()
     }
 }
 
@@ -426,6 +2651,8 @@ mod tests_ttl {
         assert!(parse_ttl("time:abc").is_err());
         assert!(parse_ttl("head:0").is_err());
         assert!(parse_ttl("unknown").is_err());
+        // errors name the offending input
+        assert!(parse_ttl("head:abc").unwrap_err().contains("'abc'"));
     }
 
     #[test]
@@ -521,15 +2748,25 @@ mod tests_context {
             .unwrap();
 
         // Test head in different contexts
-        assert_eq!(store.head("test", context_id), Some(frame1.clone()));
-        assert_eq!(store.head("test", ZERO_CONTEXT), Some(frame2.clone()));
+        assert_eq!(
+            store.head("test", context_id).unwrap(),
+            Some(frame1.clone())
+        );
+        assert_eq!(
+            store.head("test", ZERO_CONTEXT).unwrap(),
+            Some(frame2.clone())
+        );
 
         // Test reading from specific context
-        let frames: Vec<_> = store.read_sync(None, None, Some(context_id)).collect();
+        let frames: Vec<_> = store
+            .read_sync(ReadOptions::builder().context_id(context_id).build())
+            .collect();
         assert_eq!(frames, vec![frame1.clone()]);
 
         // Test reading from zero context
-        let frames: Vec<_> = store.read_sync(None, None, Some(ZERO_CONTEXT)).collect();
+        let frames: Vec<_> = store
+            .read_sync(ReadOptions::builder().context_id(ZERO_CONTEXT).build())
+            .collect();
         assert_eq!(frames, vec![context_frame.clone(), frame2.clone()]);
     }
 
@@ -568,11 +2805,17 @@ mod tests_context {
             .unwrap();
 
         // Wait for GC
-        store.wait_for_gc().await;
+        store.wait_for_gc().await.unwrap();
 
         // Verify each context keeps its own head:1
-        assert_eq!(store.head("test", context_id), Some(frame2.clone()));
-        assert_eq!(store.head("test", ZERO_CONTEXT), Some(frame4.clone()));
+        assert_eq!(
+            store.head("test", context_id).unwrap(),
+            Some(frame2.clone())
+        );
+        assert_eq!(
+            store.head("test", ZERO_CONTEXT).unwrap(),
+            Some(frame4.clone())
+        );
     }
 
     #[test]
@@ -606,21 +2849,27 @@ mod tests_context {
             .unwrap();
 
         // Test reading from specific contexts
-        let frames: Vec<_> = store.read_sync(None, None, Some(context1_id)).collect();
+        let frames: Vec<_> = store
+            .read_sync(ReadOptions::builder().context_id(context1_id).build())
+            .collect();
         assert_eq!(
             frames,
             vec![frame1.clone(), frame3.clone()],
             "Should only get frames from context1"
         );
 
-        let frames: Vec<_> = store.read_sync(None, None, Some(context2_id)).collect();
+        let frames: Vec<_> = store
+            .read_sync(ReadOptions::builder().context_id(context2_id).build())
+            .collect();
         assert_eq!(
             frames,
             vec![frame2.clone()],
             "Should only get frames from context2"
         );
 
-        let frames: Vec<_> = store.read_sync(None, None, Some(ZERO_CONTEXT)).collect();
+        let frames: Vec<_> = store
+            .read_sync(ReadOptions::builder().context_id(ZERO_CONTEXT).build())
+            .collect();
         assert_eq!(
             frames,
             vec![
@@ -632,7 +2881,7 @@ mod tests_context {
         );
 
         // Test reading all frames using None for context_id
-        let all_frames: Vec<_> = store.read_sync(None, None, None).collect();
+        let all_frames: Vec<_> = store.read_sync(ReadOptions::default()).collect();
         assert_eq!(
             all_frames,
             vec![
@@ -845,6 +3094,40 @@ mod tests_context {
         );
     }
 
+    #[tokio::test]
+    async fn test_read_skips_unreadable_frames() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Store::new(temp_dir.into_path());
+
+        let frame1 = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        let bad = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        let frame3 = store
+            .append(Frame::builder("test", ZERO_CONTEXT).build())
+            .unwrap();
+        store
+            .frame_partition
+            .insert(bad.id.as_bytes(), b"not a frame")
+            .unwrap();
+
+        assert!(store.get(&bad.id).is_err());
+
+        let frames: Vec<_> = store.iter_frames(None, None).collect();
+        assert_eq!(frames, vec![frame1.clone(), frame3.clone()]);
+        let frames: Vec<_> = store.iter_frames(Some(ZERO_CONTEXT), None).collect();
+        assert_eq!(frames, vec![frame1.clone(), frame3.clone()]);
+        let frames: Vec<_> = store.iter_frames_rev(None).collect();
+        assert_eq!(frames, vec![frame3.clone(), frame1.clone()]);
+
+        let mut rx = store.read(ReadOptions::default()).await;
+        assert_eq!(rx.recv().await, Some(frame1));
+        assert_eq!(rx.recv().await, Some(frame3));
+        assert_eq!(rx.recv().await, None);
+    }
+
     #[test]
     fn test_iter_frames_context_scope_with_last_id() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -891,6 +3174,547 @@ mod tests_context {
         let frames_ctx2: Vec<_> = store.iter_frames(Some(ctx2), None).collect();
         assert_eq!(frames_ctx2, vec![ctx2_frame1, ctx2_frame2]);
     }
+
+    #[test]
+    fn test_schema() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().to_path_buf();
+        let tagged = |meta: serde_json::Value| {
+            Frame::builder("point", ZERO_CONTEXT)
+                .meta(meta)
+                .schema("point")
+                .build()
+        };
+
+        {
+            let store = Store::new(path.clone());
+
+            // nothing registered under the name yet
+            assert!(matches!(
+                store.append(tagged(serde_json::json!({"x": 1, "y": 2}))),
+                Err(StoreError::InvalidFrame(_))
+            ));
+
+            store
+                .register_schema(
+                    "point",
+                    serde_json::json!({
+                        "type": "object",
+                        "properties": {"x": {"type": "integer"}, "y": {"type": "integer"}},
+                        "required": ["x", "y"],
+                    }),
+                )
+                .unwrap();
+            assert!(matches!(
+                store.register_schema("broken", serde_json::json!({"type": 42})),
+                Err(StoreError::InvalidFrame(_))
+            ));
+
+            let frame = store
+                .append(tagged(serde_json::json!({"x": 1, "y": 2})))
+                .unwrap();
+            assert_eq!(frame.schema.as_deref(), Some("point"));
+            assert_eq!(store.get(&frame.id).unwrap(), Some(frame));
+
+            let Err(StoreError::InvalidFrame(msg)) =
+                store.append(tagged(serde_json::json!({"x": "one"})))
+            else {
+                panic!("expected the frame to be rejected");
+            };
+            assert!(msg.contains("point"), "{}", msg);
+            assert!(matches!(
+                store.append_batch(vec![
+                    tagged(serde_json::json!({"x": 1, "y": 2})),
+                    tagged(serde_json::json!({})),
+                ]),
+                Err(StoreError::InvalidFrame(_))
+            ));
+            assert_eq!(
+                store
+                    .read_sync(ReadOptions::builder().topic("point").build())
+                    .count(),
+                1
+            );
+        }
+
+        // registrations survive a restart
+        let store = Store::new(path);
+        assert!(store.append(tagged(serde_json::json!({"x": 1}))).is_err());
+        assert!(store
+            .append(tagged(serde_json::json!({"x": 3, "y": 4})))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_append_blocking() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+
+        let with_content = store
+            .append_blocking(Frame::builder("a", ZERO_CONTEXT).build(), Some(b"content"))
+            .unwrap();
+        let without = store
+            .append_blocking(Frame::builder("b", ZERO_CONTEXT).build(), None)
+            .unwrap();
+        assert_eq!(without.hash, None);
+
+        let frames: Vec<_> = store.read_sync(ReadOptions::default()).collect();
+        assert_eq!(frames, vec![with_content.clone(), without]);
+        assert_eq!(
+            store.cas_read_sync(&with_content.hash.unwrap()).unwrap(),
+            b"content"
+        );
+    }
+
+    #[test]
+    fn test_exists() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+
+        let frame = store
+            .append(Frame::builder("a", ZERO_CONTEXT).build())
+            .unwrap();
+        assert!(store.exists(&frame.id).unwrap());
+        assert!(!store.exists(&scru128::new()).unwrap());
+
+        store.remove(&frame.id).unwrap();
+        assert!(!store.exists(&frame.id).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cas_max_bytes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::with_options(
+            temp_dir.path().to_path_buf(),
+            StoreOptions::builder().cas_max_bytes(130).build(),
+        );
+        let blob = |byte: u8| vec![byte; 40];
+
+        // a is the oldest, but a frame refers to it
+        let a = store.cas_insert(blob(b'a')).await.unwrap();
+        store
+            .append(Frame::builder("a", ZERO_CONTEXT).hash(a.clone()).build())
+            .unwrap();
+        let b = store.cas_insert(blob(b'b')).await.unwrap();
+        let c = store.cas_insert(blob(b'c')).await.unwrap();
+        store.wait_for_gc().await.unwrap();
+
+        // Reading b makes c the least recently used
+        store.cas_read(&b).await.unwrap();
+        let d = store.cas_insert(blob(b'd')).await.unwrap();
+        store.wait_for_gc().await.unwrap();
+        assert!(store.cas_exists(&a).await.unwrap());
+        assert!(store.cas_exists(&b).await.unwrap());
+        assert!(!store.cas_exists(&c).await.unwrap());
+        assert!(store.cas_exists(&d).await.unwrap());
+
+        let e = store.cas_insert(blob(b'e')).await.unwrap();
+        store.wait_for_gc().await.unwrap();
+        assert!(store.cas_exists(&a).await.unwrap());
+        assert!(!store.cas_exists(&b).await.unwrap());
+        assert!(store.cas_exists(&d).await.unwrap());
+        assert!(store.cas_exists(&e).await.unwrap());
+    }
+
+    #[test]
+    fn test_append_with_id() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let id = |n: u128| Scru128Id::from_u128(n << 80);
+        let frame = |topic: &str| Frame::builder(topic, ZERO_CONTEXT).build();
+
+        for (n, topic) in [(1, "a"), (3, "b"), (5, "c")] {
+            let appended = store.append_with_id(frame(topic), id(n), false).unwrap();
+            assert_eq!(appended.id, id(n));
+        }
+
+        // ids have to keep increasing, unless told otherwise, and can't be reused
+        assert!(store.append_with_id(frame("x"), id(2), false).is_err());
+        assert!(store.append_with_id(frame("x"), id(3), true).is_err());
+        store.append_with_id(frame("d"), id(2), true).unwrap();
+
+        let ids: Vec<_> = store
+            .read_sync(ReadOptions::default())
+            .map(|frame| (frame.id, frame.topic))
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                (id(1), "a".to_string()),
+                (id(2), "d".to_string()),
+                (id(3), "b".to_string()),
+                (id(5), "c".to_string()),
+            ]
+        );
+
+        // Fresh ids follow supplied ones, even those ahead of the clock
+        let ahead = Scru128Id::from_u128(u128::MAX - 10);
+        store.append_with_id(frame("e"), ahead, false).unwrap();
+        let next = store.append(frame("f")).unwrap();
+        assert!(next.id > ahead);
+
+        // and so do ids after a restart
+        drop(store);
+        let store = Store::new(temp_dir.path().to_path_buf());
+        assert!(store.append(frame("g")).unwrap().id > next.id);
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::open(
+            temp_dir.path().to_path_buf(),
+            StoreOptions::builder()
+                .max_clock_skew(Duration::from_secs(2 * 60 * 60))
+                .build(),
+        )
+        .unwrap();
+        let frame = |topic: &str| Frame::builder(topic, ZERO_CONTEXT).build();
+        let dated = |ahead: Duration| {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap();
+            Scru128Id::from_fields((now + ahead).as_millis() as u64, 0, 0, 0)
+        };
+
+        // Too far ahead of the clock to be a plausible id
+        assert!(store
+            .append_with_id(frame("a"), dated(Duration::from_secs(3 * 60 * 60)), false)
+            .is_err());
+
+        // A head an hour ahead is what the clock going back an hour leaves behind: fresh ids,
+        // smaller than the head, are counted up from it instead
+        let head = store
+            .append_with_id(frame("a"), dated(Duration::from_secs(60 * 60)), false)
+            .unwrap();
+        let mut last = head.clone();
+        for _ in 0..5 {
+            let next = store.append(frame("b")).unwrap();
+            assert!(next.id > last.id);
+            assert!(next.seq > last.seq);
+            assert_eq!(next.id.timestamp(), head.id.timestamp());
+            last = next;
+        }
+
+        let ids: Vec<_> = store
+            .read_sync(ReadOptions::default())
+            .map(|frame| frame.id)
+            .collect();
+        assert_eq!(ids.len(), 6);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_latest_n() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let frames: Vec<_> = (0..10)
+            .map(|i| {
+                let topic = if i % 2 == 0 { "logs.even" } else { "logs.odd" };
+                store
+                    .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(store.latest_n(3, None), frames[7..]);
+        assert_eq!(store.latest_n(100, None), frames);
+        assert_eq!(store.latest_n(0, None), []);
+
+        let odd: Vec<_> = frames
+            .iter()
+            .filter(|frame| frame.topic == "logs.odd")
+            .cloned()
+            .collect();
+        assert_eq!(store.latest_n(2, Some("logs.odd")), odd[3..]);
+        assert_eq!(store.latest_n(4, Some("logs.*")), frames[6..]);
+        assert_eq!(store.latest_n(4, Some("metrics")), []);
+    }
+
+    #[test]
+    fn test_topics() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::with_options(
+            temp_dir.path().to_path_buf(),
+            StoreOptions::builder().tombstones(true).build(),
+        );
+        let mut b_frames = Vec::new();
+        for topic in ["c", "b", "a", "b"] {
+            let frame = store
+                .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                .unwrap();
+            if topic == "b" {
+                b_frames.push(frame);
+            }
+        }
+        store
+            .append(
+                Frame::builder("d", ZERO_CONTEXT)
+                    .ttl(TTL::Ephemeral)
+                    .build(),
+            )
+            .unwrap();
+        store
+            .append_batch(vec![Frame::builder("e", ZERO_CONTEXT).build()])
+            .unwrap();
+        assert_eq!(store.topics(), ["a", "b", "c", "e"]);
+
+        // Tombstones are on a topic of their own
+        store.remove(&b_frames[0].id).unwrap();
+        assert_eq!(store.topics(), ["a", "b", "c", "e", "xs.remove"]);
+        store.remove(&b_frames[1].id).unwrap();
+        // Removing a frame twice doesn't count against its topic again
+        store.remove(&b_frames[1].id).unwrap();
+        assert_eq!(store.topics(), ["a", "c", "e", "xs.remove"]);
+
+        store.clear(Some("e")).unwrap();
+        assert_eq!(store.topics(), ["a", "c", "xs.remove"]);
+
+        // Rebuilt on open
+        drop(store);
+        let store = Store::new(temp_dir.path().to_path_buf());
+        assert_eq!(store.topics(), ["a", "c", "xs.remove"]);
+    }
+
+    #[test]
+    fn test_append_if() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let counter = || Frame::builder("counter", ZERO_CONTEXT).build();
+
+        let first = store.append_if(counter(), None).unwrap();
+        let second = store.append_if(counter(), Some(first.id)).unwrap();
+
+        // A stale head, or expecting an empty topic, conflicts and appends nothing
+        match store.append_if(counter(), Some(first.id)) {
+            Err(StoreError::HeadMismatch { expected, actual }) => {
+                assert_eq!(expected, Some(first.id));
+                assert_eq!(actual, Some(second.id));
+            }
+            other => panic!("expected a head mismatch, got {:?}", other),
+        }
+        assert!(matches!(
+            store.append_if(counter(), None),
+            Err(StoreError::HeadMismatch { .. })
+        ));
+        assert_eq!(store.head("counter", ZERO_CONTEXT).unwrap(), Some(second));
+
+        // Of several writers that all saw the same head, exactly one gets to append
+        let head = store.head("counter", ZERO_CONTEXT).unwrap().map(|f| f.id);
+        let barrier = Arc::new(std::sync::Barrier::new(8));
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    // Unconditional appends to other topics don't get in the way
+                    store
+                        .append(Frame::builder("other", ZERO_CONTEXT).build())
+                        .unwrap();
+                    store.append_if(counter(), head)
+                })
+            })
+            .collect();
+        let results: Vec<_> = writers.into_iter().map(|w| w.join().unwrap()).collect();
+        let appended: Vec<_> = results.iter().filter_map(|r| r.as_ref().ok()).collect();
+        assert_eq!(appended.len(), 1);
+        assert!(results
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .all(|e| matches!(e, StoreError::HeadMismatch { .. })));
+        assert_eq!(
+            store.head("counter", ZERO_CONTEXT).unwrap().as_ref(),
+            appended.first().copied()
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+        for topic in ["a", "b", "a", "c", "a", "b"] {
+            store
+                .append(Frame::builder(topic, ZERO_CONTEXT).build())
+                .unwrap();
+        }
+
+        let folded = store.fold(
+            std::collections::HashMap::new(),
+            |mut counts: std::collections::HashMap<String, usize>, frame| {
+                *counts.entry(frame.topic.clone()).or_default() += 1;
+                counts
+            },
+        );
+
+        let mut counted = std::collections::HashMap::new();
+        for frame in store.read_sync(ReadOptions::default()) {
+            *counted.entry(frame.topic).or_default() += 1;
+        }
+        assert_eq!(folded, counted);
+        assert_eq!(folded["a"], 3);
+    }
+
+    #[test]
+    fn test_range() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+
+        let frames: Vec<_> = (0..5)
+            .map(|_| {
+                store
+                    .append(Frame::builder("a", ZERO_CONTEXT).build())
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(store.range(None, None, None), frames);
+        assert_eq!(store.range(Some(frames[1].id), None, None), frames[2..]);
+        assert_eq!(store.range(None, Some(frames[3].id), None), frames[..3]);
+        assert_eq!(
+            store.range(Some(frames[0].id), Some(frames[4].id), None),
+            frames[1..4]
+        );
+        assert_eq!(
+            store.range(Some(frames[0].id), Some(frames[1].id), None),
+            vec![]
+        );
+        assert_eq!(store.range(None, None, Some(2)), frames[..2]);
+        assert_eq!(store.range(Some(frames[2].id), None, Some(10)), frames[3..]);
+    }
+
+    #[test]
+    fn test_flush() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().to_path_buf();
+
+        let seq = {
+            let store = Store::new(path.clone());
+            store
+                .append(Frame::builder("a", ZERO_CONTEXT).build())
+                .unwrap();
+            let ephemeral = store
+                .append(
+                    Frame::builder("a", ZERO_CONTEXT)
+                        .ttl(TTL::Ephemeral)
+                        .build(),
+                )
+                .unwrap();
+            store.flush().unwrap();
+            ephemeral.seq
+        };
+
+        // the ephemeral frame's seq goes to the next stored frame
+        let store = Store::new(path);
+        let frame = store
+            .append(Frame::builder("a", ZERO_CONTEXT).build())
+            .unwrap();
+        assert_eq!(frame.seq, seq);
+    }
+
+    #[test]
+    fn test_bincode_codec() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().to_path_buf();
+        let options = StoreOptions::builder().codec(Codec::Bincode).build();
+
+        let frames = {
+            let store = Store::with_options(path.clone(), options.clone());
+            store
+                .register_schema("any", serde_json::json!({"type": "object"}))
+                .unwrap();
+            let hash = store.cas_insert_sync("content").unwrap();
+
+            let mut frames: Vec<_> = (0..5_000)
+                .map(|n| {
+                    store
+                        .append(
+                            Frame::builder("bulk", ZERO_CONTEXT)
+                                .meta(serde_json::json!({"n": n, "nested": {"big": u64::MAX}}))
+                                .build(),
+                        )
+                        .unwrap()
+                })
+                .collect();
+
+            frames.push(
+                store
+                    .append(
+                        Frame::builder("other", ZERO_CONTEXT)
+                            .hash(hash)
+                            .ttl(TTL::Time(Duration::from_secs(3600)))
+                            .meta(serde_json::json!({}))
+                            .schema("any")
+                            .build(),
+                    )
+                    .unwrap(),
+            );
+
+            let read: Vec<_> = store
+                .read_sync(ReadOptions::builder().context_id(ZERO_CONTEXT).build())
+                .filter(|frame| frame.topic != "xs.schema")
+                .collect();
+            assert_eq!(read, frames);
+            frames
+        };
+
+        // the codec is recorded with the store
+        let Err(StoreError::CodecMismatch { stored, requested }) =
+            Store::open(path.clone(), StoreOptions::default())
+        else {
+            panic!("expected a codec mismatch");
+        };
+        assert_eq!((stored, requested), (Codec::Bincode, Codec::Json));
+
+        let store = Store::with_options(path, options);
+        let last = frames.last().unwrap();
+        assert_eq!(store.get(&last.id).unwrap().as_ref(), Some(last));
+        assert_eq!(
+            store.head("bulk", ZERO_CONTEXT).unwrap(),
+            frames.get(4_999).cloned()
+        );
+    }
+
+    #[test]
+    fn test_open_unknown_codec() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().to_path_buf();
+
+        let store = Store::new(path.clone());
+        store.meta_partition.insert(CODEC_KEY, "zstd").unwrap();
+        drop(store);
+
+        let Err(StoreError::UnknownCodec(codec)) = Store::open(path, StoreOptions::default())
+        else {
+            panic!("expected an unknown codec");
+        };
+        assert_eq!(codec, "zstd");
+    }
+
+    #[test]
+    fn test_info() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Store::new(temp_dir.path().to_path_buf());
+
+        let info = store.info().unwrap();
+        assert_eq!(info.path, temp_dir.path());
+        assert_eq!(info.first_id, None);
+        assert_eq!(info.last_id, None);
+        assert_eq!(info.frame_count, 0);
+
+        let first = store
+            .append(Frame::builder("a", ZERO_CONTEXT).build())
+            .unwrap();
+        let last = store
+            .append(Frame::builder("b", ZERO_CONTEXT).build())
+            .unwrap();
+
+        let info = store.info().unwrap();
+        assert_eq!(info.first_id, Some(first.id));
+        assert_eq!(info.last_id, Some(last.id));
+        assert_eq!(info.frame_count, 2);
+        assert!(info.started_at <= std::time::SystemTime::now());
+    }
 }
 
 mod tests_ttl_expire {
@@ -941,8 +3765,8 @@ mod tests_ttl_expire {
         );
 
         // Assert the underlying partition has been updated
-        store.wait_for_gc().await;
-        assert_eq!(store.get(&expiring_frame.id), None);
+        store.wait_for_gc().await.unwrap();
+        assert_eq!(store.get(&expiring_frame.id).unwrap(), None);
     }
 
     #[tokio::test]
@@ -998,9 +3822,11 @@ mod tests_ttl_expire {
             .unwrap();
 
         // Read all frames and assert exact expected set
-        store.wait_for_gc().await;
+        store.wait_for_gc().await.unwrap();
         // Use read_sync with explicit ZERO_CONTEXT to verify frames
-        let frames: Vec<_> = store.read_sync(None, None, Some(ZERO_CONTEXT)).collect();
+        let frames: Vec<_> = store
+            .read_sync(ReadOptions::builder().context_id(ZERO_CONTEXT).build())
+            .collect();
 
         assert_eq!(frames, vec![frame3, frame4, other_frame]);
     }
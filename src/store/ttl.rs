@@ -75,22 +75,31 @@ pub fn parse_ttl(s: &str) -> Result<TTL, String> {
         "ephemeral" => Ok(TTL::Ephemeral),
         _ if s.starts_with("time:") => {
             let duration_str = &s[5..];
-            let duration = duration_str
-                .parse::<u64>()
-                .map_err(|_| "Invalid duration for 'time' TTL".to_string())?;
+            let duration = duration_str.parse::<u64>().map_err(|_| {
+                format!(
+                    "Invalid duration for 'time' TTL: '{}' (expected whole milliseconds, e.g. time:3600000)",
+                    duration_str
+                )
+            })?;
             Ok(TTL::Time(Duration::from_millis(duration)))
         }
         _ if s.starts_with("head:") => {
             let n_str = &s[5..];
-            let n = n_str
-                .parse::<u32>()
-                .map_err(|_| "Invalid 'n' value for 'head' TTL".to_string())?;
+            let n = n_str.parse::<u32>().map_err(|_| {
+                format!(
+                    "Invalid 'n' value for 'head' TTL: '{}' (expected a whole number, e.g. head:5)",
+                    n_str
+                )
+            })?;
             if n < 1 {
                 Err("'n' must be >= 1 for 'head' TTL".to_string())
             } else {
                 Ok(TTL::Head(n))
             }
         }
-        _ => Err("Invalid TTL format".to_string()),
+        _ => Err(format!(
+            "Invalid TTL format: '{}' (expected 'forever', 'ephemeral', 'time:<milliseconds>', or 'head:<n>')",
+            s
+        )),
     }
 }
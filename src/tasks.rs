@@ -346,7 +346,7 @@ mod tests {
 
         {
             let store = store.clone();
-            let _ = tokio::spawn(async move {
+            tokio::spawn(async move {
                 serve(store, engine).await.unwrap();
             });
         }
@@ -398,7 +398,7 @@ mod tests {
 
         {
             let store = store.clone();
-            let _ = tokio::spawn(async move {
+            tokio::spawn(async move {
                 serve(store, engine).await.unwrap();
             });
         }
@@ -481,7 +481,7 @@ mod tests {
 
         {
             let store = store.clone();
-            let _ = tokio::spawn(async move {
+            tokio::spawn(async move {
                 serve(store, engine).await.unwrap();
             });
         }
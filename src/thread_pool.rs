@@ -0,0 +1,340 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+
+use tracing::Span;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+enum Message {
+    Run(Job),
+    // Sent to shrink the pool: whichever worker receives it exits
+    Exit,
+}
+
+/// A job rejected by [`ThreadPool::try_execute`], handed back to the caller.
+pub enum TryExecuteError<F> {
+    /// All workers are busy.
+    Busy(F),
+    /// The pool has no workers.
+    ShutDown(F),
+}
+
+impl<F> TryExecuteError<F> {
+    pub fn into_inner(self) -> F {
+        match self {
+            TryExecuteError::Busy(f) | TryExecuteError::ShutDown(f) => f,
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for TryExecuteError<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TryExecuteError::Busy(_) => write!(f, "Busy(..)"),
+            TryExecuteError::ShutDown(_) => write!(f, "ShutDown(..)"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ThreadPool {
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+    size: Arc<Mutex<usize>>,
+    active_count: Arc<AtomicUsize>,
+    peak_active_count: Arc<AtomicUsize>,
+    completion_pair: Arc<(Mutex<()>, Condvar)>,
+}
+
+impl ThreadPool {
+    pub fn new(size: usize) -> Self {
+        let (tx, rx) = bounded::<Message>(0);
+        let pool = Self {
+            tx,
+            rx,
+            size: Arc::new(Mutex::new(size)),
+            active_count: Arc::new(AtomicUsize::new(0)),
+            peak_active_count: Arc::new(AtomicUsize::new(0)),
+            completion_pair: Arc::new((Mutex::new(()), Condvar::new())),
+        };
+
+        for _ in 0..size {
+            pool.spawn_worker();
+        }
+
+        pool
+    }
+
+    fn spawn_worker(&self) {
+        let rx = self.rx.clone();
+        let active_count = self.active_count.clone();
+        let completion_pair = self.completion_pair.clone();
+
+        thread::spawn(move || {
+            while let Ok(Message::Run(job)) = rx.recv() {
+                // A panicking job shouldn't take the worker, or the accounting, down with it
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                finish_job(&active_count, &completion_pair);
+            }
+        });
+    }
+
+    // Jobs are counted before they're handed to a worker, so wait_for_completion can't miss
+    // one that's been handed off but not yet picked up
+    fn start_job(&self) {
+        let active = self.active_count.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_active_count.fetch_max(active, Ordering::SeqCst);
+    }
+
+    pub fn size(&self) -> usize {
+        *self.size.lock().unwrap()
+    }
+
+    /// The number of jobs submitted that haven't finished yet, including any still being handed
+    /// to a worker.
+    pub fn active_count(&self) -> usize {
+        self.active_count.load(Ordering::SeqCst)
+    }
+
+    /// The highest `active_count()` seen. If this exceeds `size()`, callers of `execute` have been
+    /// blocking waiting for a free worker.
+    pub fn peak_active_count(&self) -> usize {
+        self.peak_active_count.load(Ordering::SeqCst)
+    }
+
+    /// Grows or shrinks the pool to `n` workers. Shrinking blocks until enough workers are free
+    /// to each take an exit message; their threads wind down afterwards, without being joined.
+    pub fn set_size(&self, n: usize) {
+        let mut size = self.size.lock().unwrap();
+        for _ in *size..n {
+            self.spawn_worker();
+        }
+        for _ in n..*size {
+            self.tx.send(Message::Exit).unwrap();
+        }
+        *size = n;
+    }
+
+    /// Runs `f` on the pool, blocking until a worker is free to take it.
+    pub fn execute<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let span = Span::current();
+        let job = Box::new(move || {
+            let _entered = span.enter();
+            f();
+        });
+        self.start_job();
+        if self.tx.send(Message::Run(job)).is_err() {
+            finish_job(&self.active_count, &self.completion_pair);
+            panic!("thread pool has no workers");
+        }
+    }
+
+    /// Runs `f` on the pool if a worker is free to take it right away; otherwise hands `f` back.
+    pub fn try_execute<F>(&self, f: F) -> Result<(), TryExecuteError<F>>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if self.size() == 0 {
+            return Err(TryExecuteError::ShutDown(f));
+        }
+
+        // Boxing erases F, so keep it somewhere we can take it back from if the send fails
+        let slot = Arc::new(Mutex::new(Some(f)));
+        let span = Span::current();
+        let job = {
+            let slot = slot.clone();
+            Box::new(move || {
+                let _entered = span.enter();
+                if let Some(f) = slot.lock().unwrap().take() {
+                    f();
+                }
+            })
+        };
+
+        self.start_job();
+        match self.tx.try_send(Message::Run(job)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                finish_job(&self.active_count, &self.completion_pair);
+                let f = slot.lock().unwrap().take().unwrap();
+                match e {
+                    TrySendError::Full(_) => Err(TryExecuteError::Busy(f)),
+                    TrySendError::Disconnected(_) => Err(TryExecuteError::ShutDown(f)),
+                }
+            }
+        }
+    }
+
+    /// Runs `f` on the pool and returns a receiver for its result. If `f` panics, the receiver
+    /// yields the panic payload.
+    pub fn execute_with_result<T, F>(&self, f: F) -> Receiver<thread::Result<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (tx, rx) = bounded(1);
+        self.execute(move || {
+            let _ = tx.send(panic::catch_unwind(AssertUnwindSafe(f)));
+        });
+        rx
+    }
+
+    pub fn wait_for_completion(&self) {
+        let (lock, cvar) = &*self.completion_pair;
+        let mut guard = lock.lock().unwrap();
+        while self.active_count.load(Ordering::SeqCst) > 0 {
+            guard = cvar.wait(guard).unwrap();
+        }
+    }
+}
+
+fn finish_job(active_count: &AtomicUsize, completion_pair: &(Mutex<()>, Condvar)) {
+    if active_count.fetch_sub(1, Ordering::SeqCst) == 1 {
+        let (lock, cvar) = completion_pair;
+        let _guard = lock.lock().unwrap();
+        cvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execute_with_result() {
+        let pool = ThreadPool::new(2);
+
+        let rx = pool.execute_with_result(|| (1..=10).sum::<u32>());
+        assert_eq!(rx.recv().unwrap().unwrap(), 55);
+
+        let rx = pool.execute_with_result(|| -> u32 { panic!("boom") });
+        assert!(rx.recv().unwrap().is_err());
+
+        // the pool is still usable after a job panics
+        let rx = pool.execute_with_result(|| "ok");
+        assert_eq!(rx.recv().unwrap().unwrap(), "ok");
+
+        pool.wait_for_completion();
+        assert_eq!(pool.active_count(), 0);
+    }
+
+    #[test]
+    fn test_active_count() {
+        let pool = ThreadPool::new(3);
+        assert_eq!(pool.active_count(), 0);
+
+        let (started_tx, started_rx) = bounded(0);
+        let (release_tx, release_rx) = bounded::<()>(0);
+        for _ in 0..2 {
+            let started_tx = started_tx.clone();
+            let release_rx = release_rx.clone();
+            pool.execute(move || {
+                started_tx.send(()).unwrap();
+                let _ = release_rx.recv();
+            });
+        }
+        started_rx.recv().unwrap();
+        started_rx.recv().unwrap();
+
+        assert_eq!(pool.active_count(), 2);
+        assert_eq!(pool.peak_active_count(), 2);
+
+        drop(release_tx);
+        pool.wait_for_completion();
+        assert_eq!(pool.active_count(), 0);
+        assert_eq!(pool.peak_active_count(), 2);
+    }
+
+    #[test]
+    fn test_wait_for_completion() {
+        use std::sync::atomic::AtomicBool;
+
+        let pool = ThreadPool::new(2);
+        for _ in 0..1000 {
+            let done = Arc::new(AtomicBool::new(false));
+            let job_done = done.clone();
+            pool.execute(move || job_done.store(true, Ordering::SeqCst));
+            // the job has been handed off, but may not have started yet
+            pool.wait_for_completion();
+            assert!(done.load(Ordering::SeqCst));
+        }
+    }
+
+    #[test]
+    fn test_set_size() {
+        use std::sync::Barrier;
+
+        let pool = ThreadPool::new(1);
+        pool.set_size(4);
+        assert_eq!(pool.size(), 4);
+
+        // 4 jobs that can only complete once all 4 are running at the same time
+        let barrier = Arc::new(Barrier::new(4));
+        let results: Vec<_> = (0..4)
+            .map(|_| {
+                let barrier = barrier.clone();
+                pool.execute_with_result(move || {
+                    barrier.wait();
+                })
+            })
+            .collect();
+        for rx in results {
+            rx.recv_timeout(std::time::Duration::from_secs(5))
+                .unwrap()
+                .unwrap();
+        }
+
+        pool.set_size(1);
+        assert_eq!(pool.size(), 1);
+        let rx = pool.execute_with_result(|| 42);
+        assert_eq!(rx.recv().unwrap().unwrap(), 42);
+    }
+
+    #[test]
+    fn test_try_execute() {
+        let pool = ThreadPool::new(1);
+
+        // give the worker time to start waiting for work
+        let mut attempts = 0;
+        while pool.try_execute(|| {}).is_err() {
+            attempts += 1;
+            assert!(attempts < 100);
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        pool.wait_for_completion();
+
+        // saturate the pool
+        let (started_tx, started_rx) = bounded(0);
+        let (release_tx, release_rx) = bounded::<()>(0);
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+            let _ = release_rx.recv();
+        });
+        started_rx.recv().unwrap();
+
+        let (result_tx, result_rx) = bounded(1);
+        let rejected = pool.try_execute(move || result_tx.send(42).unwrap());
+        let Err(TryExecuteError::Busy(job)) = rejected else {
+            panic!("expected Busy, got {:?}", rejected);
+        };
+        // the job we get back is the one we submitted
+        job();
+        assert_eq!(result_rx.recv().unwrap(), 42);
+
+        drop(release_tx);
+        pool.wait_for_completion();
+
+        pool.set_size(0);
+        assert!(matches!(
+            pool.try_execute(|| {}),
+            Err(TryExecuteError::ShutDown(_))
+        ));
+    }
+}
@@ -369,7 +369,9 @@ pub fn init() {
         monitor_subscriber.monitor_long_spans();
     });
 
-    // Register the subscriber directly
-    let registry = Registry::default().with(subscriber);
+    // Register the subscriber directly. Trace level spans, like a frame's append and delivery,
+    // are too frequent to print
+    let registry = Registry::default()
+        .with(subscriber.with_filter(tracing_subscriber::filter::LevelFilter::DEBUG));
     tracing::subscriber::set_global_default(registry).expect("setting tracing default failed");
 }
@@ -0,0 +1,7 @@
+mod serve;
+#[cfg(test)]
+mod tests;
+mod transform;
+
+pub use serve::serve;
+pub use transform::Transform;
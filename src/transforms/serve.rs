@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::nu;
+use crate::nu::commands;
+use crate::store::{FollowOption, Frame, ReadOptions, Store};
+use crate::thread_pool::ThreadPool;
+use crate::transforms::Transform;
+
+async fn start_transform(
+    frame: &Frame,
+    store: &Store,
+    engine: &nu::Engine,
+    pool: &ThreadPool,
+) -> Option<CancellationToken> {
+    match Transform::from_frame(frame, store, engine.clone()).await {
+        Ok(transform) => {
+            let cancel = CancellationToken::new();
+            transform.spawn(store.clone(), pool.clone(), cancel.clone());
+            Some(cancel)
+        }
+        Err(err) => {
+            let _ = store.append(
+                Frame::builder("xs.error", frame.context_id)
+                    .meta(serde_json::json!({
+                        "transform_id": frame.id.to_string(),
+                        "error": err.to_string(),
+                    }))
+                    .build(),
+            );
+            None
+        }
+    }
+}
+
+pub async fn serve(
+    store: Store,
+    mut engine: nu::Engine,
+    pool: ThreadPool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    engine.add_commands(vec![
+        Box::new(commands::cas_command::CasCommand::new(store.clone())),
+        Box::new(commands::cas_read_command::CasReadCommand::new(
+            store.clone(),
+        )),
+        Box::new(commands::get_command::GetCommand::new(store.clone())),
+        Box::new(commands::ttl_parse_command::TtlParseCommand::new()),
+    ])?;
+
+    let options = ReadOptions::builder().follow(FollowOption::On).build();
+    let mut recver = store.read(options).await;
+
+    // Only the latest registration for each name and context is kept
+    let mut registrations = HashMap::new();
+    while let Some(frame) = recver.recv().await {
+        if frame.topic == "xs.threshold" {
+            break;
+        }
+        if let Some(name) = frame.topic.strip_suffix(".transform") {
+            registrations.insert((frame.context_id, name.to_string()), frame);
+        }
+    }
+
+    let mut ordered: Vec<_> = registrations.into_iter().collect();
+    ordered.sort_by_key(|(_, frame)| frame.id);
+
+    // Transforms stop when they're replaced, or when this task does
+    let mut running = HashMap::new();
+    for (key, frame) in ordered {
+        if let Some(cancel) = start_transform(&frame, &store, &engine, &pool).await {
+            running.insert(key, cancel.drop_guard());
+        }
+    }
+
+    while let Some(frame) = recver.recv().await {
+        if let Some(name) = frame.topic.strip_suffix(".transform") {
+            let key = (frame.context_id, name.to_string());
+            // A new registration replaces the running transform
+            running.remove(&key);
+            if let Some(cancel) = start_transform(&frame, &store, &engine, &pool).await {
+                running.insert(key, cancel.drop_guard());
+            }
+        }
+    }
+
+    Ok(())
+}
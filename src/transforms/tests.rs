@@ -0,0 +1,185 @@
+use tempfile::TempDir;
+
+use crate::nu;
+use crate::store::{FollowOption, Frame, ReadOptions, Store, StoreError, ZERO_CONTEXT};
+use crate::thread_pool::ThreadPool;
+use crate::transforms::serve;
+
+async fn setup_test_environment() -> (Store, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let store = Store::new(temp_dir.path().to_path_buf());
+    let engine = nu::Engine::new().unwrap();
+
+    {
+        let store = store.clone();
+        tokio::spawn(async move {
+            serve(store, engine, ThreadPool::new(2)).await.unwrap();
+        });
+    }
+
+    (store, temp_dir)
+}
+
+async fn register(store: &Store, name: &str, script: &str) -> Frame {
+    store
+        .append(
+            Frame::builder(format!("{}.transform", name), ZERO_CONTEXT)
+                .hash(store.cas_insert(script).await.unwrap())
+                .build(),
+        )
+        .unwrap()
+}
+
+async fn append(store: &Store, topic: &str, content: &str) -> Frame {
+    store
+        .append(
+            Frame::builder(topic, ZERO_CONTEXT)
+                .hash(store.cas_insert(content).await.unwrap())
+                .build(),
+        )
+        .unwrap()
+}
+
+async fn next_on(recver: &mut tokio::sync::mpsc::Receiver<Frame>, topic: &str) -> Frame {
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let frame = recver.recv().await.unwrap();
+            if frame.topic == topic {
+                return frame;
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out waiting for a frame on {}", topic))
+}
+
+#[tokio::test]
+async fn test_transform_uppercase() {
+    let (store, _temp_dir) = setup_test_environment().await;
+    let mut recver = store
+        .read(ReadOptions::builder().follow(FollowOption::On).build())
+        .await;
+
+    let transform = register(
+        &store,
+        "upper",
+        r#"{
+            source: "a"
+            target: "b"
+            process: {|frame| .cas $frame.hash | str upcase }
+        }"#,
+    )
+    .await;
+
+    let first = append(&store, "a", "hello").await;
+    let _ = append(&store, "other", "ignored").await;
+    let second = append(&store, "a", "world").await;
+
+    for (source, expected) in [(first, "HELLO"), (second, "WORLD")] {
+        let frame = next_on(&mut recver, "b").await;
+        let meta = frame.meta.as_ref().unwrap();
+        assert_eq!(meta["transform_id"], transform.id.to_string());
+        assert_eq!(meta["frame_id"], source.id.to_string());
+        let content = store.cas_read(&frame.hash.unwrap()).await.unwrap();
+        assert_eq!(content, expected.as_bytes());
+    }
+}
+
+#[tokio::test]
+async fn test_transform_error_keeps_running() {
+    let (store, _temp_dir) = setup_test_environment().await;
+    let mut recver = store
+        .read(ReadOptions::builder().follow(FollowOption::On).build())
+        .await;
+
+    let transform = register(
+        &store,
+        "parse",
+        r#"{
+            source: "a"
+            target: "b"
+            process: {|frame| .cas $frame.hash | into int }
+        }"#,
+    )
+    .await;
+
+    let bad = append(&store, "a", "nope").await;
+    let error = next_on(&mut recver, "xs.error").await;
+    let meta = error.meta.as_ref().unwrap();
+    assert_eq!(meta["transform_id"], transform.id.to_string());
+    assert_eq!(meta["frame_id"], bad.id.to_string());
+
+    // the transform carries on with the next frame
+    let _ = append(&store, "a", "42").await;
+    let frame = next_on(&mut recver, "b").await;
+    let content = store.cas_read(&frame.hash.unwrap()).await.unwrap();
+    assert_eq!(content, b"42");
+}
+
+#[tokio::test]
+async fn test_transform_resumes_from_cursor() {
+    let temp_dir = TempDir::new().unwrap();
+    let script = r#"{
+        source: "a"
+        target: "b"
+        process: {|frame| .cas $frame.hash | str upcase }
+    }"#;
+
+    {
+        let store = Store::new(temp_dir.path().to_path_buf());
+        let engine = nu::Engine::new().unwrap();
+        let mut recver = store
+            .read(ReadOptions::builder().follow(FollowOption::On).build())
+            .await;
+        let task = {
+            let store = store.clone();
+            tokio::spawn(async move { serve(store, engine, ThreadPool::new(1)).await })
+        };
+
+        register(&store, "upper", script).await;
+        let _ = append(&store, "a", "one").await;
+        let _ = next_on(&mut recver, "upper.cursor").await;
+        task.abort();
+        let _ = task.await;
+    }
+
+    // restart: only the frame appended since should be transformed. The stopped transform lets
+    // go of the store in its own time
+    let store = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            match Store::open(temp_dir.path().to_path_buf(), Default::default()) {
+                Ok(store) => return store,
+                Err(StoreError::AlreadyLocked { .. }) => {
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await
+                }
+                Err(e) => panic!("{}", e),
+            }
+        }
+    })
+    .await
+    .unwrap();
+    let mut recver = store
+        .read(
+            ReadOptions::builder()
+                .follow(FollowOption::On)
+                .tail(true)
+                .build(),
+        )
+        .await;
+    let _ = append(&store, "a", "two").await;
+    {
+        let store = store.clone();
+        let engine = nu::Engine::new().unwrap();
+        tokio::spawn(async move { serve(store, engine, ThreadPool::new(1)).await });
+    }
+
+    let frame = next_on(&mut recver, "b").await;
+    let content = store.cas_read(&frame.hash.unwrap()).await.unwrap();
+    assert_eq!(content, b"TWO");
+    assert_eq!(
+        store
+            .read_sync(ReadOptions::builder().topic("b").build())
+            .count(),
+        2
+    );
+}
@@ -0,0 +1,293 @@
+use std::sync::Arc;
+
+use nu_engine::eval_block_with_early_return;
+use nu_parser::parse;
+use nu_protocol::debugger::WithoutDebug;
+use nu_protocol::engine::{Closure, Stack, StateWorkingSet};
+use nu_protocol::{format_shell_error, PipelineData, ShellError, Span, Value};
+
+use scru128::Scru128Id;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::error::Error;
+use crate::nu;
+use crate::nu::util::value_to_json;
+use crate::store::{topic_matches, FollowOption, Frame, ReadOptions, Store, TTL};
+use crate::thread_pool::ThreadPool;
+
+/// Derives one topic from another: each frame appended to `source` is passed to the `process`
+/// closure, and whatever it returns is appended to `target`. Registered by appending a
+/// `<name>.transform` frame whose content is a script returning
+/// `{source: "a", target: "b", process: {|frame| ...}}`.
+///
+/// After each source frame, the transform records how far it got on `<name>.cursor`, so it picks
+/// up where it left off after a restart.
+#[derive(Clone)]
+pub struct Transform {
+    pub id: Scru128Id,
+    pub context_id: Scru128Id,
+    pub name: String,
+    pub source: String,
+    pub target: String,
+    engine: Arc<nu::Engine>,
+    process: Closure,
+}
+
+impl Transform {
+    pub fn new(
+        id: Scru128Id,
+        context_id: Scru128Id,
+        name: String,
+        mut engine: nu::Engine,
+        script: &str,
+    ) -> Result<Self, Error> {
+        let (source, target, process) = parse_transform_script(&mut engine, script)?;
+
+        let block = engine.state.get_block(process.block_id);
+        if block.signature.required_positional.len() != 1 {
+            return Err(format!(
+                "Closure must accept exactly one frame argument, found {}",
+                block.signature.required_positional.len()
+            )
+            .into());
+        }
+
+        Ok(Self {
+            id,
+            context_id,
+            name,
+            source,
+            target,
+            engine: Arc::new(engine),
+            process,
+        })
+    }
+
+    pub async fn from_frame(
+        frame: &Frame,
+        store: &Store,
+        engine: nu::Engine,
+    ) -> Result<Self, Error> {
+        let name = frame
+            .topic
+            .strip_suffix(".transform")
+            .ok_or("Frame topic must end with .transform")?;
+
+        let hash = frame.hash.as_ref().ok_or("Missing hash field")?;
+        let script = String::from_utf8(store.cas_read(hash).await?)?;
+
+        Transform::new(
+            frame.id,
+            frame.context_id,
+            name.to_string(),
+            engine,
+            &script,
+        )
+    }
+
+    fn cursor_topic(&self) -> String {
+        format!("{}.cursor", self.name)
+    }
+
+    /// The last source frame this transform finished with, if it's run before.
+    fn cursor(&self, store: &Store) -> Option<Scru128Id> {
+        let frame = store
+            .head(&self.cursor_topic(), self.context_id)
+            .ok()
+            .flatten()?;
+        let meta = frame.meta?;
+        if meta.get("transform_id")?.as_str()? != self.id.to_string() {
+            return None;
+        }
+        meta.get("frame_id")?.as_str()?.parse().ok()
+    }
+
+    /// Evaluates the closure against `frame` on `pool`.
+    async fn eval(&self, pool: &ThreadPool, frame: &Frame) -> Result<Value, Error> {
+        let engine = self.engine.clone();
+        let process = self.process.clone();
+        let frame = frame.clone();
+        let pool = pool.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            pool.execute_with_result(move || eval_closure(&engine, &process, &frame))
+                .recv()
+        })
+        .await?;
+
+        match result {
+            Ok(Ok(output)) => output,
+            Ok(Err(_)) => Err("Transform panicked".into()),
+            Err(_) => Err("Thread pool has shut down".into()),
+        }
+    }
+
+    async fn process_frame(
+        &self,
+        pool: &ThreadPool,
+        frame: &Frame,
+        store: &Store,
+    ) -> Result<(), Error> {
+        let value = self.eval(pool, frame).await?;
+
+        let content = match value {
+            Value::Nothing { .. } => return Ok(()),
+            Value::String { val, .. } => val,
+            value => value_to_json(&value).to_string(),
+        };
+
+        let hash = store.cas_insert(&content).await?;
+        store.append(
+            Frame::builder(&self.target, self.context_id)
+                .hash(hash)
+                .meta(serde_json::json!({
+                    "transform_id": self.id.to_string(),
+                    "frame_id": frame.id.to_string(),
+                }))
+                .build(),
+        )?;
+
+        Ok(())
+    }
+
+    async fn serve(&self, store: &Store, pool: &ThreadPool, cancel: &CancellationToken) {
+        let options = ReadOptions::builder()
+            .follow(FollowOption::On)
+            .topic(&self.source)
+            .context_id(self.context_id)
+            .maybe_last_id(self.cursor(store))
+            .build();
+        let mut recver = store.read(options).await;
+
+        loop {
+            let frame = tokio::select! {
+                frame = recver.recv() => match frame {
+                    Some(frame) => frame,
+                    None => break,
+                },
+                _ = cancel.cancelled() => break,
+            };
+
+            // The read's control frames, such as xs.threshold, aren't filtered by topic
+            if !topic_matches(&self.source, &frame.topic) {
+                continue;
+            }
+
+            // Skip frames this transform produced itself, in case the target matches the source
+            if frame
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.get("transform_id"))
+                .and_then(|id| id.as_str())
+                .is_some_and(|id| id == self.id.to_string())
+            {
+                continue;
+            }
+
+            // Errors are recorded rather than stopping the transform
+            if let Err(err) = self.process_frame(pool, &frame, store).await {
+                let _ = store.append(
+                    Frame::builder("xs.error", self.context_id)
+                        .meta(serde_json::json!({
+                            "transform_id": self.id.to_string(),
+                            "frame_id": frame.id.to_string(),
+                            "error": err.to_string(),
+                        }))
+                        .build(),
+                );
+            }
+
+            let _ = store.append(
+                Frame::builder(self.cursor_topic(), self.context_id)
+                    .ttl(TTL::Head(1))
+                    .meta(serde_json::json!({
+                        "transform_id": self.id.to_string(),
+                        "frame_id": frame.id.to_string(),
+                    }))
+                    .build(),
+            );
+        }
+    }
+
+    pub fn spawn(&self, store: Store, pool: ThreadPool, cancel: CancellationToken) {
+        let transform = self.clone();
+        tokio::spawn(async move {
+            transform.serve(&store, &pool, &cancel).await;
+        });
+    }
+}
+
+fn eval_closure(engine: &nu::Engine, closure: &Closure, frame: &Frame) -> Result<Value, Error> {
+    let mut stack = Stack::new();
+    let block = engine.state.get_block(closure.block_id);
+
+    let frame_var_id = block.signature.required_positional[0].var_id.unwrap();
+    stack.add_var(frame_var_id, nu::frame_to_value(frame, Span::unknown()));
+
+    eval_block_with_early_return::<WithoutDebug>(
+        &engine.state,
+        &mut stack,
+        block,
+        PipelineData::empty(),
+    )
+    .and_then(|pipeline_data| pipeline_data.into_value(Span::unknown()))
+    .map_err(|err| {
+        let working_set = StateWorkingSet::new(&engine.state);
+        Error::from(format_shell_error(&working_set, &err))
+    })
+}
+
+fn parse_transform_script(
+    engine: &mut nu::Engine,
+    script: &str,
+) -> Result<(String, String, Closure), Error> {
+    let mut working_set = StateWorkingSet::new(&engine.state);
+    let block = parse(&mut working_set, None, script.as_bytes(), false);
+
+    if let Some(err) = working_set.parse_errors.first() {
+        let shell_error = ShellError::GenericError {
+            error: "Parse error".into(),
+            msg: format!("{:?}", err),
+            span: Some(err.span()),
+            help: None,
+            inner: vec![],
+        };
+        return Err(Error::from(format_shell_error(&working_set, &shell_error)));
+    }
+
+    engine.state.merge_delta(working_set.render())?;
+
+    let mut stack = Stack::new();
+    let config = eval_block_with_early_return::<WithoutDebug>(
+        &engine.state,
+        &mut stack,
+        &block,
+        PipelineData::empty(),
+    )
+    .and_then(|pipeline_data| pipeline_data.into_value(Span::unknown()))
+    .map_err(|err| {
+        let working_set = StateWorkingSet::new(&engine.state);
+        Error::from(format_shell_error(&working_set, &err))
+    })?;
+
+    let field = |name: &str| -> Result<String, Error> {
+        config
+            .get_data_by_key(name)
+            .ok_or_else(|| format!("No '{}' field found in transform configuration", name))?
+            .as_str()
+            .map(String::from)
+            .map_err(|_| format!("{} must be a string", name).into())
+    };
+    let source = field("source")?;
+    let target = field("target")?;
+
+    let process = config
+        .get_data_by_key("process")
+        .ok_or("No 'process' field found in transform configuration")?
+        .into_closure()?;
+
+    engine.state.merge_env(&mut stack)?;
+
+    Ok((source, target, process))
+}